@@ -1,27 +1,158 @@
+use std::cmp::Ordering;
 use std::collections::BinaryHeap;
 
 use criterion::{BatchSize, Bencher, black_box, Criterion, criterion_group, criterion_main};
 
 use find_closest_points::{generate_random_points, NUM_OF_NEAREST_NEIGHBOURS};
-use find_closest_points::kd_tree::KdTreeNode;
-use find_closest_points::nearest_neighbour::{find_k_nearest_neighbours, Neighbour};
+use find_closest_points::ball_tree::BallTree;
+use find_closest_points::brute_force::BruteForce;
+use find_closest_points::kd_tree::{KdTree, KdTreeNode};
+use find_closest_points::knn_join::find_k_nearest_neighbours_join;
+use find_closest_points::nearest_neighbour::{find_k_nearest_neighbours, Neighbour, NeighbourSearch};
 use find_closest_points::point::{Dimension, Point};
+use find_closest_points::scalar::{Native, Widened};
 
 fn bench_find_closest_neighbours_kd_tree(b: &mut Bencher) {
     let points = black_box(generate_random_points(10_000_000));
     let tree = black_box(Some(Box::new(KdTreeNode::construct_tree(points))));
     let given_point = black_box(Point::random());
-    b.iter_batched(|| BinaryHeap::<Neighbour>::new(),
-                   |mut heap| black_box(find_k_nearest_neighbours(&tree,
-                                                        &given_point,
-                                                        &Dimension::X, &mut heap, NUM_OF_NEAREST_NEIGHBOURS)),
+    b.iter_batched(BinaryHeap::<Neighbour>::new,
+                   |mut heap| {
+                       find_k_nearest_neighbours(&tree, &given_point, &Dimension::X, &mut heap, NUM_OF_NEAREST_NEIGHBOURS);
+                       black_box(heap)
+                   },
                    BatchSize::SmallInput);
 }
 
+/// The kd-tree search as it was before it compared squared distances, kept to measure the difference.
+fn find_k_nearest_neighbours_by_distance(curr_node: &Option<Box<KdTreeNode>>, given_point: &Point, curr_dimension: &Dimension,
+                                         k_nearest_neighbours: &mut BinaryHeap<Neighbour>, k: usize) {
+    let Some(curr_node) = curr_node else { return; };
+    let curr_point = &curr_node.point;
+    let distance_to_other_bounding_box = (curr_point.get_dimension(curr_dimension) - given_point.get_dimension(curr_dimension)).abs();
+    let (near, far) = match given_point.compare_dimension(curr_point, curr_dimension) {
+        Ordering::Equal | Ordering::Greater => (&curr_node.right, &curr_node.left),
+        Ordering::Less => (&curr_node.left, &curr_node.right),
+    };
+    let is_potential_nearer_neighbour = |distance: f64, heap: &BinaryHeap<Neighbour>| {
+        heap.len() < k || heap.peek().is_some_and(|n| distance < n.distance)
+    };
+    find_k_nearest_neighbours_by_distance(near, given_point, &curr_dimension.turn(), k_nearest_neighbours, k);
+    if is_potential_nearer_neighbour(distance_to_other_bounding_box, k_nearest_neighbours) {
+        find_k_nearest_neighbours_by_distance(far, given_point, &curr_dimension.turn(), k_nearest_neighbours, k);
+    }
+    let curr_distance = given_point.distance_to(curr_point);
+    if is_potential_nearer_neighbour(curr_distance, k_nearest_neighbours) {
+        if k_nearest_neighbours.len() >= k { k_nearest_neighbours.pop(); }
+        k_nearest_neighbours.push(Neighbour { distance: curr_distance, point: curr_point.clone() });
+    }
+}
+
+fn bench_find_closest_neighbours_kd_tree_by_distance(b: &mut Bencher) {
+    let points = black_box(generate_random_points(10_000_000));
+    let tree = black_box(Some(Box::new(KdTreeNode::construct_tree(points))));
+    let given_point = black_box(Point::random());
+    b.iter_batched(BinaryHeap::<Neighbour>::new,
+                   |mut heap| {
+                       find_k_nearest_neighbours_by_distance(&tree, &given_point, &Dimension::X, &mut heap, NUM_OF_NEAREST_NEIGHBOURS);
+                       black_box(heap)
+                   },
+                   BatchSize::SmallInput);
+}
+
+fn bench_find_closest_neighbours_ball_tree(b: &mut Bencher) {
+    let points = black_box(generate_random_points(10_000_000));
+    let tree = black_box(BallTree::construct_tree(points));
+    let given_point = black_box(Point::random());
+    b.iter(|| black_box(tree.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS)));
+}
+
+fn bench_knn_join(b: &mut Bencher) {
+    let tree = black_box(Some(Box::new(KdTreeNode::construct_tree(generate_random_points(1_000_000)))));
+    let queries = black_box(generate_random_points(10_000));
+    b.iter(|| black_box(find_k_nearest_neighbours_join(&queries, &tree, NUM_OF_NEAREST_NEIGHBOURS)));
+}
+
+fn bench_knn_per_point_loop(b: &mut Bencher) {
+    let tree = black_box(Some(Box::new(KdTreeNode::construct_tree(generate_random_points(1_000_000)))));
+    let queries = black_box(generate_random_points(10_000));
+    b.iter(|| {
+        black_box(queries.iter().map(|query| {
+            let mut heap = BinaryHeap::new();
+            find_k_nearest_neighbours(&tree, query, &Dimension::X, &mut heap, NUM_OF_NEAREST_NEIGHBOURS);
+            heap.into_sorted_vec()
+        }).collect::<Vec<Vec<Neighbour>>>())
+    });
+}
+
+fn bench_small_brute_force(b: &mut Bencher) {
+    let index = black_box(BruteForce::construct_index(generate_random_points(100)));
+    let given_point = black_box(Point::random());
+    b.iter(|| black_box(index.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS)));
+}
+
+fn bench_small_sort(b: &mut Bencher) {
+    let points = black_box(generate_random_points(100));
+    let given_point = black_box(Point::random());
+    b.iter(|| {
+        let mut neighbours = points.iter().map(|p| Neighbour { distance: given_point.distance_to(p), point: p.clone() }).collect::<Vec<Neighbour>>();
+        neighbours.sort();
+        neighbours.truncate(NUM_OF_NEAREST_NEIGHBOURS);
+        black_box(neighbours)
+    });
+}
+
+fn bench_small_kd_tree(b: &mut Bencher) {
+    let tree = black_box(KdTreeNode::construct_tree(generate_random_points(100)));
+    let given_point = black_box(Point::random());
+    b.iter(|| black_box(tree.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS)));
+}
+
+fn bench_f64_kd_tree(b: &mut Bencher) {
+    let tree = black_box(KdTree::construct_tree(generate_random_points(1_000_000)));
+    let given_point = black_box(Point::random());
+    b.iter(|| black_box(tree.nearest_with::<Native>(&given_point, NUM_OF_NEAREST_NEIGHBOURS)));
+}
+
+fn bench_f32_kd_tree(b: &mut Bencher) {
+    let tree = black_box(KdTree::construct_tree(generate_random_points(1_000_000).iter().map(Point::cast::<f32>).collect()));
+    let given_point = black_box(Point::<f32>::random());
+    b.iter(|| black_box(tree.nearest_with::<Native>(&given_point, NUM_OF_NEAREST_NEIGHBOURS)));
+}
+
+fn bench_f32_kd_tree_widened(b: &mut Bencher) {
+    let tree = black_box(KdTree::construct_tree(generate_random_points(1_000_000).iter().map(Point::cast::<f32>).collect()));
+    let given_point = black_box(Point::<f32>::random());
+    b.iter(|| black_box(tree.nearest_with::<Widened>(&given_point, NUM_OF_NEAREST_NEIGHBOURS)));
+}
+
+fn scalar_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Find 10 nearest neighbours among 1,000,000 points by scalar type");
+    group.bench_function("f64", bench_f64_kd_tree);
+    group.bench_function("f32", bench_f32_kd_tree);
+    group.bench_function("f32 accumulated in f64", bench_f32_kd_tree_widened);
+}
+
+fn small_index_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Find 10 nearest neighbours among 100 points");
+    group.bench_function("Brute force", bench_small_brute_force);
+    group.bench_function("Sort every point", bench_small_sort);
+    group.bench_function("kd tree", bench_small_kd_tree);
+}
+
+fn knn_join_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("kNN join of 10,000 queries against 1,000,000 points");
+    group.sample_size(10);
+    group.bench_function("Dual-tree join", bench_knn_join);
+    group.bench_function("Per-point loop", bench_knn_per_point_loop);
+}
+
 fn find_closest_points_benchmark(c: &mut Criterion) {
     let mut group = c.benchmark_group("Find closest points");
     group.bench_function("Find 10 nearest neighbours using kd tree", bench_find_closest_neighbours_kd_tree);
+    group.bench_function("Find 10 nearest neighbours using kd tree comparing actual distances", bench_find_closest_neighbours_kd_tree_by_distance);
+    group.bench_function("Find 10 nearest neighbours using ball tree", bench_find_closest_neighbours_ball_tree);
 }
 
-criterion_group!(benches, find_closest_points_benchmark);
+criterion_group!(benches, find_closest_points_benchmark, knn_join_benchmark, small_index_benchmark, scalar_benchmark);
 criterion_main!(benches);
\ No newline at end of file
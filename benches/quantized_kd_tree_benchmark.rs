@@ -0,0 +1,68 @@
+use std::mem::size_of;
+
+use criterion::{black_box, Criterion, criterion_group};
+
+use find_closest_points::{generate_random_points, NUM_OF_NEAREST_NEIGHBOURS};
+use find_closest_points::brute_force::BruteForce;
+use find_closest_points::kd_tree::{KdTree, KdTreeNode};
+use find_closest_points::nearest_neighbour::NeighbourSearch;
+use find_closest_points::point::Point;
+use find_closest_points::quantized_kd_tree::QuantizedKdTree;
+
+const NUM_OF_POINTS: u32 = 1_000_000;
+const NUM_OF_QUERIES: usize = 1000;
+const CANDIDATES: [usize; 4] = [10, 20, 40, 80];
+
+/// Prints the bytes per point of the quantized tree next to the `KdTreeNode` that stores every point.
+fn report_memory_usage() {
+    let tree = QuantizedKdTree::construct_tree(generate_random_points(NUM_OF_POINTS));
+    let kd_tree_bytes = NUM_OF_POINTS as usize * size_of::<KdTreeNode>();
+    println!("Memory for {NUM_OF_POINTS} points:");
+    println!("  kd tree nodes:             {kd_tree_bytes} bytes ({} per point)", size_of::<KdTreeNode>());
+    println!("  quantized kd tree:         {} bytes ({:.2} per point)",
+             tree.memory_usage(), tree.memory_usage() as f64 / NUM_OF_POINTS as f64);
+    println!("  with full-precision points {} bytes", tree.memory_usage() + tree.len() * size_of::<Point>());
+}
+
+/// Prints the share of the true 10 nearest neighbours found when re-ranking different numbers of candidates.
+fn report_recall() {
+    let points = generate_random_points(NUM_OF_POINTS);
+    let tree = QuantizedKdTree::construct_tree(points.clone());
+    let brute_force = BruteForce::construct_index(points);
+    let queries = (0..NUM_OF_QUERIES).map(|_| Point::random()).collect::<Vec<Point>>();
+    let expected = queries.iter().map(|given_point| brute_force.nearest(given_point, NUM_OF_NEAREST_NEIGHBOURS)).collect::<Vec<_>>();
+    println!("Recall of 10 nearest neighbours over {NUM_OF_QUERIES} queries:");
+    for candidates in CANDIDATES {
+        let found = queries.iter().zip(&expected)
+            .map(|(given_point, expected)| {
+                tree.nearest_with_candidates(given_point, NUM_OF_NEAREST_NEIGHBOURS, candidates).iter()
+                    .filter(|n| expected.iter().any(|e| e.point == n.point))
+                    .count()
+            })
+            .sum::<usize>();
+        println!("  {candidates} candidates: {:.4}", found as f64 / (NUM_OF_QUERIES * NUM_OF_NEAREST_NEIGHBOURS) as f64);
+    }
+}
+
+fn quantized_kd_tree_benchmark(c: &mut Criterion) {
+    let points = generate_random_points(NUM_OF_POINTS);
+    let kd_tree = KdTree::construct_tree(points.clone());
+    let quantized_tree = QuantizedKdTree::construct_tree(points);
+    let given_point = black_box(Point::random());
+
+    let mut group = c.benchmark_group("Quantized 10 nearest neighbours among 1,000,000 points");
+    group.bench_function("kd tree", |b| b.iter(|| kd_tree.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS)));
+    for candidates in CANDIDATES {
+        group.bench_function(format!("Re-rank {candidates}"),
+                             |b| b.iter(|| quantized_tree.nearest_with_candidates(&given_point, NUM_OF_NEAREST_NEIGHBOURS, candidates)));
+    }
+}
+
+criterion_group!(benches, quantized_kd_tree_benchmark);
+
+fn main() {
+    report_memory_usage();
+    report_recall();
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
@@ -0,0 +1,273 @@
+use std::collections::BinaryHeap;
+
+use crate::bounding_box::BoundingBox;
+use crate::nearest_neighbour::{is_potential_nearer_neighbour, Neighbour, NeighbourSearch};
+use crate::point::{Dimension, Point};
+use crate::spatial_index::SpatialIndex;
+
+/// Maximum number of points kept in a leaf of the ball-tree.
+const LEAF_SIZE: usize = 16;
+
+/// Struct that represents a node in the ball-tree data structure.
+/// Every point below the node lies within `radius` of `center`; leaves keep their points directly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BallTreeNode {
+    pub center: Point,
+    pub radius: f64,
+    pub points: Vec<Point>,
+    pub left: Option<Box<BallTreeNode>>,
+    pub right: Option<Box<BallTreeNode>>,
+}
+
+/// Struct that represents a ball-tree, which prunes by distance to a bounding sphere
+/// instead of by the difference along a single axis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BallTree {
+    pub root: Option<Box<BallTreeNode>>,
+}
+
+impl BallTree {
+    /// Constructs a ball-tree from a vector of `Point` objects.
+    pub fn construct_tree(points: Vec<Point>) -> BallTree {
+        BallTree { root: construct_ball_tree(points) }
+    }
+}
+
+impl NeighbourSearch for BallTree {
+    fn nearest(&self, given_point: &Point, k: usize) -> Vec<Neighbour> {
+        let mut k_nearest_neighbours = BinaryHeap::new();
+        if let Some(root) = &self.root {
+            find_k_nearest_neighbours(root, given_point, &mut k_nearest_neighbours, k);
+        }
+        k_nearest_neighbours.into_sorted_vec()
+    }
+
+    fn within_radius(&self, given_point: &Point, radius: f64) -> Vec<Neighbour> {
+        let mut neighbours = vec![];
+        if let Some(root) = &self.root {
+            find_neighbours_within_radius(root, given_point, radius, &mut neighbours);
+        }
+        neighbours.sort();
+        neighbours
+    }
+}
+
+impl SpatialIndex for BallTree {
+    fn build(points: Vec<Point>) -> BallTree {
+        BallTree::construct_tree(points)
+    }
+
+    fn in_box(&self, bounding_box: &BoundingBox) -> Vec<Point> {
+        let mut points = vec![];
+        if let Some(root) = &self.root {
+            find_points_in_box(root, bounding_box, &mut points);
+        }
+        points
+    }
+
+    fn len(&self) -> usize {
+        self.root.as_deref().map_or(0, count_points)
+    }
+}
+
+fn construct_ball_tree(mut points: Vec<Point>) -> Option<Box<BallTreeNode>> {
+    if points.is_empty() {
+        return None;
+    }
+    let center = calculate_centroid(&points);
+    let radius = points.iter().map(|p| center.distance_to(p)).fold(0.0, f64::max);
+    if points.len() <= LEAF_SIZE {
+        return Some(Box::new(BallTreeNode { center, radius, points, left: None, right: None }));
+    }
+
+    // split at the median of the dimension with the widest spread
+    let dimension = widest_dimension(&points);
+    let middle = points.len() / 2;
+    points.select_nth_unstable_by(middle, |a, b| a.compare_dimension(b, &dimension));
+    let right_points = points.split_off(middle);
+    Some(Box::new(BallTreeNode {
+        center,
+        radius,
+        points: vec![],
+        left: construct_ball_tree(points),
+        right: construct_ball_tree(right_points),
+    }))
+}
+
+fn calculate_centroid(points: &[Point]) -> Point {
+    let len = points.len() as f64;
+    Point {
+        x: points.iter().map(|p| p.x).sum::<f64>() / len,
+        y: points.iter().map(|p| p.y).sum::<f64>() / len,
+        z: points.iter().map(|p| p.z).sum::<f64>() / len,
+    }
+}
+
+fn widest_dimension(points: &[Point]) -> Dimension {
+    let spread = |dimension: &Dimension| {
+        let values = points.iter().map(|p| p.get_dimension(dimension));
+        values.clone().fold(f64::MIN, f64::max) - values.fold(f64::MAX, f64::min)
+    };
+    [Dimension::Y, Dimension::Z].into_iter()
+        .fold(Dimension::X, |widest, dimension| if spread(&dimension) > spread(&widest) { dimension } else { widest })
+}
+
+/// Returns the smallest possible distance from the given point to any point inside the node's ball.
+fn distance_to_ball(node: &BallTreeNode, given_point: &Point) -> f64 {
+    (given_point.distance_to(&node.center) - node.radius).max(0.0)
+}
+
+fn find_k_nearest_neighbours(curr_node: &BallTreeNode, given_point: &Point,
+                             k_nearest_neighbours: &mut BinaryHeap<Neighbour>, k: usize) {
+    if !is_potential_nearer_neighbour(distance_to_ball(curr_node, given_point), k_nearest_neighbours, k) {
+        return;
+    }
+    for point in &curr_node.points {
+        let curr_distance = given_point.distance_to(point);
+        if is_potential_nearer_neighbour(curr_distance, k_nearest_neighbours, k) {
+            if k_nearest_neighbours.len() >= k { k_nearest_neighbours.pop(); }
+            k_nearest_neighbours.push(Neighbour { distance: curr_distance, point: point.clone() });
+        }
+    }
+
+    if let (Some(left), Some(right)) = (&curr_node.left, &curr_node.right) {
+        // visit the ball whose center is nearer first so the bound shrinks sooner
+        let (near, far) = match given_point.distance_to(&left.center) <= given_point.distance_to(&right.center) {
+            true => (left, right),
+            false => (right, left),
+        };
+        find_k_nearest_neighbours(near, given_point, k_nearest_neighbours, k);
+        find_k_nearest_neighbours(far, given_point, k_nearest_neighbours, k);
+    }
+}
+
+fn find_neighbours_within_radius(curr_node: &BallTreeNode, given_point: &Point, radius: f64, neighbours: &mut Vec<Neighbour>) {
+    if distance_to_ball(curr_node, given_point) > radius {
+        return;
+    }
+    for point in &curr_node.points {
+        let curr_distance = given_point.distance_to(point);
+        if curr_distance <= radius {
+            neighbours.push(Neighbour { distance: curr_distance, point: point.clone() });
+        }
+    }
+    for child in [&curr_node.left, &curr_node.right].into_iter().flatten() {
+        find_neighbours_within_radius(child, given_point, radius, neighbours);
+    }
+}
+
+fn find_points_in_box(curr_node: &BallTreeNode, bounding_box: &BoundingBox, points: &mut Vec<Point>) {
+    // every point of the ball is within its radius of the center, so a box further away holds none of them
+    if bounding_box.distance_to_point(&curr_node.center) > curr_node.radius {
+        return;
+    }
+    points.extend(curr_node.points.iter().filter(|point| bounding_box.contains(point)).cloned());
+    for child in [&curr_node.left, &curr_node.right].into_iter().flatten() {
+        find_points_in_box(child, bounding_box, points);
+    }
+}
+
+fn count_points(curr_node: &BallTreeNode) -> usize {
+    curr_node.points.len() + [&curr_node.left, &curr_node.right].into_iter().flatten().map(|child| count_points(child)).sum::<usize>()
+}
+
+#[cfg(test)]
+mod ball_tree_test {
+    use crate::nearest_neighbour::NeighbourSearch;
+    use crate::point::{Dimension, Point};
+    use crate::spatial_index::SpatialIndex;
+
+    use super::{BallTree, calculate_centroid, construct_ball_tree, LEAF_SIZE, widest_dimension};
+
+    #[test]
+    fn centroid_of_points() {
+        let points = vec![
+            Point::new(0.1, 0.2, 0.3),
+            Point::new(0.3, 0.4, 0.5),
+        ];
+        let output = calculate_centroid(&points);
+        let expected = Point::new((0.1 + 0.3) / 2.0, (0.2 + 0.4) / 2.0, (0.3 + 0.5) / 2.0);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn widest_dimension_of_points() {
+        let points = vec![
+            Point::new(0.1, 0.2, 0.3),
+            Point::new(0.3, 0.9, 0.5),
+            Point::new(0.2, 0.5, 0.1),
+        ];
+        let output = widest_dimension(&points);
+        let expected = Dimension::Y;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn build_ball_tree_empty_vec() {
+        let output = construct_ball_tree(vec![]);
+        assert_eq!(output, None);
+    }
+
+    #[test]
+    fn build_ball_tree_single_leaf() {
+        let points = vec![Point::new(0.0, 0.0, 0.0), Point::new(0.5, 0.0, 0.0)];
+        let output = construct_ball_tree(points.clone()).unwrap();
+        assert_eq!(output.center, Point::new(0.25, 0.0, 0.0));
+        assert_eq!(output.radius, 0.25);
+        assert_eq!(output.points, points);
+        assert_eq!(output.left, None);
+    }
+
+    #[test]
+    fn build_ball_tree_splits_large_nodes() {
+        let points = (0..2 * LEAF_SIZE).map(|i| Point::new(i as f64 / 100.0, 0.5, 0.5)).collect::<Vec<Point>>();
+        let output = construct_ball_tree(points).unwrap();
+        assert!(output.points.is_empty());
+        let left = output.left.unwrap();
+        let right = output.right.unwrap();
+        assert_eq!(left.points.len(), LEAF_SIZE);
+        assert_eq!(right.points.len(), LEAF_SIZE);
+        assert!(left.points.iter().all(|p| p.x < 0.16));
+        assert!(right.points.iter().all(|p| p.x >= 0.16));
+    }
+
+    #[test]
+    fn balls_contain_their_points() {
+        let points = (0..100).map(|i| Point::new(i as f64 / 100.0, (i % 7) as f64 / 7.0, (i % 3) as f64 / 3.0)).collect::<Vec<Point>>();
+        let tree = BallTree::construct_tree(points);
+        let mut stack = vec![tree.root.unwrap()];
+        while let Some(node) = stack.pop() {
+            assert!(node.points.iter().all(|p| node.center.distance_to(p) <= node.radius));
+            stack.extend(node.left.into_iter().chain(node.right));
+        }
+    }
+
+    #[test]
+    fn nearest_in_empty_tree() {
+        let output = BallTree::construct_tree(vec![]).nearest(&Point::new(0.5, 0.5, 0.5), 3);
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn within_radius_includes_boundary() {
+        let tree = BallTree::construct_tree(vec![
+            Point::new(0.5, 0.5, 0.5),
+            Point::new(0.75, 0.5, 0.5),
+            Point::new(0.5, 1.0, 0.5),
+        ]);
+        let output = tree.within_radius(&Point::new(0.5, 0.5, 0.5), 0.25)
+            .into_iter()
+            .map(|n| n.point)
+            .collect::<Vec<Point>>();
+        let expected = vec![Point::new(0.5, 0.5, 0.5), Point::new(0.75, 0.5, 0.5)];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn len_counts_points_of_every_leaf() {
+        let points = (0..5 * LEAF_SIZE).map(|i| Point::new(i as f64 / 100.0, 0.5, 0.5)).collect::<Vec<Point>>();
+        let output = BallTree::construct_tree(points).len();
+        let expected = 5 * LEAF_SIZE;
+        assert_eq!(output, expected);
+    }
+}
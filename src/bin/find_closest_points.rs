@@ -1,37 +1,339 @@
-use std::collections::BinaryHeap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
-use find_closest_points::kd_tree::KdTreeNode;
-use find_closest_points::nearest_neighbour::{find_k_nearest_neighbours, Neighbour};
 use find_closest_points::{generate_random_points, NUM_OF_NEAREST_NEIGHBOURS};
-use find_closest_points::point::{Dimension, Point};
-
-fn main() {
-    println!("generating 100_000 random points");
-    let points = generate_random_points(100_000);
-
-    println!("constructing tree...");
-    let tree = KdTreeNode::construct_tree(points.clone());
-    println!("construction finished");
-
-    println!("finding nearest neighbours");
-    let given_point = Point::random();
-    let mut heap = BinaryHeap::new();
-    find_k_nearest_neighbours(&Some(Box::new(tree)), &given_point, &Dimension::X, &mut heap, NUM_OF_NEAREST_NEIGHBOURS);
-    let mut heap_vec = heap.into_iter().collect::<Vec<Neighbour>>();
-    heap_vec.sort();
-    println!("Target point: {:?}\n", given_point);
-    println!("List of 10 nearest neighbours using kd-tree: ");
-    for neighbour in &heap_vec {
-        println!("value: {:?}, point: {:?}", neighbour.distance, neighbour.point);
-    }
-    println!();
-
-    let mut neighbours = points.into_iter().map(|p| Neighbour { distance: given_point.distance_to(&p), point: p }).collect::<Vec<Neighbour>>();
-    neighbours.sort();
-    println!("List of 10 nearest neighbours using sort: ");
-    for i in 0..10 {
-        println!("value: {:?}, point: {:?}", neighbours[i].distance, neighbours[i].point);
-    }
-
-    assert_eq!(heap_vec, &neighbours[0..NUM_OF_NEAREST_NEIGHBOURS]);
-}
\ No newline at end of file
+use find_closest_points::bounding_box::BoundingBox;
+use find_closest_points::brute_force::BruteForce;
+use find_closest_points::formats::{las, npy, pcd, ply, text};
+use find_closest_points::kd_tree::{KdTree, KdTreeNode};
+use find_closest_points::nearest_neighbour::{Neighbour, NeighbourSearch};
+use find_closest_points::point::Point;
+use find_closest_points::spatial_index::SpatialIndex;
+
+/// Build, query and measure kd-trees of 3D points.
+#[derive(Parser)]
+#[command(name = "find_closest_points", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Read a point file and save a kd-tree of its points as an index.
+    Build {
+        /// The point file to read.
+        input: PathBuf,
+        /// Where to save the index.
+        output: PathBuf,
+        #[arg(long, value_enum)]
+        input_format: Option<InputFormat>,
+    },
+    /// Find the nearest neighbours of points, the neighbours within a radius or the points in a box.
+    Query(QueryArgs),
+    /// Print the number of points, the bounds and the depth of a kd-tree.
+    Stats {
+        #[command(flatten)]
+        source: Source,
+        #[arg(long, value_enum)]
+        input_format: Option<InputFormat>,
+    },
+    /// Time building and searching a kd-tree of random points against a brute force search.
+    Bench {
+        /// The number of random points to build the tree of.
+        #[arg(long, default_value_t = 100_000)]
+        points: u32,
+        /// The number of random points to find the neighbours of.
+        #[arg(long, default_value_t = 1_000)]
+        queries: u32,
+        #[arg(long, default_value_t = NUM_OF_NEAREST_NEIGHBOURS)]
+        k: usize,
+    },
+}
+
+/// Struct for where the points to search come from, either an index saved by `build` or a point file.
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct Source {
+    /// An index saved by `build`.
+    #[arg(long)]
+    index: Option<PathBuf>,
+    /// A point file to build a kd-tree of.
+    #[arg(long)]
+    input: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct QueryArgs {
+    #[command(flatten)]
+    source: Source,
+    #[arg(long, value_enum)]
+    input_format: Option<InputFormat>,
+    /// A point to search around, as `x,y,z`, which may be given more than once. Box searches need none.
+    #[arg(long = "point", value_parser = parse_point, required_unless_present_any = ["bounding_box", "queries"])]
+    points: Vec<Point>,
+    /// A CSV or XYZ file of points to search around, or `-` to read them from the standard input.
+    /// Results are written as each point is read, so points can be piped in.
+    #[arg(long, conflicts_with_all = ["points", "bounding_box"])]
+    queries: Option<PathBuf>,
+    /// The format of the points to search around, which is otherwise chosen by the file's extension, or `xyz` for the standard input.
+    #[arg(long, value_enum, requires = "queries")]
+    queries_format: Option<QueryFormat>,
+    #[command(flatten)]
+    search: Search,
+    #[arg(long, value_enum, default_value_t = OutputFormat::Csv)]
+    output_format: OutputFormat,
+    /// Where to write the results, instead of the standard output.
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+/// Struct for the kind of search a query makes, of which exactly one is given.
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct Search {
+    /// Find the k nearest neighbours of each point.
+    #[arg(long)]
+    k: Option<usize>,
+    /// Find every neighbour within this distance of each point.
+    #[arg(long)]
+    radius: Option<f64>,
+    /// Find every point in the box `min_x,min_y,min_z,max_x,max_y,max_z`, including its boundary.
+    #[arg(long = "box", value_parser = parse_box)]
+    bounding_box: Option<BoundingBox>,
+}
+
+/// Enum for the point files that can be read, which is otherwise chosen by the file's extension.
+#[derive(Clone, Copy, ValueEnum)]
+enum InputFormat {
+    Csv,
+    Xyz,
+    Ply,
+    Pcd,
+    Las,
+    Npy,
+}
+
+/// Enum for the text files that points to search around can be read from.
+#[derive(Clone, Copy, ValueEnum)]
+enum QueryFormat {
+    /// Comma-separated x, y and z columns under a header line.
+    Csv,
+    /// Whitespace-separated x, y and z columns.
+    Xyz,
+}
+
+/// Enum for how results are written, one neighbour or point to a line. For searches around points, `csv` and `jsonl`
+/// start with the position of the point searched around and the rank of the neighbour, counted from 0, and all three
+/// give the distance of each neighbour after its coordinates.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum OutputFormat {
+    /// Comma-separated values under a header line.
+    Csv,
+    /// Space-separated coordinates and distances, without a header.
+    Xyz,
+    /// A JSON object on each line.
+    Jsonl,
+}
+
+fn parse_numbers<const N: usize>(value: &str) -> Result<[f64; N], String> {
+    let numbers = value.split(',')
+        .map(|number| number.trim().parse::<f64>().map_err(|_| format!("`{number}` is not a number")))
+        .collect::<Result<Vec<f64>, String>>()?;
+    numbers.try_into().map_err(|numbers: Vec<f64>| format!("expected {N} numbers separated by commas but found {}", numbers.len()))
+}
+
+fn parse_point(value: &str) -> Result<Point, String> {
+    let [x, y, z] = parse_numbers(value)?;
+    Ok(Point { x, y, z })
+}
+
+fn parse_box(value: &str) -> Result<BoundingBox, String> {
+    let [min_x, min_y, min_z, max_x, max_y, max_z] = parse_numbers(value)?;
+    Ok(BoundingBox::new(Point { x: min_x, y: min_y, z: min_z }, Point { x: max_x, y: max_y, z: max_z }))
+}
+
+fn read_points(path: &Path, format: Option<InputFormat>) -> Result<Vec<Point>, Box<dyn Error>> {
+    let format = match format {
+        Some(format) => format,
+        None => {
+            let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default().to_ascii_lowercase();
+            InputFormat::from_str(&extension, true)
+                .map_err(|_| format!("cannot tell the format of {} from its extension, give it with --input-format", path.display()))?
+        }
+    };
+    let reader = BufReader::new(File::open(path)?);
+    let points = match format {
+        InputFormat::Csv => text::read_points(reader, &text::TextFormat::csv())?,
+        InputFormat::Xyz => text::read_points(reader, &text::TextFormat::xyz())?,
+        InputFormat::Ply => ply::read_ply(reader)?.positions(),
+        InputFormat::Pcd => pcd::read_pcd(reader)?.positions(),
+        InputFormat::Las => las::read_las(reader)?.positions(),
+        InputFormat::Npy => npy::read_npy_points(reader)?,
+    };
+    Ok(points)
+}
+
+fn load_tree(source: &Source, format: Option<InputFormat>) -> Result<KdTree, Box<dyn Error>> {
+    match (&source.index, &source.input) {
+        (Some(index), _) => Ok(KdTree::load(BufReader::new(File::open(index)?))?),
+        (None, Some(input)) => Ok(KdTree::construct_tree(read_points(input, format)?)),
+        (None, None) => unreachable!("clap requires an index or an input"),
+    }
+}
+
+fn depth(curr_node: &Option<Box<KdTreeNode>>) -> usize {
+    match curr_node {
+        Some(node) => 1 + depth(&node.left).max(depth(&node.right)),
+        None => 0,
+    }
+}
+
+fn build(input: &Path, output: &Path, format: Option<InputFormat>) -> Result<(), Box<dyn Error>> {
+    let points = read_points(input, format)?;
+    let len = points.len();
+    KdTree::construct_tree(points).save(BufWriter::new(File::create(output)?))?;
+    println!("saved a kd-tree of {len} points to {}", output.display());
+    Ok(())
+}
+
+/// Type for the points to search around, each of which may fail to be read.
+type QueryPoints<'a> = Box<dyn Iterator<Item=Result<Point, Box<dyn Error>>> + 'a>;
+
+/// Returns the points to search around, streaming them from a file or the standard input if they are read from one.
+fn query_points(args: &QueryArgs) -> Result<QueryPoints<'_>, Box<dyn Error>> {
+    let Some(path) = &args.queries else {
+        return Ok(Box::new(args.points.iter().cloned().map(Ok)));
+    };
+    let (reader, format): (Box<dyn BufRead>, _) = match path.to_str() {
+        Some("-") => (Box::new(io::stdin().lock()), args.queries_format.unwrap_or(QueryFormat::Xyz)),
+        _ => {
+            let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default().to_ascii_lowercase();
+            let format = match args.queries_format {
+                Some(format) => format,
+                None => QueryFormat::from_str(&extension, true)
+                    .map_err(|_| format!("cannot tell the format of {} from its extension, give it with --queries-format", path.display()))?,
+            };
+            (Box::new(BufReader::new(File::open(path)?)), format)
+        }
+    };
+    let format = match format {
+        QueryFormat::Csv => text::TextFormat::csv(),
+        QueryFormat::Xyz => text::TextFormat::xyz(),
+    };
+    Ok(Box::new(text::TextPoints::new(reader, &format)?.map(|point| point.map_err(Box::from))))
+}
+
+fn write_neighbour(writer: &mut dyn Write, format: OutputFormat, query: usize, rank: usize, neighbour: &Neighbour) -> io::Result<()> {
+    let Neighbour { point, distance } = neighbour;
+    match format {
+        OutputFormat::Csv => writeln!(writer, "{query},{rank},{},{},{},{distance}", point.x, point.y, point.z),
+        OutputFormat::Xyz => writeln!(writer, "{} {} {} {distance}", point.x, point.y, point.z),
+        OutputFormat::Jsonl =>
+            writeln!(writer, r#"{{"query":{query},"rank":{rank},"x":{},"y":{},"z":{},"distance":{distance}}}"#, point.x, point.y, point.z),
+    }
+}
+
+fn write_point(writer: &mut dyn Write, format: OutputFormat, point: &Point) -> io::Result<()> {
+    match format {
+        OutputFormat::Csv => writeln!(writer, "{},{},{}", point.x, point.y, point.z),
+        OutputFormat::Xyz => writeln!(writer, "{} {} {}", point.x, point.y, point.z),
+        OutputFormat::Jsonl => writeln!(writer, r#"{{"x":{},"y":{},"z":{}}}"#, point.x, point.y, point.z),
+    }
+}
+
+fn query(args: &QueryArgs) -> Result<(), Box<dyn Error>> {
+    let tree = load_tree(&args.source, args.input_format)?;
+    let mut writer: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let csv = args.output_format == OutputFormat::Csv;
+    if let Some(bounding_box) = &args.search.bounding_box {
+        if csv {
+            writeln!(writer, "x,y,z")?;
+        }
+        for point in tree.in_box(bounding_box) {
+            write_point(&mut writer, args.output_format, &point)?;
+        }
+    } else {
+        if csv {
+            writeln!(writer, "query,rank,x,y,z,distance")?;
+        }
+        // points piped in get their results as soon as they are found, rather than when the buffer fills
+        let streaming = args.queries.as_ref().is_some_and(|path| path.to_str() == Some("-"));
+        for (i, given_point) in query_points(args)?.enumerate() {
+            let given_point = given_point?;
+            let neighbours: Vec<Neighbour> = match (args.search.k, args.search.radius) {
+                (Some(k), _) => tree.nearest(&given_point, k),
+                (None, Some(radius)) => tree.within_radius(&given_point, radius),
+                (None, None) => unreachable!("clap requires a search"),
+            };
+            for (rank, neighbour) in neighbours.iter().enumerate() {
+                write_neighbour(&mut writer, args.output_format, i, rank, neighbour)?;
+            }
+            if streaming {
+                writer.flush()?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn stats(source: &Source, format: Option<InputFormat>) -> Result<(), Box<dyn Error>> {
+    let tree = load_tree(source, format)?;
+    println!("points: {}", tree.len());
+    println!("depth: {}", depth(&tree.root));
+    if let Some(root) = &tree.root {
+        let bounds = root.bounding_box();
+        println!("min: {} {} {}", bounds.min.x, bounds.min.y, bounds.min.z);
+        println!("max: {} {} {}", bounds.max.x, bounds.max.y, bounds.max.z);
+    }
+    Ok(())
+}
+
+fn bench(points: u32, queries: u32, k: usize) -> Result<(), Box<dyn Error>> {
+    let points = generate_random_points(points);
+    let queries = generate_random_points(queries);
+
+    let start = Instant::now();
+    let tree = KdTree::construct_tree(points.clone());
+    println!("kd-tree of {} points built in {:?}", points.len(), start.elapsed());
+    let brute_force = BruteForce::construct_index(points);
+
+    let start = Instant::now();
+    let output = queries.iter().map(|query| tree.nearest(query, k)).collect::<Vec<_>>();
+    println!("{k} nearest neighbours of {} points found by the kd-tree in {:?}", queries.len(), start.elapsed());
+    let start = Instant::now();
+    let expected = queries.iter().map(|query| brute_force.nearest(query, k)).collect::<Vec<_>>();
+    println!("{k} nearest neighbours of {} points found by brute force in {:?}", queries.len(), start.elapsed());
+
+    if output != expected {
+        return Err("the kd-tree and brute force found different neighbours".into());
+    }
+    println!("the kd-tree and brute force found the same neighbours");
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let result = match Cli::parse().command {
+        Command::Build { input, output, input_format } => build(&input, &output, input_format),
+        Command::Query(args) => query(&args),
+        Command::Stats { source, input_format } => stats(&source, input_format),
+        Command::Bench { points, queries, k } => bench(points, queries, k),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
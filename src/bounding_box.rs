@@ -0,0 +1,296 @@
+use crate::point::{Dimension, Point};
+
+/// Struct that represents an axis-aligned box in a 3D space.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl BoundingBox {
+    /// Creates a new box from its lowest and highest corners.
+    pub fn new(min: Point, max: Point) -> BoundingBox {
+        BoundingBox { min, max }
+    }
+
+    /// Creates the smallest box containing all the given points, or `None` if there are no points.
+    pub fn from_points<'a>(points: impl IntoIterator<Item=&'a Point>) -> Option<BoundingBox> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let mut bounding_box = BoundingBox::new(first.clone(), first.clone());
+        for point in points {
+            bounding_box.extend(point);
+        }
+        Some(bounding_box)
+    }
+
+    /// Grows the box so that it contains the given point.
+    pub fn extend(&mut self, point: &Point) {
+        self.min.x = self.min.x.min(point.x);
+        self.min.y = self.min.y.min(point.y);
+        self.min.z = self.min.z.min(point.z);
+        self.max.x = self.max.x.max(point.x);
+        self.max.y = self.max.y.max(point.y);
+        self.max.z = self.max.z.max(point.z);
+    }
+
+    /// Returns true if the point lies inside the box or on its boundary.
+    pub fn contains(&self, point: &Point) -> bool {
+        self.min.x <= point.x && point.x <= self.max.x
+            && self.min.y <= point.y && point.y <= self.max.y
+            && self.min.z <= point.z && point.z <= self.max.z
+    }
+
+    /// Returns true if the other box lies entirely inside this box.
+    pub fn contains_box(&self, other: &BoundingBox) -> bool {
+        self.contains(&other.min) && self.contains(&other.max)
+    }
+
+    /// Returns true if the two boxes share at least one point, including touching boundaries.
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min.x <= other.max.x && other.min.x <= self.max.x
+            && self.min.y <= other.max.y && other.min.y <= self.max.y
+            && self.min.z <= other.max.z && other.min.z <= self.max.z
+    }
+
+    /// Returns the smallest box containing both boxes.
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        let mut union = self.clone();
+        union.extend(&other.min);
+        union.extend(&other.max);
+        union
+    }
+
+    /// Calculate the volume of the box.
+    pub fn volume(&self) -> f64 {
+        (self.max.x - self.min.x) * (self.max.y - self.min.y) * (self.max.z - self.min.z)
+    }
+
+    /// Calculate the sum of the edge lengths along each dimension, used to prefer boxes close to cubes.
+    pub fn margin(&self) -> f64 {
+        (self.max.x - self.min.x) + (self.max.y - self.min.y) + (self.max.z - self.min.z)
+    }
+
+    /// Calculate the volume of the intersection of two boxes, which is zero when they do not overlap.
+    pub fn overlap(&self, other: &BoundingBox) -> f64 {
+        let length = |min: f64, max: f64, other_min: f64, other_max: f64| (max.min(other_max) - min.max(other_min)).max(0.0);
+        length(self.min.x, self.max.x, other.min.x, other.max.x)
+            * length(self.min.y, self.max.y, other.min.y, other.max.y)
+            * length(self.min.z, self.max.z, other.min.z, other.max.z)
+    }
+
+    /// Returns the point in the middle of the box.
+    pub fn center(&self) -> Point {
+        Point {
+            x: (self.min.x + self.max.x) / 2.0,
+            y: (self.min.y + self.max.y) / 2.0,
+            z: (self.min.z + self.max.z) / 2.0,
+        }
+    }
+
+    /// Calculate the smallest Euclidean distance from the box to a point, which is zero for points inside the box.
+    /// For a box around a single point it is exactly what `Point::distance_to` returns.
+    pub fn distance_to_point(&self, point: &Point) -> f64 {
+        let dx = axis_gap(self.min.x, self.max.x, point.x, point.x);
+        let dy = axis_gap(self.min.y, self.max.y, point.y, point.y);
+        let dz = axis_gap(self.min.z, self.max.z, point.z, point.z);
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+
+    /// Calculate the largest Euclidean distance from a point to any point of the box, which is the distance to its furthest corner.
+    pub fn max_distance_to_point(&self, point: &Point) -> f64 {
+        let dx = (point.x - self.min.x).abs().max((point.x - self.max.x).abs());
+        let dy = (point.y - self.min.y).abs().max((point.y - self.max.y).abs());
+        let dz = (point.z - self.min.z).abs().max((point.z - self.max.z).abs());
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+
+    /// Calculate the smallest Euclidean distance between any two points of two boxes.
+    pub fn distance_to_box(&self, other: &BoundingBox) -> f64 {
+        let dx = axis_gap(self.min.x, self.max.x, other.min.x, other.max.x);
+        let dy = axis_gap(self.min.y, self.max.y, other.min.y, other.max.y);
+        let dz = axis_gap(self.min.z, self.max.z, other.min.z, other.max.z);
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+
+    /// Splits the box in two along a dimension, returning the parts below and above the given value.
+    pub fn split(&self, dimension: &Dimension, value: f64) -> (BoundingBox, BoundingBox) {
+        let mut lower = self.clone();
+        let mut upper = self.clone();
+        match dimension {
+            Dimension::X => {
+                lower.max.x = value;
+                upper.min.x = value;
+            }
+            Dimension::Y => {
+                lower.max.y = value;
+                upper.min.y = value;
+            }
+            Dimension::Z => {
+                lower.max.z = value;
+                upper.min.z = value;
+            }
+        }
+        (lower, upper)
+    }
+}
+
+/// Returns the gap between two intervals on one axis, which is zero when they overlap.
+fn axis_gap(min: f64, max: f64, other_min: f64, other_max: f64) -> f64 {
+    if other_max < min {
+        min - other_max
+    } else if other_min > max {
+        other_min - max
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod bounding_box_test {
+    use crate::point::{Dimension, Point};
+
+    use super::{axis_gap, BoundingBox};
+
+    #[test]
+    fn from_points() {
+        let points = vec![
+            Point::new(0.1, 0.5, 0.3),
+            Point::new(0.4, 0.2, 0.9),
+            Point::new(0.2, 0.7, 0.1),
+        ];
+        let output = BoundingBox::from_points(&points);
+        let expected = Some(BoundingBox::new(Point::new(0.1, 0.2, 0.1), Point::new(0.4, 0.7, 0.9)));
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn from_no_points() {
+        let output = BoundingBox::from_points(&vec![]);
+        assert_eq!(output, None);
+    }
+
+    #[test]
+    fn contains_point_on_boundary() {
+        let bounding_box = BoundingBox::new(Point::new(0.1, 0.1, 0.1), Point::new(0.5, 0.5, 0.5));
+        assert!(bounding_box.contains(&Point::new(0.5, 0.3, 0.1)));
+    }
+
+    #[test]
+    fn does_not_contain_point_outside() {
+        let bounding_box = BoundingBox::new(Point::new(0.1, 0.1, 0.1), Point::new(0.5, 0.5, 0.5));
+        assert!(!bounding_box.contains(&Point::new(0.6, 0.3, 0.2)));
+    }
+
+    #[test]
+    fn contains_inner_box() {
+        let bounding_box = BoundingBox::new(Point::new(0.1, 0.1, 0.1), Point::new(0.5, 0.5, 0.5));
+        assert!(bounding_box.contains_box(&BoundingBox::new(Point::new(0.2, 0.1, 0.3), Point::new(0.5, 0.4, 0.4))));
+        assert!(!bounding_box.contains_box(&BoundingBox::new(Point::new(0.2, 0.1, 0.3), Point::new(0.6, 0.4, 0.4))));
+    }
+
+    #[test]
+    fn intersects_touching_box() {
+        let first = BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(0.5, 0.5, 0.5));
+        let second = BoundingBox::new(Point::new(0.5, 0.2, 0.2), Point::new(1.0, 1.0, 1.0));
+        assert!(first.intersects(&second));
+    }
+
+    #[test]
+    fn does_not_intersect_separated_box() {
+        let first = BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(0.5, 0.5, 0.5));
+        let second = BoundingBox::new(Point::new(0.2, 0.6, 0.2), Point::new(1.0, 1.0, 1.0));
+        assert!(!first.intersects(&second));
+    }
+
+    #[test]
+    fn union_of_boxes() {
+        let first = BoundingBox::new(Point::new(0.0, 0.5, 0.0), Point::new(0.5, 0.75, 0.5));
+        let second = BoundingBox::new(Point::new(0.25, 0.0, 0.25), Point::new(1.0, 0.5, 0.5));
+        let output = first.union(&second);
+        let expected = BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(1.0, 0.75, 0.5));
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn volume_and_margin() {
+        let bounding_box = BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(0.5, 0.25, 1.0));
+        assert_eq!(bounding_box.volume(), 0.125);
+        assert_eq!(bounding_box.margin(), 1.75);
+    }
+
+    #[test]
+    fn overlap_of_boxes() {
+        let first = BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(0.5, 0.5, 0.5));
+        let second = BoundingBox::new(Point::new(0.25, 0.25, 0.0), Point::new(1.0, 1.0, 1.0));
+        assert_eq!(first.overlap(&second), 0.03125);
+        let third = BoundingBox::new(Point::new(0.75, 0.75, 0.75), Point::new(1.0, 1.0, 1.0));
+        assert_eq!(first.overlap(&third), 0.0);
+    }
+
+    #[test]
+    fn center_of_box() {
+        let bounding_box = BoundingBox::new(Point::new(0.0, 0.25, 0.5), Point::new(0.5, 0.75, 1.0));
+        let output = bounding_box.center();
+        let expected = Point::new(0.25, 0.5, 0.75);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn axis_gap_overlapping() {
+        let output = axis_gap(0.1, 0.5, 0.4, 0.8);
+        let expected = 0.0;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn axis_gap_separated() {
+        let output = axis_gap(0.5, 0.75, 0.0, 0.25);
+        let expected = 0.25;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn distance_to_point_inside() {
+        let bounding_box = BoundingBox::new(Point::new(0.1, 0.1, 0.1), Point::new(0.5, 0.5, 0.5));
+        let output = bounding_box.distance_to_point(&Point::new(0.3, 0.3, 0.3));
+        let expected = 0.0;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn distance_to_point_outside() {
+        let bounding_box = BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(0.5, 0.5, 0.5));
+        let output = bounding_box.distance_to_point(&Point::new(0.8, 0.9, 0.5));
+        let expected = 0.5;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn max_distance_to_point() {
+        let bounding_box = BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(0.5, 0.5, 0.5));
+        let output = bounding_box.max_distance_to_point(&Point::new(0.25, 0.5, 1.0));
+        let expected = (0.25f64 * 0.25 + 0.5 * 0.5 + 1.0).sqrt();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn distance_to_box() {
+        let first = BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(0.25, 0.25, 0.25));
+        let second = BoundingBox::new(Point::new(0.5, 0.25, 0.0), Point::new(1.0, 1.0, 1.0));
+        let output = first.distance_to_box(&second);
+        let expected = 0.25;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn split_along_y() {
+        let bounding_box = BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(1.0, 1.0, 1.0));
+        let output = bounding_box.split(&Dimension::Y, 0.4);
+        let expected = (
+            BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(1.0, 0.4, 1.0)),
+            BoundingBox::new(Point::new(0.0, 0.4, 0.0), Point::new(1.0, 1.0, 1.0)),
+        );
+        assert_eq!(output, expected);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::bounding_box::BoundingBox;
+use crate::nearest_neighbour::{Neighbour, NeighbourSearch};
+use crate::point::Point;
+use crate::spatial_index::SpatialIndex;
+
+/// Number of points whose distances are computed together, which lets the compiler turn the
+/// distance kernel into vector instructions.
+const LANES: usize = 8;
+
+/// Struct that represents an index that compares the given point with every point.
+/// The coordinates are kept in one array per dimension so that the distances to a whole block of
+/// points are computed at once, which makes it the fastest index for small numbers of points and
+/// the reference that the other indexes are checked against.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BruteForce {
+    xs: Vec<f64>,
+    ys: Vec<f64>,
+    zs: Vec<f64>,
+}
+
+impl BruteForce {
+    /// Constructs a brute-force index from a vector of `Point` objects.
+    pub fn construct_index(points: Vec<Point>) -> BruteForce {
+        BruteForce {
+            xs: points.iter().map(|p| p.x).collect(),
+            ys: points.iter().map(|p| p.y).collect(),
+            zs: points.iter().map(|p| p.z).collect(),
+        }
+    }
+
+    /// Returns the number of points in the index.
+    pub fn len(&self) -> usize {
+        self.xs.len()
+    }
+
+    /// Returns true if the index holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.xs.is_empty()
+    }
+
+    /// Returns the point at the given position, in the order the points were given.
+    pub fn point(&self, index: usize) -> Point {
+        Point { x: self.xs[index], y: self.ys[index], z: self.zs[index] }
+    }
+
+    /// Calculate the Euclidean distance from the given point to every point of the index, in the same
+    /// order as the points. The result is exactly what `Point::distance_to` returns for each point.
+    pub fn distances_to(&self, given_point: &Point) -> Vec<f64> {
+        let mut distances = Vec::with_capacity(self.len());
+        self.for_each_block(given_point, |_, block| distances.extend(block.iter().map(|distance| distance.sqrt())));
+        distances
+    }
+
+    /// Calls `f` with the position of the first point of each block and the squared distances from the
+    /// given point to the points of the block. The square root is monotonic, so squared distances
+    /// order the points exactly like the distances do.
+    fn for_each_block(&self, given_point: &Point, mut f: impl FnMut(usize, &[f64])) {
+        let mut block = [0.0; LANES];
+        let mut xs = self.xs.chunks_exact(LANES);
+        let mut ys = self.ys.chunks_exact(LANES);
+        let mut zs = self.zs.chunks_exact(LANES);
+        for (n, ((xs, ys), zs)) in xs.by_ref().zip(ys.by_ref()).zip(zs.by_ref()).enumerate() {
+            distance_kernel(given_point, &mut block, xs, ys, zs);
+            f(n * LANES, &block);
+        }
+        let remainder = xs.remainder().len();
+        distance_kernel(given_point, &mut block[..remainder], xs.remainder(), ys.remainder(), zs.remainder());
+        f(self.len() - remainder, &block[..remainder]);
+    }
+}
+
+impl NeighbourSearch for BruteForce {
+    fn nearest(&self, given_point: &Point, k: usize) -> Vec<Neighbour> {
+        if k == 0 {
+            return vec![];
+        }
+        // keep every point nearer than the current k-th nearest candidate, and when too many have been
+        // kept, use a partial selection to cut them back to the k nearest and tighten the bound
+        let capacity = 2 * k + LANES;
+        let mut candidates: Vec<(f64, usize)> = Vec::with_capacity(capacity + LANES);
+        let mut bound = f64::INFINITY;
+        self.for_each_block(given_point, |start, block| {
+            for (i, &distance) in block.iter().enumerate() {
+                if distance < bound {
+                    candidates.push((distance, start + i));
+                }
+            }
+            if candidates.len() >= capacity {
+                candidates.select_nth_unstable_by(k - 1, |a, b| a.0.total_cmp(&b.0));
+                candidates.truncate(k);
+                bound = candidates[k - 1].0;
+            }
+        });
+        if candidates.len() > k {
+            candidates.select_nth_unstable_by(k - 1, |a, b| a.0.total_cmp(&b.0));
+            candidates.truncate(k);
+        }
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+        candidates.into_iter().map(|(distance, i)| Neighbour { distance: distance.sqrt(), point: self.point(i) }).collect()
+    }
+
+    fn within_radius(&self, given_point: &Point, radius: f64) -> Vec<Neighbour> {
+        let mut neighbours = vec![];
+        self.for_each_block(given_point, |start, block| {
+            for (i, distance) in block.iter().enumerate() {
+                let distance = distance.sqrt();
+                if distance <= radius {
+                    neighbours.push(Neighbour { distance, point: self.point(start + i) });
+                }
+            }
+        });
+        neighbours.sort();
+        neighbours
+    }
+}
+
+impl SpatialIndex for BruteForce {
+    fn build(points: Vec<Point>) -> BruteForce {
+        BruteForce::construct_index(points)
+    }
+
+    fn in_box(&self, bounding_box: &BoundingBox) -> Vec<Point> {
+        (0..self.len()).map(|i| self.point(i)).filter(|point| bounding_box.contains(point)).collect()
+    }
+
+    fn len(&self) -> usize {
+        BruteForce::len(self)
+    }
+}
+
+/// Calculate the squared distances from the given point to a block of points given by their coordinates.
+fn distance_kernel(given_point: &Point, distances: &mut [f64], xs: &[f64], ys: &[f64], zs: &[f64]) {
+    // summed in the same order as `Point::squared_distance_to` so the distances match it exactly
+    for (((distance, x), y), z) in distances.iter_mut().zip(xs).zip(ys).zip(zs) {
+        let (dx, dy, dz) = (given_point.x - x, given_point.y - y, given_point.z - z);
+        *distance = dx * dx + dy * dy + dz * dz;
+    }
+}
+
+#[cfg(test)]
+mod brute_force_test {
+    use crate::nearest_neighbour::NeighbourSearch;
+    use crate::point::Point;
+
+    use super::{BruteForce, LANES};
+
+    #[test]
+    fn distances_match_distance_to() {
+        let points = (0..2 * LANES + 3).map(|_| Point::random()).collect::<Vec<Point>>();
+        let given_point = Point::random();
+        let output = BruteForce::construct_index(points.clone()).distances_to(&given_point);
+        let expected = points.iter().map(|p| given_point.distance_to(p)).collect::<Vec<f64>>();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn keeps_order_of_points() {
+        let points = vec![Point::new(0.1, 0.2, 0.3), Point::new(0.4, 0.5, 0.6)];
+        let index = BruteForce::construct_index(points.clone());
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.point(1), points[1]);
+    }
+
+    #[test]
+    fn nearest_matches_sorting_every_point() {
+        let points = (0..1000).map(|_| Point::random()).collect::<Vec<Point>>();
+        let given_point = Point::random();
+        let output = BruteForce::construct_index(points.clone()).nearest(&given_point, 10);
+        let mut expected = points.iter().map(|p| given_point.distance_to(p)).collect::<Vec<f64>>();
+        expected.sort_by(f64::total_cmp);
+        assert_eq!(output.into_iter().map(|n| n.distance).collect::<Vec<f64>>(), &expected[0..10]);
+    }
+
+    #[test]
+    fn nearest_with_k_larger_than_len() {
+        let index = BruteForce::construct_index(vec![Point::new(0.9, 0.9, 0.9), Point::new(0.1, 0.1, 0.1)]);
+        let output = index.nearest(&Point::new(0.0, 0.0, 0.0), 5).into_iter().map(|n| n.point).collect::<Vec<Point>>();
+        let expected = vec![Point::new(0.1, 0.1, 0.1), Point::new(0.9, 0.9, 0.9)];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn nearest_in_empty_index() {
+        let output = BruteForce::construct_index(vec![]).nearest(&Point::new(0.5, 0.5, 0.5), 3);
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn within_radius_includes_boundary() {
+        let index = BruteForce::construct_index(vec![
+            Point::new(0.5, 0.5, 0.5),
+            Point::new(0.75, 0.5, 0.5),
+            Point::new(0.5, 1.0, 0.5),
+        ]);
+        let output = index.within_radius(&Point::new(0.5, 0.5, 0.5), 0.25).into_iter().map(|n| n.point).collect::<Vec<Point>>();
+        let expected = vec![Point::new(0.5, 0.5, 0.5), Point::new(0.75, 0.5, 0.5)];
+        assert_eq!(output, expected);
+    }
+}
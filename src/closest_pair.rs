@@ -0,0 +1,234 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::kd_tree::{KdTreeNode, Subtree};
+use crate::point::Point;
+
+/// Struct that keeps track of a pair of points and the distance between them.
+#[derive(Debug)]
+pub struct PointPair {
+    pub distance: f64,
+    pub first: Point,
+    pub second: Point,
+}
+
+impl Eq for PointPair {}
+
+impl PartialEq<Self> for PointPair {
+    fn eq(&self, other: &Self) -> bool {
+        self.distance.eq(&other.distance)
+    }
+}
+
+impl PartialOrd<Self> for PointPair {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PointPair {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance)
+    }
+}
+
+/// Function to find the closest pair of distinct points within a kd-tree.
+pub fn find_closest_pair(tree: &Option<Box<KdTreeNode>>) -> Option<PointPair> {
+    find_k_closest_pairs(tree, 1).into_iter().next()
+}
+
+/// Function to find the k closest pairs of distinct points within a kd-tree, sorted by distance.
+pub fn find_k_closest_pairs(tree: &Option<Box<KdTreeNode>>, k: usize) -> Vec<PointPair> {
+    let mut closest_pairs = BinaryHeap::new();
+    if let Some(root) = tree {
+        find_pairs_within(&Subtree::root(root), &mut closest_pairs, k);
+    }
+    closest_pairs.into_sorted_vec()
+}
+
+/// Function to find the closest pair made of one point from each of two kd-trees.
+/// The `first` point of the pair comes from the first tree.
+pub fn find_bichromatic_closest_pair(first_tree: &Option<Box<KdTreeNode>>,
+                                     second_tree: &Option<Box<KdTreeNode>>) -> Option<PointPair> {
+    find_k_bichromatic_closest_pairs(first_tree, second_tree, 1).into_iter().next()
+}
+
+/// Function to find the k closest pairs made of one point from each of two kd-trees, sorted by distance.
+/// The `first` point of every pair comes from the first tree.
+pub fn find_k_bichromatic_closest_pairs(first_tree: &Option<Box<KdTreeNode>>,
+                                        second_tree: &Option<Box<KdTreeNode>>, k: usize) -> Vec<PointPair> {
+    let mut closest_pairs = BinaryHeap::new();
+    if let (Some(first_root), Some(second_root)) = (first_tree, second_tree) {
+        find_pairs_between(&Subtree::root(first_root), &Subtree::root(second_root), false, &mut closest_pairs, k);
+    }
+    closest_pairs.into_sorted_vec()
+}
+
+/// Collects the pairs where both points belong to the given subtree.
+fn find_pairs_within(subtree: &Subtree, closest_pairs: &mut BinaryHeap<PointPair>, k: usize) {
+    let [left, right] = subtree.children();
+    for child in left.iter().chain(right.iter()) {
+        find_pairs_with_point(&subtree.node.point, child, false, closest_pairs, k);
+        find_pairs_within(child, closest_pairs, k);
+    }
+    if let (Some(left), Some(right)) = (&left, &right) {
+        find_pairs_between(left, right, false, closest_pairs, k);
+    }
+}
+
+/// Collects the pairs made of one point from each subtree.
+/// The roles of the two subtrees alternate at every level so that both trees are descended together,
+/// and `swapped` records whether `subtree` currently holds the points of the second tree.
+fn find_pairs_between(subtree: &Subtree, other: &Subtree, swapped: bool,
+                      closest_pairs: &mut BinaryHeap<PointPair>, k: usize) {
+    if !is_potential_closer_pair(subtree.bounds.distance_to_box(&other.bounds), closest_pairs, k) {
+        return;
+    }
+    find_pairs_with_point(&subtree.node.point, other, swapped, closest_pairs, k);
+    for child in subtree.children().iter().flatten() {
+        find_pairs_between(other, child, !swapped, closest_pairs, k);
+    }
+}
+
+/// Collects the pairs made of the given point and a point from the subtree.
+fn find_pairs_with_point(point: &Point, subtree: &Subtree, swapped: bool,
+                         closest_pairs: &mut BinaryHeap<PointPair>, k: usize) {
+    if !is_potential_closer_pair(subtree.bounds.distance_to_point(point), closest_pairs, k) {
+        return;
+    }
+    let curr_distance = point.distance_to(&subtree.node.point);
+    if is_potential_closer_pair(curr_distance, closest_pairs, k) {
+        if closest_pairs.len() >= k { closest_pairs.pop(); }
+        let (first, second) = match swapped {
+            false => (point.clone(), subtree.node.point.clone()),
+            true => (subtree.node.point.clone(), point.clone()),
+        };
+        closest_pairs.push(PointPair { distance: curr_distance, first, second });
+    }
+
+    // visit the child on the same side as the point first so the bound shrinks sooner
+    let [left, right] = subtree.children();
+    let (near, far) = match point.compare_dimension(&subtree.node.point, &subtree.dimension) {
+        Ordering::Equal | Ordering::Greater => (right, left),
+        Ordering::Less => (left, right),
+    };
+    for child in near.iter().chain(far.iter()) {
+        find_pairs_with_point(point, child, swapped, closest_pairs, k);
+    }
+}
+
+fn is_potential_closer_pair(distance: f64, closest_pairs: &BinaryHeap<PointPair>, k: usize) -> bool {
+    if closest_pairs.len() < k { return true; }
+    match closest_pairs.peek() {
+        Some(pair) => distance < pair.distance,
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod closest_pair_test {
+    use std::collections::BinaryHeap;
+
+    use crate::kd_tree::KdTreeNode;
+    use crate::point::Point;
+
+    use super::{find_bichromatic_closest_pair, find_closest_pair, find_k_bichromatic_closest_pairs,
+                find_k_closest_pairs, is_potential_closer_pair, PointPair};
+
+    fn tree(points: Vec<Point>) -> Option<Box<KdTreeNode>> {
+        Some(Box::new(KdTreeNode::construct_tree(points)))
+    }
+
+    #[test]
+    fn closest_pair_in_tree() {
+        let tree = tree(vec![
+            Point::new(0.1, 0.1, 0.1),
+            Point::new(0.9, 0.9, 0.9),
+            Point::new(0.5, 0.5, 0.5),
+            Point::new(0.55, 0.5, 0.5),
+            Point::new(0.2, 0.8, 0.3),
+        ]);
+        let output = find_closest_pair(&tree).unwrap();
+        let mut points = vec![output.first, output.second];
+        points.sort_by(|a, b| a.x.total_cmp(&b.x));
+        let expected = vec![Point::new(0.5, 0.5, 0.5), Point::new(0.55, 0.5, 0.5)];
+        assert_eq!(points, expected);
+    }
+
+    #[test]
+    fn closest_pair_in_single_point_tree() {
+        let output = find_closest_pair(&tree(vec![Point::new(0.1, 0.2, 0.3)]));
+        assert_eq!(output, None);
+    }
+
+    #[test]
+    fn closest_pair_in_empty_tree() {
+        let output = find_closest_pair(&None);
+        assert_eq!(output, None);
+    }
+
+    #[test]
+    fn closest_pair_of_duplicate_points() {
+        let tree = tree(vec![
+            Point::new(0.3, 0.3, 0.3),
+            Point::new(0.7, 0.1, 0.2),
+            Point::new(0.3, 0.3, 0.3),
+        ]);
+        let output = find_closest_pair(&tree).unwrap().distance;
+        let expected = 0.0;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn k_closest_pairs_are_sorted() {
+        let tree = tree(vec![
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(0.5, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(1.0, 0.25, 0.0),
+        ]);
+        let output = find_k_closest_pairs(&tree, 3).into_iter().map(|pair| pair.distance).collect::<Vec<f64>>();
+        let expected = vec![0.25, 0.5, 0.5];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn bichromatic_closest_pair_keeps_tree_order() {
+        let first_tree = tree(vec![
+            Point::new(0.1, 0.1, 0.1),
+            Point::new(0.4, 0.4, 0.4),
+        ]);
+        let second_tree = tree(vec![
+            Point::new(0.9, 0.9, 0.9),
+            Point::new(0.5, 0.4, 0.4),
+            Point::new(0.1, 0.1, 0.15),
+        ]);
+        let output = find_bichromatic_closest_pair(&first_tree, &second_tree).unwrap();
+        assert_eq!(output.first, Point::new(0.1, 0.1, 0.1));
+        assert_eq!(output.second, Point::new(0.1, 0.1, 0.15));
+    }
+
+    #[test]
+    fn bichromatic_closest_pairs_with_empty_tree() {
+        let output = find_k_bichromatic_closest_pairs(&tree(vec![Point::new(0.1, 0.1, 0.1)]), &None, 3);
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn bichromatic_pairs_fewer_than_k() {
+        let first_tree = tree(vec![Point::new(0.1, 0.1, 0.1), Point::new(0.2, 0.2, 0.2)]);
+        let second_tree = tree(vec![Point::new(0.3, 0.3, 0.3)]);
+        let output = find_k_bichromatic_closest_pairs(&first_tree, &second_tree, 5).len();
+        let expected = 2;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn is_potential_closer_pair_with_full_heap() {
+        let closest_pairs = BinaryHeap::from(vec![
+            PointPair { distance: 0.1, first: Point::new(0.1, 0.1, 0.1), second: Point::new(0.1, 0.1, 0.2) },
+        ]);
+        assert!(is_potential_closer_pair(0.05, &closest_pairs, 1));
+        assert!(!is_potential_closer_pair(0.1, &closest_pairs, 1));
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use arrow_array::{Array, ArrayRef, Float32Array, Float64Array, RecordBatch, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+
+use crate::formats::{PointFileError, PointPositions};
+use crate::kd_tree::KdTree;
+use crate::nearest_neighbour::Neighbour;
+use crate::point::Point;
+
+/// Names of the columns that hold the coordinates of points.
+const COORDINATE_COLUMNS: [&str; 3] = ["x", "y", "z"];
+
+/// Returns the values of a `Float64` or `Float32` column, reading a `Float64` column's buffer in place.
+fn column_values<'a>(batch: &'a RecordBatch, name: &str, first_row: usize) -> Result<Box<dyn Iterator<Item=f64> + 'a>, PointFileError> {
+    let column = batch.column_by_name(name).ok_or_else(|| PointFileError::Format(format!("record batch has no `{name}` column")))?;
+    if column.null_count() > 0 {
+        let row = (0..column.len()).find(|&i| column.is_null(i)).unwrap_or(0);
+        return Err(PointFileError::Format(format!("column `{name}` is null in row {}", first_row + row)));
+    }
+    if let Some(values) = column.as_any().downcast_ref::<Float64Array>() {
+        return Ok(Box::new(values.values().iter().copied()));
+    }
+    if let Some(values) = column.as_any().downcast_ref::<Float32Array>() {
+        return Ok(Box::new(values.values().iter().map(|&value| value as f64)));
+    }
+    Err(PointFileError::Format(format!("column `{name}` is {} but should be Float64 or Float32", column.data_type())))
+}
+
+/// Reads the points held by the `x`, `y` and `z` columns of the record batches, in order, so that the position of
+/// a point is its row counted across all the batches. The columns may be `Float64` or `Float32` but may not hold nulls,
+/// and any other columns are ignored. `Float64` values are read straight from the batches' buffers, so the points
+/// are the only copy made of them.
+pub fn points_from_batches<'a>(batches: impl IntoIterator<Item=&'a RecordBatch>) -> Result<Vec<Point>, PointFileError> {
+    let mut points = vec![];
+    for batch in batches {
+        let first_row = points.len();
+        let [x, y, z] = COORDINATE_COLUMNS.map(|name| column_values(batch, name, first_row));
+        points.extend(x?.zip(y?).zip(z?).map(|((x, y), z)| Point { x, y, z }));
+    }
+    Ok(points)
+}
+
+impl KdTree {
+    /// Builds a tree over the points held by the `x`, `y` and `z` columns of the record batches, as `points_from_batches` reads them.
+    pub fn construct_tree_from_batches<'a>(batches: impl IntoIterator<Item=&'a RecordBatch>) -> Result<KdTree, PointFileError> {
+        Ok(KdTree::construct_tree(points_from_batches(batches)?))
+    }
+}
+
+/// Returns the schema of the batches made by `neighbours_to_batch`: a non-null `UInt64` `query_id` and `neighbour_id`
+/// and a non-null `Float64` `distance`.
+pub fn neighbour_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("query_id", DataType::UInt64, false),
+        Field::new("neighbour_id", DataType::UInt64, false),
+        Field::new("distance", DataType::Float64, false),
+    ]))
+}
+
+/// Returns the neighbours found for a batch of queries as a record batch with a row for each neighbour, in order,
+/// holding the position of its query, the position of its point in `points` and its distance. A neighbour whose point
+/// appears more than once in `points` gets the position of its first appearance, and neighbours whose point
+/// is not in `points` are an error.
+pub fn neighbours_to_batch(points: &[Point], neighbours: &[Vec<Neighbour>]) -> Result<RecordBatch, PointFileError> {
+    let positions = PointPositions::new(points);
+    let len = neighbours.iter().map(Vec::len).sum();
+    let mut query_ids = Vec::with_capacity(len);
+    let mut neighbour_ids = Vec::with_capacity(len);
+    let mut distances = Vec::with_capacity(len);
+    for (query_id, row) in neighbours.iter().enumerate() {
+        for neighbour in row {
+            let neighbour_id = positions.get(&neighbour.point)
+                .ok_or_else(|| PointFileError::Format(format!("neighbour {:?} is not one of the points", neighbour.point)))?;
+            query_ids.push(query_id as u64);
+            neighbour_ids.push(neighbour_id as u64);
+            distances.push(neighbour.distance);
+        }
+    }
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from(query_ids)),
+        Arc::new(UInt64Array::from(neighbour_ids)),
+        Arc::new(Float64Array::from(distances)),
+    ];
+    RecordBatch::try_new(neighbour_schema(), columns).map_err(|error| PointFileError::Format(error.to_string()))
+}
+
+#[cfg(test)]
+mod arrow_test {
+    use std::sync::Arc;
+
+    use arrow_array::{Array, ArrayRef, Float32Array, Float64Array, Int32Array, RecordBatch, UInt64Array};
+
+    use crate::formats::PointFileError;
+    use crate::nearest_neighbour::Neighbour;
+    use crate::point::Point;
+
+    use super::{neighbour_schema, neighbours_to_batch, points_from_batches};
+
+    fn batch(x: ArrayRef, y: ArrayRef, z: ArrayRef) -> RecordBatch {
+        RecordBatch::try_from_iter([("id", Arc::new(Int32Array::from(vec![0; x.len()])) as ArrayRef), ("x", x), ("y", y), ("z", z)]).unwrap()
+    }
+
+    #[test]
+    fn read_float64_and_float32_columns_across_batches() {
+        let first = batch(Arc::new(Float64Array::from(vec![1.0, 4.0])), Arc::new(Float64Array::from(vec![2.0, 5.0])),
+                          Arc::new(Float64Array::from(vec![3.0, 6.0])));
+        let second = batch(Arc::new(Float32Array::from(vec![0.5])), Arc::new(Float64Array::from(vec![0.25])),
+                           Arc::new(Float32Array::from(vec![0.125])));
+        let output = points_from_batches([&first, &second]).unwrap();
+        let expected = vec![Point { x: 1.0, y: 2.0, z: 3.0 }, Point { x: 4.0, y: 5.0, z: 6.0 }, Point { x: 0.5, y: 0.25, z: 0.125 }];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn reject_missing_null_and_other_columns() {
+        let values = || Arc::new(Float64Array::from(vec![1.0, 2.0])) as ArrayRef;
+        let missing = RecordBatch::try_from_iter([("x", values()), ("y", values())]).unwrap();
+        let output = points_from_batches([&missing]);
+        assert!(matches!(output, Err(PointFileError::Format(message)) if message.contains("`z`")));
+
+        let null = batch(values(), Arc::new(Float64Array::from(vec![Some(1.0), None])), values());
+        let output = points_from_batches([&null]);
+        assert!(matches!(output, Err(PointFileError::Format(message)) if message.contains("row 1")));
+
+        let integers = batch(values(), values(), Arc::new(Int32Array::from(vec![1, 2])));
+        let output = points_from_batches([&integers]);
+        assert!(matches!(output, Err(PointFileError::Format(message)) if message.contains("Int32")));
+    }
+
+    #[test]
+    fn write_neighbours_as_rows() {
+        let points = vec![Point { x: 0.0, y: 0.0, z: 0.0 }, Point { x: 1.0, y: 0.0, z: 0.0 }];
+        let neighbours = vec![
+            vec![Neighbour { point: points[1].clone(), distance: 0.5 }, Neighbour { point: points[0].clone(), distance: 1.5 }],
+            vec![Neighbour { point: points[0].clone(), distance: 0.25 }],
+        ];
+        let output = neighbours_to_batch(&points, &neighbours).unwrap();
+        assert_eq!(output.schema(), neighbour_schema());
+        let column = |i: usize| output.column(i).as_any().downcast_ref::<UInt64Array>().unwrap().values().to_vec();
+        assert_eq!(column(0), vec![0, 0, 1]);
+        assert_eq!(column(1), vec![1, 0, 0]);
+        let distances = output.column(2).as_any().downcast_ref::<Float64Array>().unwrap().values().to_vec();
+        assert_eq!(distances, vec![0.5, 1.5, 0.25]);
+    }
+}
@@ -0,0 +1,299 @@
+use std::io::{self, Read};
+
+use crate::formats::{PayloadPoint, PointFileError};
+use crate::point::Point;
+
+/// Length of the header of LAS 1.0 to 1.2, which later versions extend.
+const MIN_HEADER_LEN: usize = 227;
+
+/// Length of the header of LAS 1.4, which records 64-bit point counts.
+const HEADER_LEN_1_4: usize = 375;
+
+/// Payload values of every point, whatever its format.
+const BASE_PAYLOAD: [&str; 7] = ["intensity", "return_number", "number_of_returns", "classification", "scan_angle", "user_data", "point_source_id"];
+
+/// Struct that represents the points of a LAS file, with their scale and offset applied to their coordinates.
+/// Their payload holds the values named by `payload_names`, which depend on the point format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LasCloud {
+    /// The major and minor version of the file, such as `(1, 4)`.
+    pub version: (u8, u8),
+    /// The point data record format, from 0 to 10.
+    pub point_format: u8,
+    pub scale: [f64; 3],
+    pub offset: [f64; 3],
+    pub points: Vec<PayloadPoint>,
+}
+
+impl LasCloud {
+    /// Returns the names of the payload values of the points, in order. Every format has the intensity, return number,
+    /// number of returns, classification, scan angle in degrees, user data and point source id, followed by
+    /// the GPS time, the red, green and blue channels and the near infrared channel for the formats that record them.
+    pub fn payload_names(&self) -> Vec<&'static str> {
+        payload_names(self.point_format)
+    }
+
+    /// Returns the positions of the points, to build an index over.
+    pub fn positions(&self) -> Vec<Point> {
+        self.points.iter().map(|payload_point| payload_point.point.clone()).collect()
+    }
+}
+
+fn payload_names(point_format: u8) -> Vec<&'static str> {
+    let mut names = BASE_PAYLOAD.to_vec();
+    if has_gps_time(point_format) {
+        names.push("gps_time");
+    }
+    if has_rgb(point_format) {
+        names.extend(["red", "green", "blue"]);
+    }
+    if matches!(point_format, 8 | 10) {
+        names.push("nir");
+    }
+    names
+}
+
+fn has_gps_time(point_format: u8) -> bool {
+    !matches!(point_format, 0 | 2)
+}
+
+fn has_rgb(point_format: u8) -> bool {
+    matches!(point_format, 2 | 3 | 5 | 7 | 8 | 10)
+}
+
+/// Returns the fewest bytes a record of the point format takes, before any extra bytes.
+fn min_record_len(point_format: u8) -> usize {
+    match point_format {
+        0 => 20,
+        1 => 28,
+        2 => 26,
+        3 => 34,
+        4 => 57,
+        5 => 63,
+        6 => 30,
+        7 => 36,
+        8 => 38,
+        9 => 59,
+        _ => 67,
+    }
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap())
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+fn f64_at(bytes: &[u8], at: usize) -> f64 {
+    f64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+fn truncated(error: io::Error, what: &str) -> PointFileError {
+    match error.kind() {
+        io::ErrorKind::UnexpectedEof => PointFileError::Format(format!("LAS file ends in its {what}")),
+        _ => PointFileError::Io(error),
+    }
+}
+
+/// Reads the points of a LAS 1.0 to 1.4 file in any of the point formats 0 to 10, skipping its variable length records
+/// and any extra bytes of its points. LAZ files, whose points are compressed, are recognised and rejected,
+/// since decompressing them is not supported.
+pub fn read_las<R: Read>(mut reader: R) -> Result<LasCloud, PointFileError> {
+    let mut header = vec![0; MIN_HEADER_LEN];
+    reader.read_exact(&mut header).map_err(|error| truncated(error, "header"))?;
+    if &header[..4] != b"LASF" {
+        return Err(PointFileError::Format("not a LAS file".to_string()));
+    }
+    let version = (header[24], header[25]);
+    if version.0 != 1 || version.1 > 4 {
+        return Err(PointFileError::Format(format!("unsupported LAS version {}.{}", version.0, version.1)));
+    }
+    let header_len = u16_at(&header, 94) as usize;
+    let data_offset = u32_at(&header, 96) as usize;
+    if header_len < MIN_HEADER_LEN || data_offset < header_len {
+        return Err(PointFileError::Format(format!("LAS header of {header_len} bytes with points at byte {data_offset} is invalid")));
+    }
+    header.resize(header_len, 0);
+    reader.read_exact(&mut header[MIN_HEADER_LEN..]).map_err(|error| truncated(error, "header"))?;
+
+    // LAZ sets the top bits of the point format to mark its points as compressed
+    let point_format = header[104];
+    if point_format & 0xc0 != 0 {
+        return Err(PointFileError::Format("LAZ compressed points are not supported, decompress the file to LAS first".to_string()));
+    }
+    if point_format > 10 {
+        return Err(PointFileError::Format(format!("unknown LAS point format {point_format}")));
+    }
+    let record_len = u16_at(&header, 105) as usize;
+    if record_len < min_record_len(point_format) {
+        return Err(PointFileError::Format(format!("LAS point format {point_format} needs records of at least {} bytes but they have {record_len}",
+                                                  min_record_len(point_format))));
+    }
+    let legacy_len = u32_at(&header, 107) as u64;
+    let len = match version.1 >= 4 && header_len >= HEADER_LEN_1_4 {
+        true => u64_at(&header, 247).max(legacy_len),
+        false => legacy_len,
+    };
+    let scale = [f64_at(&header, 131), f64_at(&header, 139), f64_at(&header, 147)];
+    let offset = [f64_at(&header, 155), f64_at(&header, 163), f64_at(&header, 171)];
+
+    // the variable length records between the header and the points hold nothing the points need
+    io::copy(&mut (&mut reader).take((data_offset - header_len) as u64), &mut io::sink())?;
+
+    let extended = point_format >= 6;
+    let gps_time_at = if extended { 22 } else { 20 };
+    let rgb_at = match point_format {
+        2 => 20,
+        3 | 5 => 28,
+        _ => 30,
+    };
+    let mut points = Vec::with_capacity(len.min(1 << 20) as usize);
+    let mut record = vec![0; record_len];
+    for i in 0..len {
+        reader.read_exact(&mut record).map_err(|error| truncated(error, &format!("point {i}")))?;
+        let point = Point {
+            x: u32_at(&record, 0) as i32 as f64 * scale[0] + offset[0],
+            y: u32_at(&record, 4) as i32 as f64 * scale[1] + offset[1],
+            z: u32_at(&record, 8) as i32 as f64 * scale[2] + offset[2],
+        };
+        let (return_number, number_of_returns, classification, scan_angle, user_data, point_source_id) = match extended {
+            true => (record[14] & 0x0f, record[14] >> 4, record[16], u16_at(&record, 18) as i16 as f64 * 0.006, record[17], u16_at(&record, 20)),
+            false => (record[14] & 0x07, (record[14] >> 3) & 0x07, record[15] & 0x1f, record[16] as i8 as f64, record[17], u16_at(&record, 18)),
+        };
+        let mut payload = vec![
+            u16_at(&record, 12) as f64,
+            return_number as f64,
+            number_of_returns as f64,
+            classification as f64,
+            scan_angle,
+            user_data as f64,
+            point_source_id as f64,
+        ];
+        if has_gps_time(point_format) {
+            payload.push(f64_at(&record, gps_time_at));
+        }
+        if has_rgb(point_format) {
+            payload.extend((0..3).map(|channel| u16_at(&record, rgb_at + 2 * channel) as f64));
+        }
+        if matches!(point_format, 8 | 10) {
+            payload.push(u16_at(&record, 36) as f64);
+        }
+        points.push(PayloadPoint { point, payload });
+    }
+    Ok(LasCloud { version, point_format, scale, offset, points })
+}
+
+#[cfg(test)]
+mod las_test {
+    use crate::formats::PointFileError;
+    use crate::point::Point;
+
+    use super::{min_record_len, read_las};
+
+    /// Returns a LAS file with one variable length record of 10 bytes and the records given, scaled by 0.01 with no offset.
+    fn las_file(minor: u8, point_format: u8, records: &[Vec<u8>]) -> Vec<u8> {
+        let header_len: u16 = if minor >= 4 { 375 } else { 227 };
+        let mut bytes = vec![0; header_len as usize];
+        bytes[..4].copy_from_slice(b"LASF");
+        bytes[24] = 1;
+        bytes[25] = minor;
+        bytes[94..96].copy_from_slice(&header_len.to_le_bytes());
+        bytes[96..100].copy_from_slice(&(header_len as u32 + 10).to_le_bytes());
+        bytes[100..104].copy_from_slice(&1u32.to_le_bytes());
+        bytes[104] = point_format;
+        bytes[105..107].copy_from_slice(&(records[0].len() as u16).to_le_bytes());
+        match minor >= 4 {
+            true => bytes[247..255].copy_from_slice(&(records.len() as u64).to_le_bytes()),
+            false => bytes[107..111].copy_from_slice(&(records.len() as u32).to_le_bytes()),
+        }
+        for at in [131, 139, 147] {
+            bytes[at..at + 8].copy_from_slice(&0.01f64.to_le_bytes());
+        }
+        bytes.extend([0; 10]);
+        for record in records {
+            bytes.extend(record);
+        }
+        bytes
+    }
+
+    fn record(point_format: u8, coordinates: [i32; 3]) -> Vec<u8> {
+        let mut record = vec![0; min_record_len(point_format)];
+        for (i, coordinate) in coordinates.iter().enumerate() {
+            record[4 * i..4 * i + 4].copy_from_slice(&coordinate.to_le_bytes());
+        }
+        record
+    }
+
+    #[test]
+    fn read_format_1_with_scale() {
+        let mut first = record(1, [100, -250, 5]);
+        first[12..14].copy_from_slice(&900u16.to_le_bytes());
+        first[14] = 2 | 3 << 3;
+        first[15] = 6 | 0x80;
+        first[16] = -12i8 as u8;
+        first[20..28].copy_from_slice(&1.5f64.to_le_bytes());
+        let output = read_las(las_file(2, 1, &[first, record(1, [0, 0, 0])]).as_slice()).unwrap();
+        assert_eq!(output.points.len(), 2);
+        assert_eq!(output.points[0].point, Point { x: 1.0, y: -2.5, z: 0.05 });
+        assert_eq!(output.points[0].payload, vec![900.0, 2.0, 3.0, 6.0, -12.0, 0.0, 0.0, 1.5]);
+        assert_eq!(output.payload_names().len(), output.points[0].payload.len());
+    }
+
+    #[test]
+    fn read_format_8_from_las_1_4() {
+        let mut first = record(8, [1, 2, 3]);
+        first[14] = 4 | 5 << 4;
+        first[16] = 2;
+        first[18..20].copy_from_slice(&1000i16.to_le_bytes());
+        first[30..38].copy_from_slice(&[1, 0, 2, 0, 3, 0, 4, 0]);
+        let output = read_las(las_file(4, 8, &[first]).as_slice()).unwrap();
+        assert_eq!(output.version, (1, 4));
+        assert_eq!(output.points[0].payload, vec![0.0, 4.0, 5.0, 2.0, 6.0, 0.0, 0.0, 0.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(output.payload_names().last(), Some(&"nir"));
+    }
+
+    #[test]
+    fn read_every_point_format() {
+        for point_format in 0..=10 {
+            let output = read_las(las_file(4, point_format, &[record(point_format, [7, 8, 9])]).as_slice()).unwrap();
+            assert_eq!(output.points[0].point, Point { x: 0.07, y: 0.08, z: 0.09 }, "{point_format}");
+            assert_eq!(output.points[0].payload.len(), output.payload_names().len(), "{point_format}");
+        }
+    }
+
+    #[test]
+    fn skip_extra_bytes() {
+        let mut first = record(0, [1, 1, 1]);
+        first.extend([0xff; 6]);
+        let mut second = record(0, [2, 2, 2]);
+        second.extend([0xff; 6]);
+        let output = read_las(las_file(2, 0, &[first, second]).as_slice()).unwrap();
+        assert_eq!(output.points[1].point, Point { x: 0.02, y: 0.02, z: 0.02 });
+    }
+
+    #[test]
+    fn reject_laz() {
+        let output = read_las(las_file(2, 3 | 0x80, &[record(3, [0, 0, 0])]).as_slice());
+        assert!(matches!(output, Err(PointFileError::Format(message)) if message.contains("LAZ")));
+    }
+
+    #[test]
+    fn reject_short_records() {
+        let output = read_las(las_file(2, 3, &[record(1, [0, 0, 0])]).as_slice());
+        assert!(matches!(output, Err(PointFileError::Format(_))));
+    }
+
+    #[test]
+    fn reject_truncated_points() {
+        let mut bytes = las_file(2, 0, &[record(0, [0, 0, 0]), record(0, [0, 0, 0])]);
+        bytes.truncate(bytes.len() - 1);
+        let output = read_las(bytes.as_slice());
+        assert!(matches!(output, Err(PointFileError::Format(message)) if message.contains("point 1")));
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::nearest_neighbour::Neighbour;
+use crate::point::Point;
+
+pub mod text;
+pub mod ply;
+pub mod pcd;
+pub mod las;
+pub mod npy;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+
+/// Struct that represents a point read from a file together with the values of its other fields,
+/// such as colour, normals or intensity, in the order the file declares them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayloadPoint {
+    pub point: Point,
+    pub payload: Vec<f64>,
+}
+
+/// Enum for the ways reading or writing a point file can fail.
+#[derive(Debug)]
+pub enum PointFileError {
+    Io(io::Error),
+    /// A line of a text file could not be parsed, where lines are counted from 1.
+    Parse { line: u64, message: String },
+    /// The file is not laid out as its format requires, or uses a feature of it that is not supported.
+    Format(String),
+}
+
+impl fmt::Display for PointFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointFileError::Io(error) => write!(f, "I/O error: {error}"),
+            PointFileError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            PointFileError::Format(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl Error for PointFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PointFileError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PointFileError {
+    fn from(error: io::Error) -> PointFileError {
+        PointFileError::Io(error)
+    }
+}
+
+/// Struct that finds the position of a point among those it was made from, by the bits of its coordinates,
+/// so that the neighbours found by a search can be traced back to the rows they were read from.
+pub(crate) struct PointPositions(HashMap<[u64; 3], usize>);
+
+impl PointPositions {
+    /// Indexes the points, where a point that appears more than once has the position of its first appearance.
+    pub(crate) fn new<'a>(points: impl IntoIterator<Item=&'a Point>) -> PointPositions {
+        let mut positions = HashMap::new();
+        for (i, point) in points.into_iter().enumerate() {
+            positions.entry(PointPositions::key(point)).or_insert(i);
+        }
+        PointPositions(positions)
+    }
+
+    pub(crate) fn get(&self, point: &Point) -> Option<usize> {
+        self.0.get(&PointPositions::key(point)).copied()
+    }
+
+    fn key(point: &Point) -> [u64; 3] {
+        [point.x.to_bits(), point.y.to_bits(), point.z.to_bits()]
+    }
+}
+
+/// Returns the neighbours with the payload their point has among `points` followed by their distance,
+/// or a payload of `payload_len` zeros if their point is not among them.
+pub(crate) fn select_payloads(points: &[PayloadPoint], payload_len: usize, neighbours: &[Neighbour]) -> Vec<PayloadPoint> {
+    let positions = PointPositions::new(points.iter().map(|payload_point| &payload_point.point));
+    neighbours.iter()
+        .map(|neighbour| {
+            let mut payload = match positions.get(&neighbour.point) {
+                Some(i) => points[i].payload.clone(),
+                None => vec![0.0; payload_len],
+            };
+            payload.push(neighbour.distance);
+            PayloadPoint { point: neighbour.point.clone(), payload }
+        })
+        .collect()
+}
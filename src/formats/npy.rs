@@ -0,0 +1,306 @@
+use std::io::{BufWriter, Read, Write};
+#[cfg(feature = "npz")]
+use std::io::Seek;
+
+use crate::formats::{PointFileError, PointPositions};
+use crate::nearest_neighbour::Neighbour;
+use crate::point::Point;
+
+/// Bytes that every `.npy` file starts with.
+const MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// Enum for the element types of arrays that can be read as points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NpyType {
+    F32,
+    F64,
+}
+
+/// Struct that represents the header of a `.npy` file, which describes the array that follows it.
+#[derive(Debug, PartialEq)]
+struct NpyHeader {
+    element: NpyType,
+    big_endian: bool,
+    fortran_order: bool,
+    shape: Vec<usize>,
+}
+
+/// Reads an `(N, 3)` array of `float32` or `float64` from a `.npy` file as points, whichever the byte order of
+/// its elements and whether it is stored in C or Fortran order.
+pub fn read_npy_points<R: Read>(mut reader: R) -> Result<Vec<Point>, PointFileError> {
+    let header = read_header(&mut reader)?;
+    let len = match header.shape.as_slice() {
+        [len, 3] => *len,
+        shape => return Err(PointFileError::Format(format!("expected an array of shape (N, 3) but found {shape:?}"))),
+    };
+    let size = match header.element {
+        NpyType::F32 => 4,
+        NpyType::F64 => 8,
+    };
+    let data_len = len.checked_mul(3 * size)
+        .ok_or_else(|| PointFileError::Format(format!("an array of {len} points is too large to read")))?;
+    // the shape in the header is not trusted to size the buffer, which only grows as the data arrives
+    let mut data = Vec::with_capacity(data_len.min(1 << 20));
+    reader.take(data_len as u64).read_to_end(&mut data)?;
+    if data.len() != data_len {
+        return Err(PointFileError::Format(".npy file ends in its data".to_string()));
+    }
+    let value = |index: usize| {
+        let bytes = &data[index * size..(index + 1) * size];
+        match (header.element, header.big_endian) {
+            (NpyType::F32, false) => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            (NpyType::F32, true) => f32::from_be_bytes(bytes.try_into().unwrap()) as f64,
+            (NpyType::F64, false) => f64::from_le_bytes(bytes.try_into().unwrap()),
+            (NpyType::F64, true) => f64::from_be_bytes(bytes.try_into().unwrap()),
+        }
+    };
+    // in Fortran order every x comes first, then every y and then every z
+    let index = |row: usize, column: usize| match header.fortran_order {
+        true => column * len + row,
+        false => row * 3 + column,
+    };
+    Ok((0..len).map(|row| Point { x: value(index(row, 0)), y: value(index(row, 1)), z: value(index(row, 2)) }).collect())
+}
+
+/// Reads the `(N, 3)` array stored under `name` in a `.npz` archive, as written by `numpy.savez` or `numpy.savez_compressed`,
+/// as points. Arrays passed to `savez` without a keyword are named `arr_0`, `arr_1` and so on.
+#[cfg(feature = "npz")]
+pub fn read_npz_points<R: Read + Seek>(reader: R, name: &str) -> Result<Vec<Point>, PointFileError> {
+    let zip_error = |error: zip::result::ZipError| match error {
+        zip::result::ZipError::Io(error) => PointFileError::Io(error),
+        error => PointFileError::Format(format!("could not read .npz archive: {error}")),
+    };
+    let mut archive = zip::ZipArchive::new(reader).map_err(zip_error)?;
+    let entry = format!("{name}.npy");
+    if archive.index_for_name(&entry).is_none() {
+        let names = archive.file_names().filter_map(|file| file.strip_suffix(".npy")).collect::<Vec<&str>>().join(", ");
+        return Err(PointFileError::Format(format!("no array named `{name}` in the .npz archive, which holds {names}")));
+    }
+    let file = archive.by_name(&entry).map_err(zip_error)?;
+    read_npy_points(file)
+}
+
+/// Writes the points as an `(N, 3)` array of little-endian `float64` in C order.
+pub fn write_npy_points<'a, W: Write>(writer: W, points: impl ExactSizeIterator<Item=&'a Point>) -> Result<(), PointFileError> {
+    let mut writer = BufWriter::new(writer);
+    write_header(&mut writer, "<f8", &[points.len(), 3])?;
+    for point in points {
+        for value in [point.x, point.y, point.z] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the positions in `points` of the neighbours found for each of a batch of queries as a `(queries, k)` array
+/// of little-endian `int64`, where `k` is the most neighbours any query has. Like `scipy.spatial.KDTree.query`,
+/// rows with fewer neighbours are padded with the number of points, and a neighbour whose point appears more than
+/// once in `points` gets the position of its first appearance. Neighbours whose point is not in `points` are an error.
+pub fn write_npy_neighbour_indices<W: Write>(writer: W, points: &[Point], neighbours: &[Vec<Neighbour>]) -> Result<(), PointFileError> {
+    let positions = PointPositions::new(points);
+    let k = neighbours.iter().map(Vec::len).max().unwrap_or(0);
+    let mut writer = BufWriter::new(writer);
+    write_header(&mut writer, "<i8", &[neighbours.len(), k])?;
+    for row in neighbours {
+        for neighbour in row {
+            let index = positions.get(&neighbour.point)
+                .ok_or_else(|| PointFileError::Format(format!("neighbour {:?} is not one of the points", neighbour.point)))?;
+            writer.write_all(&(index as i64).to_le_bytes())?;
+        }
+        for _ in row.len()..k {
+            writer.write_all(&(points.len() as i64).to_le_bytes())?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the distances of the neighbours found for each of a batch of queries as a `(queries, k)` array of little-endian
+/// `float64`, in the same layout as `write_npy_neighbour_indices`, with rows padded by infinity.
+pub fn write_npy_neighbour_distances<W: Write>(writer: W, neighbours: &[Vec<Neighbour>]) -> Result<(), PointFileError> {
+    let k = neighbours.iter().map(Vec::len).max().unwrap_or(0);
+    let mut writer = BufWriter::new(writer);
+    write_header(&mut writer, "<f8", &[neighbours.len(), k])?;
+    for row in neighbours {
+        for distance in row.iter().map(|neighbour| neighbour.distance).chain(std::iter::repeat(f64::INFINITY)).take(k) {
+            writer.write_all(&distance.to_le_bytes())?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes a version 1.0 header, padded with spaces so that the data starts at a multiple of 64 bytes as NumPy does.
+fn write_header<W: Write>(writer: &mut W, descr: &str, shape: &[usize]) -> Result<(), PointFileError> {
+    let shape = match shape {
+        [len] => format!("({len},)"),
+        _ => format!("({})", shape.iter().map(usize::to_string).collect::<Vec<String>>().join(", ")),
+    };
+    let mut dict = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+    let unpadded = MAGIC.len() + 4 + dict.len() + 1;
+    dict.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    dict.push('\n');
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[1, 0])?;
+    writer.write_all(&(dict.len() as u16).to_le_bytes())?;
+    writer.write_all(dict.as_bytes())?;
+    Ok(())
+}
+
+fn read_header<R: Read>(reader: &mut R) -> Result<NpyHeader, PointFileError> {
+    let mut start = [0; 8];
+    reader.read_exact(&mut start)?;
+    if &start[..6] != MAGIC {
+        return Err(PointFileError::Format("not a .npy file".to_string()));
+    }
+    let header_len = match start[6] {
+        1 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            u16::from_le_bytes(len) as usize
+        }
+        2 | 3 => {
+            let mut len = [0; 4];
+            reader.read_exact(&mut len)?;
+            u32::from_le_bytes(len) as usize
+        }
+        major => return Err(PointFileError::Format(format!("unsupported .npy version {major}.{}", start[7]))),
+    };
+    let mut dict = Vec::with_capacity(header_len.min(1 << 20));
+    reader.take(header_len as u64).read_to_end(&mut dict)?;
+    if dict.len() != header_len {
+        return Err(PointFileError::Format(".npy file ends in its header".to_string()));
+    }
+    let dict = String::from_utf8_lossy(&dict);
+    parse_header(&dict).ok_or_else(|| PointFileError::Format(format!("could not parse .npy header `{}`", dict.trim())))
+}
+
+/// Parses the Python dictionary literal of a header, returning `None` if a key is missing or has a value that is not supported.
+fn parse_header(dict: &str) -> Option<NpyHeader> {
+    let value_of = |key: &str| {
+        let start = dict.find(&format!("'{key}'"))? + key.len() + 2;
+        dict[start..].trim_start().strip_prefix(':').map(str::trim_start)
+    };
+    let descr = value_of("descr")?.strip_prefix('\'')?.split('\'').next()?;
+    let big_endian = match descr.as_bytes().first()? {
+        b'<' => false,
+        b'>' => true,
+        b'=' => cfg!(target_endian = "big"),
+        _ => return None,
+    };
+    let element = match &descr[1..] {
+        "f4" => NpyType::F32,
+        "f8" => NpyType::F64,
+        _ => return None,
+    };
+    let fortran_order = value_of("fortran_order")?;
+    let fortran_order = match () {
+        _ if fortran_order.starts_with("True") => true,
+        _ if fortran_order.starts_with("False") => false,
+        _ => return None,
+    };
+    let shape = value_of("shape")?.strip_prefix('(')?.split(')').next()?
+        .split(',')
+        .map(str::trim)
+        .filter(|dimension| !dimension.is_empty())
+        .map(|dimension| dimension.trim_end_matches('L').parse().ok())
+        .collect::<Option<Vec<usize>>>()?;
+    Some(NpyHeader { element, big_endian, fortran_order, shape })
+}
+
+#[cfg(test)]
+mod npy_test {
+    use crate::formats::PointFileError;
+    use crate::nearest_neighbour::Neighbour;
+    use crate::point::Point;
+
+    use super::{NpyHeader, NpyType, parse_header, read_npy_points, write_npy_neighbour_distances, write_npy_neighbour_indices, write_npy_points};
+
+    /// Returns a `.npy` file of version 1.0 with the header and data given.
+    fn npy_file(dict: &str, data: &[u8]) -> Vec<u8> {
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend((dict.len() as u16).to_le_bytes());
+        bytes.extend(dict.as_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    #[test]
+    fn parse_header_of_numpy() {
+        let output = parse_header("{'descr': '>f4', 'fortran_order': True, 'shape': (12, 3), }          \n");
+        let expected = Some(NpyHeader { element: NpyType::F32, big_endian: true, fortran_order: true, shape: vec![12, 3] });
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn read_c_order_f32() {
+        let data = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0].iter().flat_map(|value| value.to_le_bytes()).collect::<Vec<u8>>();
+        let output = read_npy_points(npy_file("{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3), }\n", &data).as_slice()).unwrap();
+        let expected = vec![Point { x: 1.0, y: 2.0, z: 3.0 }, Point { x: 4.0, y: 5.0, z: 6.0 }];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn read_fortran_order_big_endian_f64() {
+        let data = [1.0f64, 4.0, 2.0, 5.0, 3.0, 6.0].iter().flat_map(|value| value.to_be_bytes()).collect::<Vec<u8>>();
+        let output = read_npy_points(npy_file("{'descr': '>f8', 'fortran_order': True, 'shape': (2, 3), }\n", &data).as_slice()).unwrap();
+        let expected = vec![Point { x: 1.0, y: 2.0, z: 3.0 }, Point { x: 4.0, y: 5.0, z: 6.0 }];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn reject_truncated_data() {
+        let data = [1.0f64, 2.0, 3.0, 4.0].iter().flat_map(|value| value.to_le_bytes()).collect::<Vec<u8>>();
+        let output = read_npy_points(npy_file("{'descr': '<f8', 'fortran_order': False, 'shape': (2, 3), }\n", &data).as_slice());
+        assert!(matches!(output, Err(PointFileError::Format(message)) if message.contains("ends in its data")));
+    }
+
+    #[test]
+    fn reject_shapes_larger_than_the_file() {
+        let huge = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, 3), }}\n", 1u64 << 40);
+        let output = read_npy_points(npy_file(&huge, &[0; 24]).as_slice());
+        assert!(matches!(output, Err(PointFileError::Format(message)) if message.contains("ends in its data")));
+
+        let overflowing = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, 3), }}\n", usize::MAX);
+        let output = read_npy_points(npy_file(&overflowing, &[]).as_slice());
+        assert!(matches!(output, Err(PointFileError::Format(message)) if message.contains("too large")));
+    }
+
+    #[test]
+    fn reject_other_shapes_and_types() {
+        let output = read_npy_points(npy_file("{'descr': '<f8', 'fortran_order': False, 'shape': (6,), }\n", &[0; 48]).as_slice());
+        assert!(matches!(output, Err(PointFileError::Format(message)) if message.contains("shape")));
+        let output = read_npy_points(npy_file("{'descr': '<i8', 'fortran_order': False, 'shape': (2, 3), }\n", &[0; 48]).as_slice());
+        assert!(matches!(output, Err(PointFileError::Format(_))));
+    }
+
+    #[test]
+    fn write_points_aligned_and_read_back() {
+        let points = vec![Point { x: 0.1, y: -2.0, z: 1e300 }, Point { x: 3.0, y: 4.0, z: 5.0 }];
+        let mut bytes = vec![];
+        write_npy_points(&mut bytes, points.iter()).unwrap();
+        assert_eq!((bytes.len() - 48) % 64, 0);
+        let output = read_npy_points(bytes.as_slice()).unwrap();
+        assert_eq!(output, points);
+    }
+
+    #[test]
+    fn write_neighbours_padded_like_scipy() {
+        let points = vec![Point { x: 0.0, y: 0.0, z: 0.0 }, Point { x: 1.0, y: 0.0, z: 0.0 }];
+        let neighbours = vec![
+            vec![Neighbour { point: points[1].clone(), distance: 0.5 }, Neighbour { point: points[0].clone(), distance: 1.5 }],
+            vec![Neighbour { point: points[0].clone(), distance: 0.25 }],
+        ];
+        let mut indices = vec![];
+        write_npy_neighbour_indices(&mut indices, &points, &neighbours).unwrap();
+        let mut distances = vec![];
+        write_npy_neighbour_distances(&mut distances, &neighbours).unwrap();
+        let data_start = |bytes: &[u8]| 10 + u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert!(String::from_utf8_lossy(&indices[..data_start(&indices)]).contains("'descr': '<i8', 'fortran_order': False, 'shape': (2, 2)"));
+        let output = indices[data_start(&indices)..].chunks(8).map(|bytes| i64::from_le_bytes(bytes.try_into().unwrap())).collect::<Vec<i64>>();
+        assert_eq!(output, vec![1, 0, 0, 2]);
+        let output = distances[data_start(&distances)..].chunks(8).map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap())).collect::<Vec<f64>>();
+        assert_eq!(output, vec![0.5, 1.5, 0.25, f64::INFINITY]);
+    }
+}
@@ -0,0 +1,630 @@
+use std::io::{self, BufRead, BufWriter, Read, Write};
+
+use crate::formats::{PayloadPoint, PointFileError, select_payloads};
+use crate::nearest_neighbour::Neighbour;
+use crate::point::Point;
+
+/// Enum for the ways the data of a PCD file can be stored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PcdEncoding {
+    /// One point per line of text.
+    Ascii,
+    /// Little-endian records of every field of a point, one point after another.
+    Binary,
+    /// Every value of one field after another, compressed with LZF.
+    BinaryCompressed,
+}
+
+/// Enum for the kinds of numbers a PCD field holds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PcdKind {
+    Signed,
+    Unsigned,
+    Float,
+}
+
+/// Struct that represents a field of a PCD file, which holds `count` numbers of `size` bytes for every point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PcdField {
+    pub name: String,
+    pub kind: PcdKind,
+    pub size: usize,
+    pub count: usize,
+}
+
+impl PcdField {
+    pub fn new(name: &str, kind: PcdKind, size: usize, count: usize) -> PcdField {
+        PcdField { name: name.to_string(), kind, size, count }
+    }
+
+    fn is_coordinate(&self) -> bool {
+        matches!(self.name.as_str(), "x" | "y" | "z")
+    }
+
+    fn is_supported(&self) -> bool {
+        match self.kind {
+            PcdKind::Signed | PcdKind::Unsigned => matches!(self.size, 1 | 2 | 4 | 8),
+            PcdKind::Float => matches!(self.size, 4 | 8),
+        }
+    }
+
+    /// Returns true for the `rgb` and `rgba` fields of PCL, whose 4-byte floats hold packed colour channels.
+    fn is_packed_colour(&self) -> bool {
+        self.kind == PcdKind::Float && self.size == 4 && matches!(self.name.as_str(), "rgb" | "rgba")
+    }
+
+    fn type_letter(&self) -> char {
+        match self.kind {
+            PcdKind::Signed => 'I',
+            PcdKind::Unsigned => 'U',
+            PcdKind::Float => 'F',
+        }
+    }
+}
+
+/// Struct that represents the points of a PCD file, whose fields other than `x`, `y` and `z` become their payload.
+/// The payload holds every number of those fields in their order, including the packed `rgb` floats and any `_` padding,
+/// which `f64` keeps exactly except for 64-bit integers beyond 2^53. 4-byte floats keep their bits even when they are
+/// NaNs, as packed colours with an alpha of 255 often are, and ASCII data holds `rgb` and `rgba` as the integer of
+/// their bits, like PCL writes them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PcdCloud {
+    pub encoding: PcdEncoding,
+    pub fields: Vec<PcdField>,
+    pub points: Vec<PayloadPoint>,
+}
+
+impl PcdCloud {
+    /// Creates a cloud of points without payloads, stored as 8-byte float coordinates.
+    pub fn from_points(points: impl IntoIterator<Item=Point>, encoding: PcdEncoding) -> PcdCloud {
+        PcdCloud {
+            encoding,
+            fields: ["x", "y", "z"].into_iter().map(|name| PcdField::new(name, PcdKind::Float, 8, 1)).collect(),
+            points: points.into_iter().map(|point| PayloadPoint { point, payload: vec![] }).collect(),
+        }
+    }
+
+    /// Returns the positions of the points, to build an index over.
+    pub fn positions(&self) -> Vec<Point> {
+        self.points.iter().map(|payload_point| payload_point.point.clone()).collect()
+    }
+
+    /// Returns a cloud of the neighbours found by a search of this cloud's points, in their order, with the payload
+    /// each point has in this cloud followed by a `distance` field. Neighbours whose point is not in the cloud
+    /// get a payload of zeros.
+    pub fn select(&self, neighbours: &[Neighbour]) -> PcdCloud {
+        let mut fields = self.fields.clone();
+        fields.push(PcdField::new("distance", PcdKind::Float, 8, 1));
+        let points = select_payloads(&self.points, self.payload_len(), neighbours);
+        PcdCloud { encoding: self.encoding, fields, points }
+    }
+
+    fn payload_len(&self) -> usize {
+        self.fields.iter().filter(|field| !field.is_coordinate()).map(|field| field.count).sum()
+    }
+}
+
+/// Reads the points of a PCD file in any of its encodings.
+pub fn read_pcd<R: BufRead>(mut reader: R) -> Result<PcdCloud, PointFileError> {
+    let (fields, len, encoding, mut line) = read_header(&mut reader)?;
+    let record_len = fields.iter()
+        .try_fold(0usize, |record_len, field| field.size.checked_mul(field.count).and_then(|len| record_len.checked_add(len)))
+        .ok_or_else(|| PointFileError::Format("PCD fields hold too many values".to_string()))?;
+    let mut points = Vec::with_capacity(len.min(1 << 20));
+    let mut values = vec![];
+    match encoding {
+        PcdEncoding::Ascii => {
+            let value_fields = fields.iter().flat_map(|field| std::iter::repeat_n(field, field.count)).collect::<Vec<&PcdField>>();
+            let mut text = String::new();
+            while points.len() < len {
+                text.clear();
+                line += 1;
+                if reader.read_line(&mut text)? == 0 {
+                    return Err(PointFileError::Parse { line, message: format!("file ends after {} of {len} points", points.len()) });
+                }
+                if text.trim().is_empty() { continue; }
+                values.clear();
+                for (i, word) in text.split_whitespace().enumerate() {
+                    let value = match value_fields.get(i) {
+                        Some(field) if field.is_packed_colour() => word.parse::<u32>().map(f32_bits_to_f64).or_else(|_| word.parse::<f64>()),
+                        _ => word.parse::<f64>(),
+                    };
+                    values.push(value.map_err(|_| PointFileError::Parse { line, message: format!("could not parse `{word}` as a number") })?);
+                }
+                if values.len() != record_len_in_values(&fields) {
+                    return Err(PointFileError::Parse { line, message: format!("expected {} values but found {}", record_len_in_values(&fields), values.len()) });
+                }
+                points.push(to_payload_point(&fields, &values));
+            }
+        }
+        PcdEncoding::Binary => {
+            let mut record = vec![0; record_len];
+            for i in 0..len {
+                reader.read_exact(&mut record).map_err(|error| truncated(error, i))?;
+                values.clear();
+                let mut offset = 0;
+                for field in &fields {
+                    for _ in 0..field.count {
+                        values.push(decode(&record[offset..offset + field.size], field));
+                        offset += field.size;
+                    }
+                }
+                points.push(to_payload_point(&fields, &values));
+            }
+        }
+        PcdEncoding::BinaryCompressed => {
+            let mut sizes = [0; 8];
+            reader.read_exact(&mut sizes).map_err(|error| truncated(error, 0))?;
+            let compressed_len = u32::from_le_bytes(sizes[..4].try_into().unwrap()) as usize;
+            let uncompressed_len = u32::from_le_bytes(sizes[4..].try_into().unwrap()) as usize;
+            let data_len = record_len.checked_mul(len)
+                .ok_or_else(|| PointFileError::Format(format!("{len} points of {record_len} bytes are too many for compressed PCD data")))?;
+            if uncompressed_len != data_len {
+                return Err(PointFileError::Format(format!("compressed PCD data holds {uncompressed_len} bytes but {len} points take {data_len}")));
+            }
+            let mut compressed = vec![];
+            reader.take(compressed_len as u64).read_to_end(&mut compressed)?;
+            if compressed.len() != compressed_len {
+                return Err(PointFileError::Format("PCD file ends in its compressed data".to_string()));
+            }
+            let data = lzf_decompress(&compressed, uncompressed_len)?;
+            // the data holds every value of the first field, then every value of the second, and so on
+            let mut field_offsets = vec![];
+            let mut offset = 0;
+            for field in &fields {
+                field_offsets.push(offset);
+                offset += field.size * field.count * len;
+            }
+            for i in 0..len {
+                values.clear();
+                for (field, field_offset) in fields.iter().zip(&field_offsets) {
+                    let start = field_offset + i * field.size * field.count;
+                    for j in 0..field.count {
+                        values.push(decode(&data[start + j * field.size..start + (j + 1) * field.size], field));
+                    }
+                }
+                points.push(to_payload_point(&fields, &values));
+            }
+        }
+    }
+    Ok(PcdCloud { encoding, fields, points })
+}
+
+fn record_len_in_values(fields: &[PcdField]) -> usize {
+    fields.iter().map(|field| field.count).sum()
+}
+
+fn truncated(error: io::Error, point: usize) -> PointFileError {
+    match error.kind() {
+        io::ErrorKind::UnexpectedEof => PointFileError::Format(format!("PCD file ends in point {point}")),
+        _ => PointFileError::Io(error),
+    }
+}
+
+fn to_payload_point(fields: &[PcdField], values: &[f64]) -> PayloadPoint {
+    let mut point = Point { x: 0.0, y: 0.0, z: 0.0 };
+    let mut payload = Vec::with_capacity(values.len() - 3);
+    let mut values = values.iter();
+    for field in fields {
+        match field.name.as_str() {
+            "x" => point.x = *values.next().unwrap(),
+            "y" => point.y = *values.next().unwrap(),
+            "z" => point.z = *values.next().unwrap(),
+            _ => payload.extend(values.by_ref().take(field.count)),
+        }
+    }
+    PayloadPoint { point, payload }
+}
+
+/// Reads the header up to and including its `DATA` line, returning the fields, the number of points,
+/// the encoding and the number of lines read.
+fn read_header<R: BufRead>(reader: &mut R) -> Result<(Vec<PcdField>, usize, PcdEncoding, u64), PointFileError> {
+    let mut names = vec![];
+    let mut sizes = vec![];
+    let mut types = vec![];
+    let mut counts = vec![];
+    let mut width = None;
+    let mut height = None;
+    let mut len = None;
+    let mut line = 0;
+    let mut text = String::new();
+    let encoding = loop {
+        text.clear();
+        line += 1;
+        if reader.read_line(&mut text)? == 0 {
+            return Err(PointFileError::Parse { line, message: "PCD header has no DATA line".to_string() });
+        }
+        let error = |message: String| PointFileError::Parse { line, message };
+        let numbers = |words: &[&str]| words.iter()
+            .map(|word| word.parse::<usize>().map_err(|_| error(format!("could not parse `{word}` as a number"))))
+            .collect::<Result<Vec<usize>, PointFileError>>();
+        let words = text.split_whitespace().collect::<Vec<&str>>();
+        match words.as_slice() {
+            [] => {}
+            [word, ..] if word.starts_with('#') => {}
+            ["VERSION", ..] | ["VIEWPOINT", ..] => {}
+            ["FIELDS", fields @ ..] => names = fields.iter().map(|name| name.to_string()).collect(),
+            ["SIZE", values @ ..] => sizes = numbers(values)?,
+            ["COUNT", values @ ..] => counts = numbers(values)?,
+            ["TYPE", values @ ..] => {
+                types = values.iter()
+                    .map(|value| match *value {
+                        "I" => Ok(PcdKind::Signed),
+                        "U" => Ok(PcdKind::Unsigned),
+                        "F" => Ok(PcdKind::Float),
+                        _ => Err(error(format!("unknown field type `{value}`"))),
+                    })
+                    .collect::<Result<Vec<PcdKind>, PointFileError>>()?;
+            }
+            ["WIDTH", value] => width = Some(numbers(&[value])?[0]),
+            ["HEIGHT", value] => height = Some(numbers(&[value])?[0]),
+            ["POINTS", value] => len = Some(numbers(&[value])?[0]),
+            ["DATA", "ascii"] => break PcdEncoding::Ascii,
+            ["DATA", "binary"] => break PcdEncoding::Binary,
+            ["DATA", "binary_compressed"] => break PcdEncoding::BinaryCompressed,
+            ["DATA", data] => return Err(error(format!("unknown PCD data encoding `{data}`"))),
+            _ => return Err(error(format!("could not parse PCD header line `{}`", text.trim()))),
+        }
+    };
+
+    if counts.is_empty() {
+        counts = vec![1; names.len()];
+    }
+    if sizes.len() != names.len() || types.len() != names.len() || counts.len() != names.len() {
+        return Err(PointFileError::Format("PCD header has a different number of FIELDS, SIZE, TYPE and COUNT values".to_string()));
+    }
+    let fields = names.iter().zip(types).zip(sizes).zip(counts)
+        .map(|(((name, kind), size), count)| PcdField::new(name, kind, size, count))
+        .collect::<Vec<PcdField>>();
+    for field in &fields {
+        if !field.is_supported() {
+            return Err(PointFileError::Format(format!("field `{}` of type {}{} is not supported", field.name, field.type_letter(), field.size)));
+        }
+    }
+    for name in ["x", "y", "z"] {
+        match fields.iter().find(|field| field.name == name) {
+            Some(field) if field.count == 1 => {}
+            Some(_) => return Err(PointFileError::Format(format!("field `{name}` holds more than one number"))),
+            None => return Err(PointFileError::Format(format!("PCD file has no `{name}` field"))),
+        }
+    }
+    let len = match (len, width, height) {
+        (Some(len), _, _) => len,
+        (None, Some(width), Some(height)) => width.checked_mul(height)
+            .ok_or_else(|| PointFileError::Format(format!("PCD header gives a WIDTH of {width} and HEIGHT of {height}, which are too many points")))?,
+        _ => return Err(PointFileError::Format("PCD header gives neither POINTS nor WIDTH and HEIGHT".to_string())),
+    };
+    Ok((fields, len, encoding, line))
+}
+
+fn decode(bytes: &[u8], field: &PcdField) -> f64 {
+    match (field.kind, field.size) {
+        (PcdKind::Signed, 1) => bytes[0] as i8 as f64,
+        (PcdKind::Signed, 2) => i16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        (PcdKind::Signed, 4) => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        (PcdKind::Signed, _) => i64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        (PcdKind::Unsigned, 1) => bytes[0] as f64,
+        (PcdKind::Unsigned, 2) => u16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        (PcdKind::Unsigned, 4) => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        (PcdKind::Unsigned, _) => u64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        (PcdKind::Float, 4) => f32_bits_to_f64(u32::from_le_bytes(bytes.try_into().unwrap())),
+        (PcdKind::Float, _) => f64::from_le_bytes(bytes.try_into().unwrap()),
+    }
+}
+
+/// Widens the bits of a 4-byte float to an `f64`. NaNs are widened bit by bit rather than with `as`,
+/// which quiets signalling NaNs on some targets and so would change the colours packed into them.
+fn f32_bits_to_f64(bits: u32) -> f64 {
+    if bits & 0x7fff_ffff <= 0x7f80_0000 {
+        return f32::from_bits(bits) as f64;
+    }
+    let sign = (bits >> 31) as u64;
+    let mantissa = (bits & 0x7f_ffff) as u64;
+    f64::from_bits(sign << 63 | 0x7ff << 52 | mantissa << 29)
+}
+
+/// Narrows an `f64` read by `f32_bits_to_f64` back to the bits of a 4-byte float, keeping the bits of NaNs.
+fn f64_to_f32_bits(value: f64) -> u32 {
+    if !value.is_nan() {
+        return (value as f32).to_bits();
+    }
+    let bits = value.to_bits();
+    ((bits >> 63) as u32) << 31 | 0xff << 23 | ((bits >> 29) & 0x7f_ffff) as u32
+}
+
+fn encode(value: f64, field: &PcdField, bytes: &mut Vec<u8>) {
+    match (field.kind, field.size) {
+        (PcdKind::Signed, 1) => bytes.extend_from_slice(&(value as i8).to_le_bytes()),
+        (PcdKind::Signed, 2) => bytes.extend_from_slice(&(value as i16).to_le_bytes()),
+        (PcdKind::Signed, 4) => bytes.extend_from_slice(&(value as i32).to_le_bytes()),
+        (PcdKind::Signed, _) => bytes.extend_from_slice(&(value as i64).to_le_bytes()),
+        (PcdKind::Unsigned, 1) => bytes.extend_from_slice(&(value as u8).to_le_bytes()),
+        (PcdKind::Unsigned, 2) => bytes.extend_from_slice(&(value as u16).to_le_bytes()),
+        (PcdKind::Unsigned, 4) => bytes.extend_from_slice(&(value as u32).to_le_bytes()),
+        (PcdKind::Unsigned, _) => bytes.extend_from_slice(&(value as u64).to_le_bytes()),
+        (PcdKind::Float, 4) => bytes.extend_from_slice(&f64_to_f32_bits(value).to_le_bytes()),
+        (PcdKind::Float, _) => bytes.extend_from_slice(&value.to_le_bytes()),
+    }
+}
+
+/// Returns the values of a point for every field in order, taking the coordinates from its position.
+fn field_values<'a>(fields: &'a [PcdField], payload_point: &'a PayloadPoint) -> impl Iterator<Item=(&'a PcdField, f64)> + 'a {
+    let mut payload = payload_point.payload.iter();
+    fields.iter().flat_map(move |field| {
+        let values = match field.name.as_str() {
+            "x" => vec![payload_point.point.x],
+            "y" => vec![payload_point.point.y],
+            "z" => vec![payload_point.point.z],
+            _ => payload.by_ref().take(field.count).copied().collect(),
+        };
+        values.into_iter().map(move |value| (field, value))
+    })
+}
+
+/// Writes the cloud as an unorganised PCD file in its encoding, with a `HEIGHT` of 1 and the default viewpoint.
+/// Values are converted to the type of their field, so they should fit in it.
+pub fn write_pcd<W: Write>(writer: W, cloud: &PcdCloud) -> Result<(), PointFileError> {
+    for field in &cloud.fields {
+        if !field.is_supported() {
+            return Err(PointFileError::Format(format!("field `{}` of type {}{} is not supported", field.name, field.type_letter(), field.size)));
+        }
+    }
+    let payload_len = cloud.payload_len();
+    if let Some(i) = cloud.points.iter().position(|payload_point| payload_point.payload.len() != payload_len) {
+        return Err(PointFileError::Format(format!("point {i} has {} payload values but its fields hold {payload_len}", cloud.points[i].payload.len())));
+    }
+
+    let mut writer = BufWriter::new(writer);
+    let join = |value: &dyn Fn(&PcdField) -> String| cloud.fields.iter().map(value).collect::<Vec<String>>().join(" ");
+    writeln!(writer, "# .PCD v0.7 - Point Cloud Data file format\nVERSION 0.7")?;
+    writeln!(writer, "FIELDS {}", join(&|field| field.name.clone()))?;
+    writeln!(writer, "SIZE {}", join(&|field| field.size.to_string()))?;
+    writeln!(writer, "TYPE {}", join(&|field| field.type_letter().to_string()))?;
+    writeln!(writer, "COUNT {}", join(&|field| field.count.to_string()))?;
+    writeln!(writer, "WIDTH {}\nHEIGHT 1\nVIEWPOINT 0 0 0 1 0 0 0\nPOINTS {}", cloud.points.len(), cloud.points.len())?;
+    match cloud.encoding {
+        PcdEncoding::Ascii => {
+            writeln!(writer, "DATA ascii")?;
+            for payload_point in &cloud.points {
+                let line = field_values(&cloud.fields, payload_point)
+                    .map(|(field, value)| match (field.kind, field.size) {
+                        (PcdKind::Float, 4) if field.is_packed_colour() => f64_to_f32_bits(value).to_string(),
+                        (PcdKind::Float, 4) => (value as f32).to_string(),
+                        (PcdKind::Float, _) => value.to_string(),
+                        (PcdKind::Unsigned, _) => (value as u64).to_string(),
+                        (PcdKind::Signed, _) => (value as i64).to_string(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                writeln!(writer, "{line}")?;
+            }
+        }
+        PcdEncoding::Binary => {
+            writeln!(writer, "DATA binary")?;
+            let mut record = vec![];
+            for payload_point in &cloud.points {
+                record.clear();
+                for (field, value) in field_values(&cloud.fields, payload_point) {
+                    encode(value, field, &mut record);
+                }
+                writer.write_all(&record)?;
+            }
+        }
+        PcdEncoding::BinaryCompressed => {
+            writeln!(writer, "DATA binary_compressed")?;
+            let mut columns = vec![vec![]; cloud.fields.len()];
+            for payload_point in &cloud.points {
+                let mut field_index = 0;
+                let mut values_left = cloud.fields[0].count;
+                for (field, value) in field_values(&cloud.fields, payload_point) {
+                    while values_left == 0 {
+                        field_index += 1;
+                        values_left = cloud.fields[field_index].count;
+                    }
+                    encode(value, field, &mut columns[field_index]);
+                    values_left -= 1;
+                }
+            }
+            let data = columns.concat();
+            let compressed = lzf_compress(&data);
+            writer.write_all(&(compressed.len() as u32).to_le_bytes())?;
+            writer.write_all(&(data.len() as u32).to_le_bytes())?;
+            writer.write_all(&compressed)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Largest distance back that an LZF back-reference can reach.
+const LZF_MAX_OFFSET: usize = 1 << 13;
+
+/// Longest match an LZF back-reference can copy.
+const LZF_MAX_MATCH: usize = 264;
+
+/// Compresses the data with LZF, the format of `binary_compressed` PCD files, finding earlier matches
+/// of every three bytes through a hash table of where each was last seen.
+fn lzf_compress(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() / 2 + 16);
+    let mut table = vec![usize::MAX; 1 << 14];
+    let mut literal_start = 0;
+    let mut i = 0;
+    let flush_literals = |output: &mut Vec<u8>, literals: &[u8]| {
+        for run in literals.chunks(32) {
+            output.push((run.len() - 1) as u8);
+            output.extend_from_slice(run);
+        }
+    };
+    while i + 2 < input.len() {
+        let hash = ((input[i] as usize) << 6 ^ (input[i + 1] as usize) << 3 ^ input[i + 2] as usize) & ((1 << 14) - 1);
+        let candidate = table[hash];
+        table[hash] = i;
+        if candidate != usize::MAX && i - candidate <= LZF_MAX_OFFSET && input[candidate..candidate + 3] == input[i..i + 3] {
+            let max_len = LZF_MAX_MATCH.min(input.len() - i);
+            let mut len = 3;
+            while len < max_len && input[candidate + len] == input[i + len] {
+                len += 1;
+            }
+            flush_literals(&mut output, &input[literal_start..i]);
+            let offset = i - candidate - 1;
+            match len - 2 {
+                short @ 1..=6 => output.push(((short as u8) << 5) | (offset >> 8) as u8),
+                long => {
+                    output.push((7 << 5) | (offset >> 8) as u8);
+                    output.push((long - 7) as u8);
+                }
+            }
+            output.push(offset as u8);
+            i += len;
+            literal_start = i;
+        } else {
+            i += 1;
+        }
+    }
+    flush_literals(&mut output, &input[literal_start..]);
+    output
+}
+
+fn lzf_decompress(input: &[u8], len: usize) -> Result<Vec<u8>, PointFileError> {
+    let corrupted = || PointFileError::Format("compressed PCD data is corrupted".to_string());
+    let mut output = Vec::with_capacity(len);
+    let mut i = 0;
+    while i < input.len() {
+        let control = input[i] as usize;
+        i += 1;
+        if control < 32 {
+            let literals = input.get(i..i + control + 1).ok_or_else(corrupted)?;
+            output.extend_from_slice(literals);
+            i += control + 1;
+        } else {
+            let mut match_len = control >> 5;
+            if match_len == 7 {
+                match_len += *input.get(i).ok_or_else(corrupted)? as usize;
+                i += 1;
+            }
+            let offset = ((control & 0x1f) << 8) + *input.get(i).ok_or_else(corrupted)? as usize + 1;
+            i += 1;
+            let start = output.len().checked_sub(offset).ok_or_else(corrupted)?;
+            // the match may overlap the bytes it produces, so it is copied one byte at a time
+            for j in start..start + match_len + 2 {
+                output.push(output[j]);
+            }
+        }
+        if output.len() > len {
+            return Err(corrupted());
+        }
+    }
+    if output.len() != len {
+        return Err(corrupted());
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod pcd_test {
+    use crate::formats::PointFileError;
+    use crate::point::Point;
+
+    use super::{f32_bits_to_f64, f64_to_f32_bits, lzf_compress, lzf_decompress, PcdCloud, PcdEncoding, PcdField, PcdKind, read_pcd, write_pcd};
+
+    const ASCII: &str = "# .PCD v0.7 - Point Cloud Data file format\nVERSION 0.7\nFIELDS x y z intensity normal\n\
+                         SIZE 4 4 4 2 4\nTYPE F F F U F\nCOUNT 1 1 1 1 3\nWIDTH 2\nHEIGHT 1\nVIEWPOINT 0 0 0 1 0 0 0\nPOINTS 2\nDATA ascii\n\
+                         0.5 1 -2 300 0 0 1\n3 4 5 7 1 0 0\n";
+
+    #[test]
+    fn read_ascii_with_payload() {
+        let output = read_pcd(ASCII.as_bytes()).unwrap();
+        assert_eq!(output.fields.len(), 5);
+        assert_eq!(output.points[0].point, Point { x: 0.5, y: 1.0, z: -2.0 });
+        assert_eq!(output.points[0].payload, vec![300.0, 0.0, 0.0, 1.0]);
+        assert_eq!(output.points[1].payload, vec![7.0, 1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn round_trip_every_encoding() {
+        let mut cloud = read_pcd(ASCII.as_bytes()).unwrap();
+        for encoding in [PcdEncoding::Ascii, PcdEncoding::Binary, PcdEncoding::BinaryCompressed] {
+            cloud.encoding = encoding;
+            let mut bytes = vec![];
+            write_pcd(&mut bytes, &cloud).unwrap();
+            let output = read_pcd(bytes.as_slice()).unwrap();
+            assert_eq!(output, cloud);
+        }
+    }
+
+    #[test]
+    fn round_trip_signalling_nan_colour_in_every_encoding() {
+        // alpha 255 makes the packed colour a signalling NaN, whose red channel `as` would turn from 0x90 into 0xd0
+        let rgba = 0xff90_1010;
+        let mut fields = ["x", "y", "z"].into_iter().map(|name| PcdField::new(name, PcdKind::Float, 4, 1)).collect::<Vec<PcdField>>();
+        fields.push(PcdField::new("rgba", PcdKind::Float, 4, 1));
+        let mut cloud = PcdCloud::from_points(vec![Point::new(0.5, 0.25, 0.125)], PcdEncoding::Ascii);
+        cloud.fields = fields;
+        cloud.points[0].payload = vec![f32_bits_to_f64(rgba)];
+        for encoding in [PcdEncoding::Ascii, PcdEncoding::Binary, PcdEncoding::BinaryCompressed] {
+            cloud.encoding = encoding;
+            let mut bytes = vec![];
+            write_pcd(&mut bytes, &cloud).unwrap();
+            let output = f64_to_f32_bits(read_pcd(bytes.as_slice()).unwrap().points[0].payload[0]);
+            assert_eq!(output, rgba, "{encoding:?}");
+        }
+    }
+
+    #[test]
+    fn lzf_round_trip() {
+        let input = (0..5000u32).flat_map(|i| (i % 300).to_le_bytes()).chain(b"abcabcabcabcabcabc".iter().copied()).collect::<Vec<u8>>();
+        let compressed = lzf_compress(&input);
+        assert!(compressed.len() < input.len());
+        let output = lzf_decompress(&compressed, input.len()).unwrap();
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn lzf_decompress_overlapping_match() {
+        // a literal `a` followed by a back-reference one byte back, copying 9 bytes
+        let output = lzf_decompress(&[0, b'a', 7 << 5, 0, 0], 10).unwrap();
+        let expected = vec![b'a'; 10];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn reject_corrupted_compressed_data() {
+        let output = lzf_decompress(&[1 << 5, 5], 3);
+        assert!(matches!(output, Err(PointFileError::Format(_))));
+    }
+
+    #[test]
+    fn report_header_line() {
+        let output = read_pcd("VERSION 0.7\nFIELDS x y z\nSIZE 4 4 4\nTYPE F F Q\nDATA ascii\n".as_bytes());
+        assert!(matches!(output, Err(PointFileError::Parse { line: 4, .. })));
+    }
+
+    #[test]
+    fn reject_width_times_height_overflow() {
+        let output = read_pcd(format!("FIELDS x y z\nSIZE 4 4 4\nTYPE F F F\nWIDTH {}\nHEIGHT 3\nDATA ascii\n", usize::MAX).as_bytes());
+        assert!(matches!(output, Err(PointFileError::Format(_))));
+    }
+
+    #[test]
+    fn reject_compressed_points_overflow() {
+        let mut bytes = format!("FIELDS x y z\nSIZE 4 4 4\nTYPE F F F\nPOINTS {}\nDATA binary_compressed\n", usize::MAX / 4).into_bytes();
+        bytes.extend_from_slice(&[0; 8]);
+        let output = read_pcd(bytes.as_slice());
+        assert!(matches!(output, Err(PointFileError::Format(_))));
+    }
+
+    #[test]
+    fn report_data_line() {
+        let output = read_pcd("FIELDS x y z\nSIZE 4 4 4\nTYPE F F F\nPOINTS 2\nDATA ascii\n1 2 3\n4 5\n".as_bytes());
+        assert!(matches!(output, Err(PointFileError::Parse { line: 7, .. })));
+    }
+
+    #[test]
+    fn read_points_without_payload() {
+        let points = vec![Point::new(0.1, 0.2, 0.3), Point::new(0.4, 0.5, 0.6)];
+        let mut bytes = vec![];
+        write_pcd(&mut bytes, &PcdCloud::from_points(points.clone(), PcdEncoding::BinaryCompressed)).unwrap();
+        let output = read_pcd(bytes.as_slice()).unwrap().positions();
+        assert_eq!(output, points);
+    }
+}
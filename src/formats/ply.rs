@@ -0,0 +1,450 @@
+use std::io::{self, BufRead, BufWriter, Write};
+
+use crate::formats::{PayloadPoint, PointFileError, select_payloads};
+use crate::nearest_neighbour::Neighbour;
+use crate::point::Point;
+
+/// Enum for the ways the body of a PLY file can be encoded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlyEncoding {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// Enum for the scalar types of PLY properties, which all convert to `f64` exactly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlyType {
+    Char,
+    UChar,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Float,
+    Double,
+}
+
+impl PlyType {
+    fn parse(name: &str) -> Option<PlyType> {
+        match name {
+            "char" | "int8" => Some(PlyType::Char),
+            "uchar" | "uint8" => Some(PlyType::UChar),
+            "short" | "int16" => Some(PlyType::Short),
+            "ushort" | "uint16" => Some(PlyType::UShort),
+            "int" | "int32" => Some(PlyType::Int),
+            "uint" | "uint32" => Some(PlyType::UInt),
+            "float" | "float32" => Some(PlyType::Float),
+            "double" | "float64" => Some(PlyType::Double),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            PlyType::Char => "char",
+            PlyType::UChar => "uchar",
+            PlyType::Short => "short",
+            PlyType::UShort => "ushort",
+            PlyType::Int => "int",
+            PlyType::UInt => "uint",
+            PlyType::Float => "float",
+            PlyType::Double => "double",
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            PlyType::Char | PlyType::UChar => 1,
+            PlyType::Short | PlyType::UShort => 2,
+            PlyType::Int | PlyType::UInt | PlyType::Float => 4,
+            PlyType::Double => 8,
+        }
+    }
+}
+
+/// Struct that represents a scalar property of the vertices of a PLY file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlyProperty {
+    pub name: String,
+    pub ply_type: PlyType,
+}
+
+impl PlyProperty {
+    pub fn new(name: &str, ply_type: PlyType) -> PlyProperty {
+        PlyProperty { name: name.to_string(), ply_type }
+    }
+
+    fn is_coordinate(&self) -> bool {
+        matches!(self.name.as_str(), "x" | "y" | "z")
+    }
+}
+
+/// Struct that represents the vertices of a PLY file. Other elements, such as faces, are skipped when reading.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlyCloud {
+    pub encoding: PlyEncoding,
+    /// Every property of the vertices in the order they are stored, including `x`, `y` and `z`.
+    pub properties: Vec<PlyProperty>,
+    /// The vertices, with the values of their properties other than `x`, `y` and `z` as their payload.
+    pub points: Vec<PayloadPoint>,
+}
+
+impl PlyCloud {
+    /// Creates a cloud of points without payloads, stored as `double` coordinates.
+    pub fn from_points(points: impl IntoIterator<Item=Point>, encoding: PlyEncoding) -> PlyCloud {
+        PlyCloud {
+            encoding,
+            properties: vec![PlyProperty::new("x", PlyType::Double), PlyProperty::new("y", PlyType::Double), PlyProperty::new("z", PlyType::Double)],
+            points: points.into_iter().map(|point| PayloadPoint { point, payload: vec![] }).collect(),
+        }
+    }
+
+    /// Returns the positions of the vertices, to build an index over.
+    pub fn positions(&self) -> Vec<Point> {
+        self.points.iter().map(|vertex| vertex.point.clone()).collect()
+    }
+
+    /// Returns a cloud of the neighbours found by a search of this cloud's points, in their order, with the payload
+    /// each point has in this cloud followed by a `distance` property. Neighbours whose point is not in the cloud
+    /// get a payload of zeros.
+    pub fn select(&self, neighbours: &[Neighbour]) -> PlyCloud {
+        let payload_len = self.properties.iter().filter(|property| !property.is_coordinate()).count();
+        let mut properties = self.properties.clone();
+        properties.push(PlyProperty::new("distance", PlyType::Double));
+        let points = select_payloads(&self.points, payload_len, neighbours);
+        PlyCloud { encoding: self.encoding, properties, points }
+    }
+}
+
+/// Enum for a property of an element in the header, where lists are only supported on elements that are skipped.
+#[derive(Debug, Clone, PartialEq)]
+enum ElementProperty {
+    Scalar(PlyProperty),
+    List { count_type: PlyType, item_type: PlyType, name: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Element {
+    name: String,
+    count: u64,
+    properties: Vec<ElementProperty>,
+}
+
+/// Reads the vertices of a PLY file in any of its encodings.
+pub fn read_ply<R: BufRead>(reader: R) -> Result<PlyCloud, PointFileError> {
+    let mut body = BodyReader { reader, encoding: PlyEncoding::Ascii, line: 0, tokens: vec![], next_token: 0 };
+    let elements = read_header(&mut body)?;
+    let vertex = elements.iter().find(|element| element.name == "vertex")
+        .ok_or_else(|| PointFileError::Format("PLY file has no vertex element".to_string()))?;
+    let mut properties = vec![];
+    for property in &vertex.properties {
+        match property {
+            ElementProperty::Scalar(property) => properties.push(property.clone()),
+            ElementProperty::List { name, .. } => return Err(PointFileError::Format(format!("list property `{name}` of vertices is not supported"))),
+        }
+    }
+    let coordinate = |name: &str| properties.iter().position(|property| property.name == name)
+        .ok_or_else(|| PointFileError::Format(format!("vertices have no `{name}` property")));
+    let coordinates = [coordinate("x")?, coordinate("y")?, coordinate("z")?];
+
+    let mut points = vec![];
+    for element in &elements {
+        if element.name != "vertex" {
+            body.skip_element(element)?;
+            continue;
+        }
+        points.reserve(element.count.min(1 << 20) as usize);
+        let mut values = vec![0.0; properties.len()];
+        for i in 0..element.count {
+            body.start_instance(&element.name, i)?;
+            for (value, property) in values.iter_mut().zip(&properties) {
+                *value = body.read_value(property.ply_type, &element.name, i)?;
+            }
+            let payload = properties.iter().zip(&values)
+                .filter(|(property, _)| !property.is_coordinate())
+                .map(|(_, value)| *value)
+                .collect();
+            let point = Point { x: values[coordinates[0]], y: values[coordinates[1]], z: values[coordinates[2]] };
+            points.push(PayloadPoint { point, payload });
+        }
+    }
+    Ok(PlyCloud { encoding: body.encoding, properties, points })
+}
+
+fn read_header<R: BufRead>(body: &mut BodyReader<R>) -> Result<Vec<Element>, PointFileError> {
+    let mut elements: Vec<Element> = vec![];
+    let mut format = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        body.line += 1;
+        if body.reader.read_line(&mut line)? == 0 {
+            return Err(PointFileError::Parse { line: body.line, message: "PLY header has no end_header line".to_string() });
+        }
+        let error = |message: String| PointFileError::Parse { line: body.line, message };
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        if body.line == 1 {
+            if words != ["ply"] {
+                return Err(error("not a PLY file".to_string()));
+            }
+            continue;
+        }
+        match words.as_slice() {
+            ["format", encoding, "1.0"] => {
+                format = Some(match *encoding {
+                    "ascii" => PlyEncoding::Ascii,
+                    "binary_little_endian" => PlyEncoding::BinaryLittleEndian,
+                    "binary_big_endian" => PlyEncoding::BinaryBigEndian,
+                    _ => return Err(error(format!("unknown PLY format `{encoding}`"))),
+                });
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            ["element", name, count] => {
+                let count = count.parse().map_err(|_| error(format!("could not parse `{count}` as a number of elements")))?;
+                elements.push(Element { name: name.to_string(), count, properties: vec![] });
+            }
+            ["property", "list", count_type, item_type, name] => {
+                let element = elements.last_mut().ok_or_else(|| error("property before any element".to_string()))?;
+                let parse = |name: &str| PlyType::parse(name).ok_or_else(|| error(format!("unknown property type `{name}`")));
+                element.properties.push(ElementProperty::List { count_type: parse(count_type)?, item_type: parse(item_type)?, name: name.to_string() });
+            }
+            ["property", ply_type, name] => {
+                let element = elements.last_mut().ok_or_else(|| error("property before any element".to_string()))?;
+                let ply_type = PlyType::parse(ply_type).ok_or_else(|| error(format!("unknown property type `{ply_type}`")))?;
+                element.properties.push(ElementProperty::Scalar(PlyProperty::new(name, ply_type)));
+            }
+            ["end_header"] => break,
+            _ => return Err(error(format!("could not parse PLY header line `{}`", line.trim()))),
+        }
+    }
+    body.encoding = format.ok_or_else(|| PointFileError::Format("PLY header has no format line".to_string()))?;
+    Ok(elements)
+}
+
+/// Struct that reads the values of the body of a PLY file, from lines of text or from binary data.
+struct BodyReader<R> {
+    reader: R,
+    encoding: PlyEncoding,
+    line: u64,
+    tokens: Vec<String>,
+    next_token: usize,
+}
+
+impl<R: BufRead> BodyReader<R> {
+    /// Reads the line of the next element in an ASCII file, where every element is on a line of its own.
+    fn start_instance(&mut self, element: &str, index: u64) -> Result<(), PointFileError> {
+        if self.encoding != PlyEncoding::Ascii { return Ok(()); }
+        let mut line = String::new();
+        loop {
+            line.clear();
+            self.line += 1;
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(PointFileError::Parse { line: self.line, message: format!("file ends before {element} {index}") });
+            }
+            if !line.trim().is_empty() { break; }
+        }
+        self.tokens = line.split_whitespace().map(str::to_string).collect();
+        self.next_token = 0;
+        Ok(())
+    }
+
+    fn read_value(&mut self, ply_type: PlyType, element: &str, index: u64) -> Result<f64, PointFileError> {
+        match self.encoding {
+            PlyEncoding::Ascii => {
+                let token = self.tokens.get(self.next_token).ok_or_else(|| PointFileError::Parse {
+                    line: self.line,
+                    message: format!("{element} {index} has fewer values than its properties"),
+                })?;
+                self.next_token += 1;
+                token.parse().map_err(|_| PointFileError::Parse { line: self.line, message: format!("could not parse `{token}` as a number") })
+            }
+            PlyEncoding::BinaryLittleEndian | PlyEncoding::BinaryBigEndian => {
+                let mut bytes = [0; 8];
+                let bytes = &mut bytes[..ply_type.size()];
+                self.reader.read_exact(bytes).map_err(|error| match error.kind() {
+                    io::ErrorKind::UnexpectedEof => PointFileError::Format(format!("PLY file ends in {element} {index}")),
+                    _ => PointFileError::Io(error),
+                })?;
+                if self.encoding == PlyEncoding::BinaryBigEndian {
+                    bytes.reverse();
+                }
+                Ok(decode_little_endian(bytes, ply_type))
+            }
+        }
+    }
+
+    fn skip_element(&mut self, element: &Element) -> Result<(), PointFileError> {
+        for i in 0..element.count {
+            self.start_instance(&element.name, i)?;
+            for property in &element.properties {
+                match property {
+                    ElementProperty::Scalar(property) => { self.read_value(property.ply_type, &element.name, i)?; }
+                    ElementProperty::List { count_type, item_type, .. } => {
+                        let count = self.read_value(*count_type, &element.name, i)?;
+                        for _ in 0..count as u64 {
+                            self.read_value(*item_type, &element.name, i)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn decode_little_endian(bytes: &[u8], ply_type: PlyType) -> f64 {
+    match ply_type {
+        PlyType::Char => bytes[0] as i8 as f64,
+        PlyType::UChar => bytes[0] as f64,
+        PlyType::Short => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        PlyType::UShort => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        PlyType::Int => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        PlyType::UInt => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        PlyType::Float => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        PlyType::Double => f64::from_le_bytes(bytes.try_into().unwrap()),
+    }
+}
+
+fn encode_little_endian(value: f64, ply_type: PlyType, bytes: &mut Vec<u8>) {
+    match ply_type {
+        PlyType::Char => bytes.extend_from_slice(&(value as i8).to_le_bytes()),
+        PlyType::UChar => bytes.extend_from_slice(&(value as u8).to_le_bytes()),
+        PlyType::Short => bytes.extend_from_slice(&(value as i16).to_le_bytes()),
+        PlyType::UShort => bytes.extend_from_slice(&(value as u16).to_le_bytes()),
+        PlyType::Int => bytes.extend_from_slice(&(value as i32).to_le_bytes()),
+        PlyType::UInt => bytes.extend_from_slice(&(value as u32).to_le_bytes()),
+        PlyType::Float => bytes.extend_from_slice(&(value as f32).to_le_bytes()),
+        PlyType::Double => bytes.extend_from_slice(&value.to_le_bytes()),
+    }
+}
+
+/// Writes the cloud as a PLY file in its encoding, with its properties in their order.
+/// Values are converted to the type of their property, so they should fit in it.
+pub fn write_ply<W: Write>(writer: W, cloud: &PlyCloud) -> Result<(), PointFileError> {
+    let payload_len = cloud.properties.iter().filter(|property| !property.is_coordinate()).count();
+    for name in ["x", "y", "z"] {
+        if !cloud.properties.iter().any(|property| property.name == name) {
+            return Err(PointFileError::Format(format!("vertices have no `{name}` property")));
+        }
+    }
+    if let Some(i) = cloud.points.iter().position(|vertex| vertex.payload.len() != payload_len) {
+        return Err(PointFileError::Format(format!("vertex {i} has {} payload values but there are {payload_len} properties besides x, y and z",
+                                                  cloud.points[i].payload.len())));
+    }
+
+    let mut writer = BufWriter::new(writer);
+    let encoding = match cloud.encoding {
+        PlyEncoding::Ascii => "ascii",
+        PlyEncoding::BinaryLittleEndian => "binary_little_endian",
+        PlyEncoding::BinaryBigEndian => "binary_big_endian",
+    };
+    writeln!(writer, "ply\nformat {encoding} 1.0\nelement vertex {}", cloud.points.len())?;
+    for property in &cloud.properties {
+        writeln!(writer, "property {} {}", property.ply_type.name(), property.name)?;
+    }
+    writeln!(writer, "end_header")?;
+
+    let mut bytes = vec![];
+    let mut line = String::new();
+    for vertex in &cloud.points {
+        let mut payload = vertex.payload.iter();
+        bytes.clear();
+        line.clear();
+        for property in &cloud.properties {
+            let value = match property.name.as_str() {
+                "x" => vertex.point.x,
+                "y" => vertex.point.y,
+                "z" => vertex.point.z,
+                _ => *payload.next().unwrap(),
+            };
+            match cloud.encoding {
+                PlyEncoding::Ascii => {
+                    if !line.is_empty() { line.push(' '); }
+                    match property.ply_type {
+                        PlyType::Float => line.push_str(&(value as f32).to_string()),
+                        PlyType::Double => line.push_str(&value.to_string()),
+                        _ => line.push_str(&(value as i64).to_string()),
+                    }
+                }
+                PlyEncoding::BinaryLittleEndian => encode_little_endian(value, property.ply_type, &mut bytes),
+                PlyEncoding::BinaryBigEndian => {
+                    let start = bytes.len();
+                    encode_little_endian(value, property.ply_type, &mut bytes);
+                    bytes[start..].reverse();
+                }
+            }
+        }
+        match cloud.encoding {
+            PlyEncoding::Ascii => writeln!(writer, "{line}")?,
+            _ => writer.write_all(&bytes)?,
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod ply_test {
+    use crate::formats::PointFileError;
+    use crate::nearest_neighbour::Neighbour;
+    use crate::point::Point;
+
+    use super::{PlyCloud, PlyEncoding, PlyProperty, PlyType, read_ply, write_ply};
+
+    const ASCII: &str = "ply\nformat ascii 1.0\ncomment made by hand\nelement vertex 2\nproperty float x\nproperty float y\nproperty float z\n\
+                         property uchar red\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n\
+                         0.5 1 -2 255\n3 4 5 7\n3 0 1 1\n";
+
+    #[test]
+    fn read_ascii_with_payload_and_faces() {
+        let output = read_ply(ASCII.as_bytes()).unwrap();
+        assert_eq!(output.encoding, PlyEncoding::Ascii);
+        assert_eq!(output.properties.len(), 4);
+        assert_eq!(output.points[0].point, Point { x: 0.5, y: 1.0, z: -2.0 });
+        assert_eq!(output.points[0].payload, vec![255.0]);
+        assert_eq!(output.points[1].payload, vec![7.0]);
+    }
+
+    #[test]
+    fn round_trip_every_encoding() {
+        let mut cloud = read_ply(ASCII.as_bytes()).unwrap();
+        for encoding in [PlyEncoding::Ascii, PlyEncoding::BinaryLittleEndian, PlyEncoding::BinaryBigEndian] {
+            cloud.encoding = encoding;
+            let mut bytes = vec![];
+            write_ply(&mut bytes, &cloud).unwrap();
+            let output = read_ply(bytes.as_slice()).unwrap();
+            assert_eq!(output, cloud);
+        }
+    }
+
+    #[test]
+    fn report_header_line() {
+        let output = read_ply("ply\nformat ascii 1.0\nelement vertex 1\nproperty quad x\nend_header\n".as_bytes());
+        assert!(matches!(output, Err(PointFileError::Parse { line: 4, .. })));
+    }
+
+    #[test]
+    fn report_body_line() {
+        let output = read_ply("ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nproperty float y\nproperty float z\nend_header\n1 2 3\n4 five 6\n".as_bytes());
+        assert!(matches!(output, Err(PointFileError::Parse { line: 9, message }) if message.contains("`five`")));
+    }
+
+    #[test]
+    fn report_truncated_binary() {
+        let mut bytes = vec![];
+        write_ply(&mut bytes, &PlyCloud::from_points(vec![Point::new(0.1, 0.2, 0.3)], PlyEncoding::BinaryLittleEndian)).unwrap();
+        bytes.pop();
+        let output = read_ply(bytes.as_slice());
+        assert!(matches!(output, Err(PointFileError::Format(_))));
+    }
+
+    #[test]
+    fn select_neighbours_with_payloads() {
+        let cloud = read_ply(ASCII.as_bytes()).unwrap();
+        let neighbours = vec![Neighbour { distance: 0.25, point: Point { x: 3.0, y: 4.0, z: 5.0 } }];
+        let output = cloud.select(&neighbours);
+        assert_eq!(output.properties.last(), Some(&PlyProperty::new("distance", PlyType::Double)));
+        assert_eq!(output.points[0].payload, vec![7.0, 0.25]);
+    }
+}
@@ -0,0 +1,262 @@
+use std::io::{BufRead, BufWriter, Write};
+
+use crate::formats::PointFileError;
+use crate::point::Point;
+
+/// Enum for how the fields of a line are separated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Delimiter {
+    /// Fields are separated by this character, such as `,` or `;`.
+    Char(char),
+    /// Fields are separated by any run of spaces and tabs.
+    Whitespace,
+}
+
+/// Enum for which columns hold the x, y and z coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Columns {
+    /// The positions of the columns, counted from 0.
+    Indices([usize; 3]),
+    /// The names of the columns in the header line.
+    Names([String; 3]),
+}
+
+/// Struct that describes the layout of a CSV or XYZ text file of points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextFormat {
+    pub delimiter: Delimiter,
+    pub columns: Columns,
+    /// Whether the first line that is not blank or a comment names the columns rather than holding a point.
+    pub header: bool,
+    /// Lines starting with this character are skipped, as are blank lines.
+    pub comment: Option<char>,
+}
+
+impl TextFormat {
+    /// Comma-separated x, y and z columns under a header line.
+    pub fn csv() -> TextFormat {
+        TextFormat { delimiter: Delimiter::Char(','), columns: Columns::Indices([0, 1, 2]), header: true, comment: None }
+    }
+
+    /// Whitespace-separated x, y and z columns without a header, skipping lines starting with `#`.
+    /// Any columns after the coordinates, such as colours or normals, are ignored.
+    pub fn xyz() -> TextFormat {
+        TextFormat { delimiter: Delimiter::Whitespace, columns: Columns::Indices([0, 1, 2]), header: false, comment: Some('#') }
+    }
+
+    fn split<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        match self.delimiter {
+            Delimiter::Char(delimiter) => line.split(delimiter).map(|field| field.trim().trim_matches('"')).collect(),
+            Delimiter::Whitespace => line.split_whitespace().collect(),
+        }
+    }
+}
+
+impl Default for TextFormat {
+    fn default() -> Self {
+        TextFormat::csv()
+    }
+}
+
+/// Struct that streams the points of a text file, one line at a time.
+/// Each item is a point or the error of the line it could not be parsed from, after which the following lines are still read.
+pub struct TextPoints<R> {
+    reader: R,
+    format: TextFormat,
+    columns: [usize; 3],
+    line: u64,
+    buffer: String,
+}
+
+impl<R: BufRead> TextPoints<R> {
+    /// Starts reading points from `reader`, first reading the header line if the format has one.
+    pub fn new(reader: R, format: &TextFormat) -> Result<TextPoints<R>, PointFileError> {
+        let mut points = TextPoints { reader, format: format.clone(), columns: [0, 1, 2], line: 0, buffer: String::new() };
+        let header = match format.header {
+            true if points.next_line()? => {
+                let header = format.split(points.buffer.trim()).into_iter().map(str::to_string).collect::<Vec<String>>();
+                Some((points.line, header))
+            }
+            _ => None,
+        };
+        points.columns = match (&format.columns, header) {
+            (Columns::Indices(indices), _) => *indices,
+            (Columns::Names(names), Some((line, header))) => {
+                let mut columns = [0; 3];
+                for (column, name) in columns.iter_mut().zip(names) {
+                    *column = header.iter().position(|field| field == name)
+                        .ok_or_else(|| PointFileError::Parse { line, message: format!("no column named `{name}` in the header") })?;
+                }
+                columns
+            }
+            (Columns::Names(_), None) => return Err(PointFileError::Format("columns can only be chosen by name from a header line".to_string())),
+        };
+        Ok(points)
+    }
+
+    /// Reads lines into the buffer until one is neither blank nor a comment, or returns false at the end of the file.
+    fn next_line(&mut self) -> Result<bool, PointFileError> {
+        loop {
+            self.buffer.clear();
+            self.line += 1;
+            if self.reader.read_line(&mut self.buffer)? == 0 {
+                return Ok(false);
+            }
+            let line = self.buffer.trim();
+            if !line.is_empty() && !self.format.comment.is_some_and(|comment| line.starts_with(comment)) {
+                return Ok(true);
+            }
+        }
+    }
+
+    fn parse(&self) -> Result<Point, PointFileError> {
+        let fields = self.format.split(self.buffer.trim());
+        let mut coordinates = [0.0; 3];
+        for (coordinate, &column) in coordinates.iter_mut().zip(&self.columns) {
+            let field = fields.get(column).ok_or_else(|| PointFileError::Parse {
+                line: self.line,
+                message: format!("expected a coordinate in column {} but found {} columns", column + 1, fields.len()),
+            })?;
+            *coordinate = field.parse().map_err(|_| PointFileError::Parse {
+                line: self.line,
+                message: format!("could not parse `{field}` in column {} as a number", column + 1),
+            })?;
+        }
+        Ok(Point { x: coordinates[0], y: coordinates[1], z: coordinates[2] })
+    }
+}
+
+impl<R: BufRead> Iterator for TextPoints<R> {
+    type Item = Result<Point, PointFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_line() {
+            Ok(true) => Some(self.parse()),
+            Ok(false) => None,
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
+/// Reads every point of a text file, stopping at the first line that cannot be parsed.
+pub fn read_points<R: BufRead>(reader: R, format: &TextFormat) -> Result<Vec<Point>, PointFileError> {
+    TextPoints::new(reader, format)?.collect()
+}
+
+/// Writes the points as x, y and z columns, under a header line if the format has one, which names
+/// the columns as the format does or else `x`, `y` and `z`. Whitespace-delimited columns are separated by a space.
+pub fn write_points<'a, W: Write>(writer: W, points: impl IntoIterator<Item=&'a Point>, format: &TextFormat) -> Result<(), PointFileError> {
+    let mut writer = BufWriter::new(writer);
+    let delimiter = match format.delimiter {
+        Delimiter::Char(delimiter) => delimiter,
+        Delimiter::Whitespace => ' ',
+    };
+    if format.header {
+        match &format.columns {
+            Columns::Names([x, y, z]) => writeln!(writer, "{x}{delimiter}{y}{delimiter}{z}")?,
+            Columns::Indices(_) => writeln!(writer, "x{delimiter}y{delimiter}z")?,
+        }
+    }
+    for point in points {
+        writeln!(writer, "{}{delimiter}{}{delimiter}{}", point.x, point.y, point.z)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod text_test {
+    use std::io::{self, BufReader, Read};
+
+    use crate::formats::PointFileError;
+    use crate::point::Point;
+
+    use super::{Columns, Delimiter, read_points, TextFormat, TextPoints, write_points};
+
+    #[test]
+    fn read_csv_with_header() {
+        let output = read_points("x,y,z\n1.5,-2,3e2\n\n0,0,0.25\n".as_bytes(), &TextFormat::csv()).unwrap();
+        let expected = vec![Point { x: 1.5, y: -2.0, z: 300.0 }, Point { x: 0.0, y: 0.0, z: 0.25 }];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn read_xyz_skipping_comments_and_extra_columns() {
+        let output = read_points("# scan\n1 2 3 255 0 0\n\t4  5 6\n".as_bytes(), &TextFormat::xyz()).unwrap();
+        let expected = vec![Point { x: 1.0, y: 2.0, z: 3.0 }, Point { x: 4.0, y: 5.0, z: 6.0 }];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn read_columns_by_name() {
+        let format = TextFormat {
+            delimiter: Delimiter::Char(';'),
+            columns: Columns::Names(["X".to_string(), "Y".to_string(), "Z".to_string()]),
+            ..TextFormat::csv()
+        };
+        let output = read_points("id;Z;\"Y\";X\n7;3;2;1\n".as_bytes(), &format).unwrap();
+        let expected = vec![Point { x: 1.0, y: 2.0, z: 3.0 }];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn read_columns_by_index() {
+        let format = TextFormat { columns: Columns::Indices([2, 0, 1]), header: false, ..TextFormat::csv() };
+        let output = read_points("1,2,3\n".as_bytes(), &format).unwrap();
+        let expected = vec![Point { x: 3.0, y: 1.0, z: 2.0 }];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn report_line_of_bad_number() {
+        let output = read_points("x,y,z\n1,2,3\n\n4,five,6\n".as_bytes(), &TextFormat::csv());
+        assert!(matches!(output, Err(PointFileError::Parse { line: 4, message }) if message.contains("`five`") && message.contains("column 2")));
+    }
+
+    #[test]
+    fn report_line_of_missing_column() {
+        let output = read_points("1 2 3\n4 5\n".as_bytes(), &TextFormat::xyz());
+        assert!(matches!(output, Err(PointFileError::Parse { line: 2, .. })));
+    }
+
+    #[test]
+    fn report_missing_column_name() {
+        let format = TextFormat { columns: Columns::Names(["x".to_string(), "y".to_string(), "w".to_string()]), ..TextFormat::csv() };
+        let output = TextPoints::new("x,y,z\n".as_bytes(), &format);
+        assert!(matches!(output, Err(PointFileError::Parse { line: 1, .. })));
+    }
+
+    #[test]
+    fn report_read_failure_as_io_error() {
+        struct FailingReader;
+
+        impl Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("disk failed"))
+            }
+        }
+
+        let output = read_points(BufReader::new(FailingReader), &TextFormat::xyz());
+        assert!(matches!(output, Err(PointFileError::Io(_))));
+    }
+
+    #[test]
+    fn stream_past_bad_lines() {
+        let output = TextPoints::new("1 2 3\nbad\n4 5 6\n".as_bytes(), &TextFormat::xyz()).unwrap()
+            .map(|point| point.is_ok())
+            .collect::<Vec<bool>>();
+        let expected = vec![true, false, true];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn write_and_read_back() {
+        let points = vec![Point { x: 0.1, y: -1e-300, z: 12345.678 }, Point { x: 1.0 / 3.0, y: 2.0, z: 0.0 }];
+        for format in [TextFormat::csv(), TextFormat::xyz()] {
+            let mut bytes = vec![];
+            write_points(&mut bytes, &points, &format).unwrap();
+            let output = read_points(bytes.as_slice(), &format).unwrap();
+            assert_eq!(output, points);
+        }
+    }
+}
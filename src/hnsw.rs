@@ -0,0 +1,328 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::nearest_neighbour::Neighbour;
+use crate::vp_tree::Metric;
+
+/// Struct that holds the parameters of an HNSW index.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HnswParams {
+    /// Number of neighbours each item is linked to on the layers above the bottom one,
+    /// which links twice as many.
+    pub m: usize,
+    /// Number of candidates kept while looking for the neighbours of a new item.
+    pub ef_construction: usize,
+    /// Number of candidates kept while searching, traded off against speed for better recall.
+    pub ef_search: usize,
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        HnswParams { m: 16, ef_construction: 200, ef_search: 50 }
+    }
+}
+
+/// Struct that represents an item of the graph and its links on every layer it belongs to.
+#[derive(Debug, Clone, PartialEq)]
+struct HnswNode<T> {
+    item: T,
+    links: Vec<Vec<usize>>,
+}
+
+/// Struct that represents a Hierarchical Navigable Small World graph, an approximate nearest
+/// neighbour index for items of any metric space, such as high-dimensional vectors.
+/// Every item is on the bottom layer and on each layer above it with a geometrically falling probability,
+/// so searches cross the sparse upper layers in a few long hops before refining on the dense lower ones.
+/// Neighbours are reported by the id of the item, which is its position in insertion order.
+#[derive(Debug, Clone)]
+pub struct Hnsw<T, M> {
+    nodes: Vec<HnswNode<T>>,
+    entry_point: Option<usize>,
+    params: HnswParams,
+    metric: M,
+    rng: StdRng,
+}
+
+impl<T, M: Metric<T>> Hnsw<T, M> {
+    /// Creates an empty index with the default parameters.
+    pub fn new(metric: M) -> Hnsw<T, M> {
+        Hnsw::with_params(metric, HnswParams::default())
+    }
+
+    /// Creates an empty index with the given parameters.
+    pub fn with_params(metric: M, params: HnswParams) -> Hnsw<T, M> {
+        Hnsw::with_rng(metric, params, StdRng::from_entropy())
+    }
+
+    /// Creates an empty index with the given parameters that draws the layers of its items from a
+    /// generator seeded with `seed`, so that inserting the same items always builds the same graph.
+    pub fn with_seed(metric: M, params: HnswParams, seed: u64) -> Hnsw<T, M> {
+        Hnsw::with_rng(metric, params, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(metric: M, params: HnswParams, rng: StdRng) -> Hnsw<T, M> {
+        let params = HnswParams { m: params.m.max(2), ef_construction: params.ef_construction.max(1), ef_search: params.ef_search.max(1) };
+        Hnsw { nodes: vec![], entry_point: None, params, metric, rng }
+    }
+
+    /// Constructs an index from a vector of items, inserting them in order.
+    pub fn construct_index(items: Vec<T>, metric: M, params: HnswParams) -> Hnsw<T, M> {
+        Hnsw::with_params(metric, params).extend_with(items)
+    }
+
+    /// Constructs an index from a vector of items like `construct_index`, drawing their layers from a generator seeded with `seed`.
+    pub fn construct_index_with_seed(items: Vec<T>, metric: M, params: HnswParams, seed: u64) -> Hnsw<T, M> {
+        Hnsw::with_seed(metric, params, seed).extend_with(items)
+    }
+
+    fn extend_with(mut self, items: Vec<T>) -> Hnsw<T, M> {
+        for item in items {
+            self.insert(item);
+        }
+        self
+    }
+
+    /// Returns the metric used by the index.
+    pub fn metric(&self) -> &M {
+        &self.metric
+    }
+
+    /// Returns the parameters of the index.
+    pub fn params(&self) -> &HnswParams {
+        &self.params
+    }
+
+    /// Changes the number of candidates kept while searching.
+    pub fn set_ef_search(&mut self, ef_search: usize) {
+        self.params.ef_search = ef_search.max(1);
+    }
+
+    /// Returns the number of items in the index.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if the index holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the item with the given id.
+    pub fn item(&self, id: usize) -> Option<&T> {
+        self.nodes.get(id).map(|node| &node.item)
+    }
+
+    /// Inserts an item and returns its id.
+    pub fn insert(&mut self, item: T) -> usize {
+        let id = self.nodes.len();
+        let layer = self.random_layer();
+        self.nodes.push(HnswNode { item, links: vec![vec![]; layer + 1] });
+        let Some(entry_point) = self.entry_point else {
+            self.entry_point = Some(id);
+            return id;
+        };
+
+        let top_layer = self.top_layer();
+        let mut entry_points = vec![self.neighbour(id, entry_point)];
+        for curr_layer in (layer + 1..=top_layer).rev() {
+            entry_points = self.search_layer(&self.nodes[id].item, entry_points, 1, curr_layer);
+        }
+        for curr_layer in (0..=layer.min(top_layer)).rev() {
+            let candidates = self.search_layer(&self.nodes[id].item, entry_points, self.params.ef_construction, curr_layer);
+            let neighbours = self.select_neighbours(candidates.clone(), self.params.m);
+            for neighbour in &neighbours {
+                self.nodes[neighbour.point].links[curr_layer].push(id);
+                self.shrink_links(neighbour.point, curr_layer);
+            }
+            self.nodes[id].links[curr_layer] = neighbours.into_iter().map(|neighbour| neighbour.point).collect();
+            entry_points = candidates;
+        }
+        if layer > top_layer {
+            self.entry_point = Some(id);
+        }
+        id
+    }
+
+    /// Returns the approximate k nearest neighbours to the given item, sorted by distance.
+    pub fn nearest(&self, given_item: &T, k: usize) -> Vec<Neighbour<usize>> {
+        self.nearest_with_ef(given_item, k, self.params.ef_search)
+    }
+
+    /// Returns the approximate k nearest neighbours to the given item, keeping `ef` candidates
+    /// while searching the bottom layer instead of the index's `ef_search`.
+    pub fn nearest_with_ef(&self, given_item: &T, k: usize, ef: usize) -> Vec<Neighbour<usize>> {
+        let Some(entry_point) = self.entry_point else { return vec![]; };
+        if k == 0 {
+            return vec![];
+        }
+        let entry_distance = self.metric.distance(given_item, &self.nodes[entry_point].item);
+        let mut entry_points = vec![Neighbour { distance: entry_distance, point: entry_point }];
+        for curr_layer in (1..=self.top_layer()).rev() {
+            entry_points = self.search_layer(given_item, entry_points, 1, curr_layer);
+        }
+        let mut neighbours = self.search_layer(given_item, entry_points, ef.max(k), 0);
+        neighbours.truncate(k);
+        neighbours
+    }
+
+    fn top_layer(&self) -> usize {
+        self.entry_point.map_or(0, |entry_point| self.nodes[entry_point].links.len() - 1)
+    }
+
+    /// Draws the highest layer of a new item, which is above `layer` with probability `1 / m^layer`.
+    fn random_layer(&mut self) -> usize {
+        let normalisation = 1.0 / (self.params.m as f64).ln();
+        let uniform: f64 = self.rng.gen_range(f64::EPSILON..1.0);
+        (-uniform.ln() * normalisation).floor() as usize
+    }
+
+    fn neighbour(&self, from: usize, to: usize) -> Neighbour<usize> {
+        Neighbour { distance: self.metric.distance(&self.nodes[from].item, &self.nodes[to].item), point: to }
+    }
+
+    /// Greedily expands the closest unexpanded candidate until none can improve on the `ef` nearest
+    /// items found so far, and returns those sorted by distance.
+    fn search_layer(&self, given_item: &T, entry_points: Vec<Neighbour<usize>>, ef: usize, layer: usize) -> Vec<Neighbour<usize>> {
+        let mut visited = HashSet::new();
+        let mut candidates = BinaryHeap::new();
+        let mut nearest = BinaryHeap::new();
+        for entry_point in entry_points {
+            visited.insert(entry_point.point);
+            candidates.push(Reverse(entry_point.clone()));
+            nearest.push(entry_point);
+        }
+        while nearest.len() > ef {
+            nearest.pop();
+        }
+
+        while let Some(Reverse(candidate)) = candidates.pop() {
+            let furthest = nearest.peek().map_or(f64::INFINITY, |n: &Neighbour<usize>| n.distance);
+            if candidate.distance > furthest {
+                break;
+            }
+            for &link in &self.nodes[candidate.point].links[layer] {
+                if !visited.insert(link) {
+                    continue;
+                }
+                let curr_distance = self.metric.distance(given_item, &self.nodes[link].item);
+                let furthest = nearest.peek().map_or(f64::INFINITY, |n: &Neighbour<usize>| n.distance);
+                if nearest.len() < ef || curr_distance < furthest {
+                    candidates.push(Reverse(Neighbour { distance: curr_distance, point: link }));
+                    nearest.push(Neighbour { distance: curr_distance, point: link });
+                    if nearest.len() > ef {
+                        nearest.pop();
+                    }
+                }
+            }
+        }
+        nearest.into_sorted_vec()
+    }
+
+    /// Picks up to `m` of the candidates with the neighbour selection heuristic: a candidate is kept
+    /// only if it is nearer to the new item than to every candidate kept before it, which spreads the
+    /// links over different directions. Discarded candidates fill any remaining places.
+    fn select_neighbours(&self, mut candidates: Vec<Neighbour<usize>>, m: usize) -> Vec<Neighbour<usize>> {
+        candidates.sort();
+        let mut selected: Vec<Neighbour<usize>> = vec![];
+        let mut discarded = vec![];
+        for candidate in candidates {
+            if selected.len() >= m {
+                break;
+            }
+            let is_diverse = selected.iter()
+                .all(|kept| self.neighbour(candidate.point, kept.point).distance > candidate.distance);
+            match is_diverse {
+                true => selected.push(candidate),
+                false => discarded.push(candidate),
+            }
+        }
+        let missing = m.saturating_sub(selected.len());
+        selected.extend(discarded.into_iter().take(missing));
+        selected
+    }
+
+    /// Trims the links of an item back to the most a layer allows, keeping the ones the heuristic selects.
+    fn shrink_links(&mut self, id: usize, layer: usize) {
+        let max_links = match layer {
+            0 => 2 * self.params.m,
+            _ => self.params.m,
+        };
+        if self.nodes[id].links[layer].len() <= max_links {
+            return;
+        }
+        let candidates = self.nodes[id].links[layer].iter().map(|&link| self.neighbour(id, link)).collect();
+        let links = self.select_neighbours(candidates, max_links).into_iter().map(|neighbour| neighbour.point).collect();
+        self.nodes[id].links[layer] = links;
+    }
+}
+
+#[cfg(test)]
+mod hnsw_test {
+    use super::{Hnsw, HnswParams};
+
+    fn absolute_difference(a: &i64, b: &i64) -> f64 {
+        (a - b).abs() as f64
+    }
+
+    #[test]
+    fn parameters_are_at_least_one() {
+        let hnsw = Hnsw::with_params(absolute_difference, HnswParams { m: 0, ef_construction: 0, ef_search: 0 });
+        let output = *hnsw.params();
+        let expected = HnswParams { m: 2, ef_construction: 1, ef_search: 1 };
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn first_item_becomes_entry_point() {
+        let mut hnsw = Hnsw::new(absolute_difference);
+        assert_eq!(hnsw.insert(7), 0);
+        assert_eq!(hnsw.entry_point, Some(0));
+        assert_eq!(hnsw.item(0), Some(&7));
+    }
+
+    #[test]
+    fn links_are_bounded() {
+        let params = HnswParams { m: 4, ef_construction: 16, ef_search: 16 };
+        let hnsw = Hnsw::construct_index((0..500).collect(), absolute_difference, params);
+        for node in &hnsw.nodes {
+            assert!(node.links[0].len() <= 8);
+            assert!(node.links[1..].iter().all(|links| links.len() <= 4));
+        }
+    }
+
+    #[test]
+    fn nearest_integers() {
+        let hnsw = Hnsw::construct_index(vec![1, 10, 20, 30, 40], absolute_difference, HnswParams::default());
+        let output = hnsw.nearest(&22, 2).into_iter().map(|n| *hnsw.item(n.point).unwrap()).collect::<Vec<i64>>();
+        let expected = vec![20, 30];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn same_seed_builds_same_graph() {
+        let params = HnswParams { m: 4, ef_construction: 16, ef_search: 16 };
+        let output = Hnsw::construct_index_with_seed((0..200).collect(), absolute_difference, params, 7);
+        let expected = Hnsw::construct_index_with_seed((0..200).collect(), absolute_difference, params, 7);
+        assert_eq!(output.nodes, expected.nodes);
+        assert_eq!(output.entry_point, expected.entry_point);
+    }
+
+    #[test]
+    fn nearest_in_empty_index() {
+        let output = Hnsw::new(absolute_difference).nearest(&3, 5);
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn selection_prefers_diverse_neighbours() {
+        let hnsw = Hnsw::construct_index(vec![0, 1, 2, -3], absolute_difference, HnswParams::default());
+        let candidates = [1, 2, 3].into_iter().map(|id| hnsw.neighbour(0, id)).collect();
+        let output = hnsw.select_neighbours(candidates, 2).into_iter().map(|n| n.point).collect::<Vec<usize>>();
+        // the item 2 is nearer to the item 1 than to 0, so the item -3 on the other side is kept instead
+        let expected = vec![1, 3];
+        assert_eq!(output, expected);
+    }
+}
@@ -1,17 +1,21 @@
 use std::cmp::Ordering;
 
+use crate::bounding_box::BoundingBox;
 use crate::point::{Dimension, Point};
+use crate::scalar::Coordinate;
+use crate::spatial_index::SpatialIndex;
 
 /// Struct that represents a node in the kd-tree data structure.
 #[derive(Debug, Clone, PartialEq)]
-pub struct KdTreeNode {
-    pub point: Point,
-    pub left: Option<Box<KdTreeNode>>,
-    pub right: Option<Box<KdTreeNode>>,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KdTreeNode<S = f64> {
+    pub point: Point<S>,
+    pub left: Option<Box<KdTreeNode<S>>>,
+    pub right: Option<Box<KdTreeNode<S>>>,
 }
 
-impl KdTreeNode {
-    fn new(point: Point) -> KdTreeNode {
+impl<S: Coordinate> KdTreeNode<S> {
+    fn new(point: Point<S>) -> KdTreeNode<S> {
         KdTreeNode {
             point,
             left: None,
@@ -20,7 +24,7 @@ impl KdTreeNode {
     }
 
     /// Constructs a kd-tree from a vector of `Point` objects.
-    pub fn construct_tree(points: Vec<Point>) -> KdTreeNode {
+    pub fn construct_tree(points: Vec<Point<S>>) -> KdTreeNode<S> {
         match construct_kd_tree(points, &Dimension::X) {
             Some(x) => *x,
             None => KdTreeNode { point: Default::default(), left: None, right: None }
@@ -28,7 +32,99 @@ impl KdTreeNode {
     }
 }
 
-fn construct_kd_tree(mut points: Vec<Point>, curr_dimension: &Dimension) -> Option<Box<KdTreeNode>> {
+impl KdTreeNode {
+
+    /// Returns the smallest box containing every point in the tree.
+    pub fn bounding_box(&self) -> BoundingBox {
+        let mut bounding_box = BoundingBox::new(self.point.clone(), self.point.clone());
+        extend_bounding_box(&self.left, &mut bounding_box);
+        extend_bounding_box(&self.right, &mut bounding_box);
+        bounding_box
+    }
+}
+
+/// Struct that represents a whole kd-tree, which unlike a `KdTreeNode` can also hold no points.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KdTree<S = f64> {
+    pub root: Option<Box<KdTreeNode<S>>>,
+    pub(crate) len: usize,
+}
+
+impl<S: Coordinate> KdTree<S> {
+    /// Constructs a kd-tree from a vector of `Point` objects.
+    pub fn construct_tree(points: Vec<Point<S>>) -> KdTree<S> {
+        let len = points.len();
+        KdTree { root: construct_kd_tree(points, &Dimension::X), len }
+    }
+}
+
+impl SpatialIndex for KdTree {
+    fn build(points: Vec<Point>) -> KdTree {
+        KdTree::construct_tree(points)
+    }
+
+    fn in_box(&self, bounding_box: &BoundingBox) -> Vec<Point> {
+        let mut points = vec![];
+        find_points_in_box(&self.root, bounding_box, &Dimension::X, &mut points);
+        points
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Function to find every point inside the given box within kd-tree, including points on its boundary.
+/// The points are appended to `points` in no particular order.
+pub fn find_points_in_box(curr_node: &Option<Box<KdTreeNode>>, bounding_box: &BoundingBox, curr_dimension: &Dimension,
+                          points: &mut Vec<Point>) {
+    let Some(curr_node) = curr_node else { return; };
+    let split = curr_node.point.get_dimension(curr_dimension);
+
+    // the left subtree only holds points below the split and the right subtree the rest
+    if bounding_box.min.get_dimension(curr_dimension) < split {
+        find_points_in_box(&curr_node.left, bounding_box, &curr_dimension.turn(), points);
+    }
+    if bounding_box.max.get_dimension(curr_dimension) >= split {
+        find_points_in_box(&curr_node.right, bounding_box, &curr_dimension.turn(), points);
+    }
+    if bounding_box.contains(&curr_node.point) {
+        points.push(curr_node.point.clone());
+    }
+}
+
+/// A subtree of the kd-tree together with its splitting dimension and a box containing all of its points.
+pub(crate) struct Subtree<'a> {
+    pub(crate) node: &'a KdTreeNode,
+    pub(crate) dimension: Dimension,
+    pub(crate) bounds: BoundingBox,
+}
+
+impl<'a> Subtree<'a> {
+    pub(crate) fn root(node: &'a KdTreeNode) -> Subtree<'a> {
+        Subtree { node, dimension: Dimension::X, bounds: node.bounding_box() }
+    }
+
+    /// Returns the left and right subtrees, with the bounds split at this node's point.
+    pub(crate) fn children(&self) -> [Option<Subtree<'a>>; 2] {
+        let (left_bounds, right_bounds) = self.bounds.split(&self.dimension, self.node.point.get_dimension(&self.dimension));
+        let child = |node: &'a Option<Box<KdTreeNode>>, bounds: BoundingBox| {
+            node.as_deref().map(|node| Subtree { node, dimension: self.dimension.turn(), bounds })
+        };
+        [child(&self.node.left, left_bounds), child(&self.node.right, right_bounds)]
+    }
+}
+
+fn extend_bounding_box(curr_node: &Option<Box<KdTreeNode>>, bounding_box: &mut BoundingBox) {
+    if let Some(node) = curr_node {
+        bounding_box.extend(&node.point);
+        extend_bounding_box(&node.left, bounding_box);
+        extend_bounding_box(&node.right, bounding_box);
+    }
+}
+
+pub(crate) fn construct_kd_tree<S: Coordinate>(mut points: Vec<Point<S>>, curr_dimension: &Dimension) -> Option<Box<KdTreeNode<S>>> {
     // base cases
     // remaining points is 1 or 0 length
     match points.len() {
@@ -54,7 +150,7 @@ fn construct_kd_tree(mut points: Vec<Point>, curr_dimension: &Dimension) -> Opti
     }
 }
 
-fn partition(mut points: Vec<Point>, curr_dimension: &Dimension) -> (Point, Vec<Point>, Vec<Point>) {
+fn partition<S: Coordinate>(mut points: Vec<Point<S>>, curr_dimension: &Dimension) -> (Point<S>, Vec<Point<S>>, Vec<Point<S>>) {
     let pivot = get_pivot(&mut points, curr_dimension);
 
     let mut left_sub_tree = vec![];
@@ -71,12 +167,12 @@ fn partition(mut points: Vec<Point>, curr_dimension: &Dimension) -> (Point, Vec<
 
 /// We calculate the mean then find the pivot point that has the closest value
 /// in the current dimension.
-fn get_pivot(points: &mut Vec<Point>, curr_dimension: &Dimension) -> Point {
-    let mean = calculate_mean(&points, curr_dimension);
+fn get_pivot<S: Coordinate>(points: &mut Vec<Point<S>>, curr_dimension: &Dimension) -> Point<S> {
+    let mean = calculate_mean(points, curr_dimension);
     let mut min_diff = f64::MAX;
     let mut mean_index = 0;
     for (i, point) in points.iter().enumerate() {
-        let curr_point_dimension = point.get_dimension(curr_dimension);
+        let curr_point_dimension = point.get_dimension(curr_dimension).to_f64();
         let diff = (curr_point_dimension - mean).abs();
         if diff < min_diff {
             min_diff = diff;
@@ -86,16 +182,19 @@ fn get_pivot(points: &mut Vec<Point>, curr_dimension: &Dimension) -> Point {
     points.swap_remove(mean_index)
 }
 
-fn calculate_mean(points: &Vec<Point>, curr_dimension: &Dimension) -> f64 {
+/// The mean is summed in `f64` whatever the coordinate type, since summing millions of `f32`s would lose most of their precision.
+fn calculate_mean<S: Coordinate>(points: &[Point<S>], curr_dimension: &Dimension) -> f64 {
     if points.is_empty() { return f64::default(); }
-    points.iter().map(|p| p.get_dimension(curr_dimension)).sum::<f64>() / points.len() as f64
+    points.iter().map(|p| p.get_dimension(curr_dimension).to_f64()).sum::<f64>() / points.len() as f64
 }
 
 #[cfg(test)]
 mod kd_tree_test {
+    use crate::bounding_box::BoundingBox;
     use crate::point::{Dimension, Point};
+    use crate::spatial_index::SpatialIndex;
 
-    use super::{calculate_mean, construct_kd_tree, get_pivot, KdTreeNode, partition};
+    use super::{calculate_mean, construct_kd_tree, get_pivot, KdTree, KdTreeNode, partition};
 
     #[test]
     fn get_x_mean_from_points() {
@@ -135,7 +234,7 @@ mod kd_tree_test {
 
     #[test]
     fn get_mean_from_empty_vec() {
-        let points = vec![];
+        let points: Vec<Point> = vec![];
         let output = calculate_mean(&points, &Dimension::X);
         let expected = 0.0;
         assert_eq!(output, expected);
@@ -271,7 +370,7 @@ mod kd_tree_test {
 
     #[test]
     fn build_kd_tree_empty_vec() {
-        let points = vec![];
+        let points: Vec<Point> = vec![];
         let output = construct_kd_tree(points, &Dimension::X);
         let expected = None;
         assert_eq!(output, expected);
@@ -305,5 +404,39 @@ mod kd_tree_test {
 
         assert_eq!(output, expected);
     }
-}
 
+    #[test]
+    fn bounding_box_of_tree() {
+        let points = vec![
+            Point::new(0.1, 0.6, 0.3),
+            Point::new(0.2, 0.2, 0.9),
+            Point::new(0.7, 0.3, 0.2),
+            Point::new(0.4, 0.5, 0.5),
+        ];
+        let output = KdTreeNode::construct_tree(points).bounding_box();
+        let expected = BoundingBox::new(Point::new(0.1, 0.2, 0.2), Point::new(0.7, 0.6, 0.9));
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn points_in_box_include_boundary() {
+        let points = vec![
+            Point::new(0.1, 0.6, 0.3),
+            Point::new(0.2, 0.2, 0.9),
+            Point::new(0.7, 0.3, 0.2),
+            Point::new(0.4, 0.5, 0.5),
+        ];
+        let tree = KdTree::construct_tree(points);
+        let mut output = tree.in_box(&BoundingBox::new(Point::new(0.1, 0.2, 0.3), Point::new(0.4, 0.6, 0.9)));
+        output.sort_by(|a, b| a.x.total_cmp(&b.x));
+        let expected = vec![Point::new(0.1, 0.6, 0.3), Point::new(0.2, 0.2, 0.9), Point::new(0.4, 0.5, 0.5)];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn empty_tree_holds_no_points() {
+        let tree = KdTree::construct_tree(vec![]);
+        assert!(tree.is_empty());
+        assert!(tree.in_box(&BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(1.0, 1.0, 1.0))).is_empty());
+    }
+}
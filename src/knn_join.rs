@@ -0,0 +1,228 @@
+use std::collections::BinaryHeap;
+
+use crate::bounding_box::BoundingBox;
+use crate::kd_tree::{KdTreeNode, Subtree};
+use crate::nearest_neighbour::{is_potential_nearer_neighbour, Neighbour};
+use crate::point::{Dimension, Point};
+
+/// Maximum number of queries in a leaf of the query tree.
+const QUERY_LEAF_SIZE: usize = 8;
+
+/// Struct for a node of the kd-tree built over the query points, which covers the queries at `order[start..end]`.
+struct QueryNode {
+    bounds: BoundingBox,
+    start: usize,
+    end: usize,
+    /// Largest k-th nearest neighbour distance of the queries below this node, infinite while any has fewer than k.
+    max_distance: f64,
+    children: Option<Box<[QueryNode; 2]>>,
+}
+
+/// Struct that keeps track of the nearest neighbours found so far for every query point.
+struct KnnJoin<'a> {
+    queries: &'a [Point],
+    order: Vec<usize>,
+    k_nearest_neighbours: Vec<BinaryHeap<Neighbour>>,
+    k: usize,
+}
+
+/// Function to find the k nearest neighbours within the reference kd-tree for every query point.
+///
+/// A kd-tree is built over the query points as well, and both trees are walked together over pairs of
+/// query and reference nodes. A pair is pruned as soon as the distance between their boxes exceeds the
+/// largest k-th nearest neighbour distance found so far for the queries of the query node.
+/// The neighbours of each query are sorted by distance and returned in the same order as `queries`.
+pub fn find_k_nearest_neighbours_join(queries: &[Point], reference_tree: &Option<Box<KdTreeNode>>, k: usize) -> Vec<Vec<Neighbour>> {
+    if k == 0 {
+        return vec![vec![]; queries.len()];
+    }
+    let mut join = KnnJoin {
+        queries,
+        order: (0..queries.len()).collect(),
+        k_nearest_neighbours: vec![BinaryHeap::new(); queries.len()],
+        k,
+    };
+    let query_tree = construct_query_tree(queries, &mut join.order, 0, &Dimension::X);
+    if let (Some(mut query_tree), Some(reference_root)) = (query_tree, reference_tree) {
+        join.search(&mut query_tree, &Subtree::root(reference_root));
+    }
+    join.k_nearest_neighbours.into_iter().map(BinaryHeap::into_sorted_vec).collect()
+}
+
+/// Builds the query tree over `order`, arranging the query indices in kd-tree order by splitting at
+/// the median of the current dimension, so that every leaf covers a run of nearby queries.
+fn construct_query_tree(queries: &[Point], order: &mut [usize], start: usize, curr_dimension: &Dimension) -> Option<QueryNode> {
+    let bounds = BoundingBox::from_points(order.iter().map(|&i| &queries[i]))?;
+    let end = start + order.len();
+    let mut children = None;
+    if order.len() > QUERY_LEAF_SIZE {
+        // keep the left half a whole number of leaves so that leaves never straddle the split
+        let middle = (order.len() / 2).div_ceil(QUERY_LEAF_SIZE) * QUERY_LEAF_SIZE;
+        order.select_nth_unstable_by(middle, |&a, &b| queries[a].compare_dimension(&queries[b], curr_dimension));
+        let (left, right) = order.split_at_mut(middle);
+        let left = construct_query_tree(queries, left, start, &curr_dimension.turn());
+        let right = construct_query_tree(queries, right, start + middle, &curr_dimension.turn());
+        children = left.zip(right).map(|(left, right)| Box::new([left, right]));
+    }
+    Some(QueryNode { bounds, start, end, max_distance: f64::INFINITY, children })
+}
+
+impl KnnJoin<'_> {
+    /// Walks a pair of query and reference nodes: the reference node's point is offered to every query
+    /// below the query node, then the children of both nodes are paired up, descending the query tree
+    /// until its leaves and visiting the nearer reference child first.
+    fn search(&mut self, query_node: &mut QueryNode, reference: &Subtree) {
+        if query_node.bounds.distance_to_box(&reference.bounds) > query_node.max_distance {
+            return;
+        }
+
+        let reference_point = &reference.node.point;
+        for &i in &self.order[query_node.start..query_node.end] {
+            let curr_distance = self.queries[i].distance_to(reference_point);
+            let k_nearest_neighbours = &mut self.k_nearest_neighbours[i];
+            if is_potential_nearer_neighbour(curr_distance, k_nearest_neighbours, self.k) {
+                if k_nearest_neighbours.len() >= self.k { k_nearest_neighbours.pop(); }
+                k_nearest_neighbours.push(Neighbour { distance: curr_distance, point: reference_point.clone() });
+            }
+        }
+
+        let reference_children = reference.children();
+        match query_node.children.as_deref_mut() {
+            Some(query_children) => {
+                for query_child in query_children.iter_mut() {
+                    self.search_nearer_first(query_child, &reference_children);
+                }
+                query_node.max_distance = query_children[0].max_distance.max(query_children[1].max_distance);
+            }
+            None => {
+                self.search_nearer_first(query_node, &reference_children);
+                query_node.max_distance = self.max_distance(query_node);
+            }
+        }
+    }
+
+    /// Pairs the query node with each of the reference children, the one with the nearer box first.
+    fn search_nearer_first(&mut self, query_node: &mut QueryNode, reference_children: &[Option<Subtree>; 2]) {
+        let distance = |child: &Option<Subtree>| {
+            child.as_ref().map_or(f64::INFINITY, |child| query_node.bounds.distance_to_box(&child.bounds))
+        };
+        let [left, right] = reference_children;
+        let (nearer, further) = if distance(right) < distance(left) { (right, left) } else { (left, right) };
+        for child in [nearer, further].into_iter().flatten() {
+            self.search(query_node, child);
+        }
+    }
+
+    /// Returns the largest k-th nearest neighbour distance of the queries of a leaf.
+    fn max_distance(&self, query_node: &QueryNode) -> f64 {
+        self.order[query_node.start..query_node.end].iter().map(|&i| {
+            let k_nearest_neighbours = &self.k_nearest_neighbours[i];
+            match k_nearest_neighbours.peek() {
+                Some(n) if k_nearest_neighbours.len() >= self.k => n.distance,
+                _ => f64::INFINITY,
+            }
+        }).fold(0.0, f64::max)
+    }
+}
+
+#[cfg(test)]
+mod knn_join_test {
+    use crate::bounding_box::BoundingBox;
+    use crate::kd_tree::KdTreeNode;
+    use crate::point::{Dimension, Point};
+
+    use super::{construct_query_tree, find_k_nearest_neighbours_join, QueryNode, QUERY_LEAF_SIZE};
+
+    fn tree(points: Vec<Point>) -> Option<Box<KdTreeNode>> {
+        Some(Box::new(KdTreeNode::construct_tree(points)))
+    }
+
+    fn leaves(node: &QueryNode, order: &[usize], output: &mut Vec<Vec<usize>>) {
+        match &node.children {
+            Some(children) => children.iter().for_each(|child| leaves(child, order, output)),
+            None => output.push(order[node.start..node.end].to_vec()),
+        }
+    }
+
+    #[test]
+    fn query_tree_leaves_hold_nearby_queries() {
+        let queries = (0..4 * QUERY_LEAF_SIZE).map(|i| {
+            let coordinate = 1.0 - i as f64 / 32.0;
+            Point::new(coordinate, coordinate, coordinate)
+        }).collect::<Vec<Point>>();
+        let mut order = (0..queries.len()).collect::<Vec<usize>>();
+        let query_tree = construct_query_tree(&queries, &mut order, 0, &Dimension::X).unwrap();
+        let mut output = vec![];
+        leaves(&query_tree, &order, &mut output);
+        output.iter_mut().for_each(|leaf| leaf.sort());
+        let expected = (0..4).rev()
+            .map(|n| (n * QUERY_LEAF_SIZE..(n + 1) * QUERY_LEAF_SIZE).collect::<Vec<usize>>())
+            .collect::<Vec<Vec<usize>>>();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn query_tree_bounds_hold_its_queries() {
+        let queries = (0..20).map(|i| Point::new(i as f64 / 20.0, 0.5, 1.0 - i as f64 / 20.0)).collect::<Vec<Point>>();
+        let mut order = (0..queries.len()).collect::<Vec<usize>>();
+        let query_tree = construct_query_tree(&queries, &mut order, 0, &Dimension::X).unwrap();
+        let output = BoundingBox::from_points(&queries).unwrap();
+        assert_eq!(output, query_tree.bounds);
+        for child in query_tree.children.unwrap().iter() {
+            assert!(order[child.start..child.end].iter().all(|&i| child.bounds.contains(&queries[i])));
+        }
+    }
+
+    #[test]
+    fn join_keeps_query_order() {
+        let reference_tree = tree(vec![
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(0.5, 0.5, 0.5),
+            Point::new(1.0, 1.0, 1.0),
+        ]);
+        let queries = vec![Point::new(0.9, 0.9, 0.9), Point::new(0.1, 0.1, 0.1), Point::new(0.4, 0.5, 0.5)];
+        let output = find_k_nearest_neighbours_join(&queries, &reference_tree, 1)
+            .into_iter()
+            .map(|neighbours| neighbours[0].point.clone())
+            .collect::<Vec<Point>>();
+        let expected = vec![Point::new(1.0, 1.0, 1.0), Point::new(0.0, 0.0, 0.0), Point::new(0.5, 0.5, 0.5)];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn join_with_duplicate_queries() {
+        let reference_tree = tree(vec![Point::new(0.2, 0.2, 0.2), Point::new(0.8, 0.8, 0.8)]);
+        let queries = vec![Point::new(0.3, 0.3, 0.3), Point::new(0.3, 0.3, 0.3)];
+        let output = find_k_nearest_neighbours_join(&queries, &reference_tree, 2);
+        assert_eq!(output[0], output[1]);
+        assert_eq!(output[0].len(), 2);
+    }
+
+    #[test]
+    fn join_with_fewer_than_k_reference_points() {
+        let reference_tree = tree(vec![Point::new(0.2, 0.2, 0.2), Point::new(0.8, 0.8, 0.8)]);
+        let output = find_k_nearest_neighbours_join(&[Point::new(0.5, 0.5, 0.5)], &reference_tree, 10)[0].len();
+        let expected = 2;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn join_with_empty_reference_tree() {
+        let output = find_k_nearest_neighbours_join(&[Point::new(0.5, 0.5, 0.5)], &None, 10);
+        let expected: Vec<Vec<_>> = vec![vec![]];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn join_with_zero_k() {
+        let output = find_k_nearest_neighbours_join(&[Point::new(0.5, 0.5, 0.5)], &tree(vec![Point::new(0.5, 0.5, 0.5)]), 0);
+        let expected: Vec<Vec<_>> = vec![vec![]];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn join_without_queries() {
+        let output = find_k_nearest_neighbours_join(&[], &tree(vec![Point::new(0.5, 0.5, 0.5)]), 10);
+        assert!(output.is_empty());
+    }
+}
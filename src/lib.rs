@@ -3,11 +3,33 @@
 //!
 //! ## Description
 //! Generate a huge number of random 3D points and build a kd-tree with those points, then use the kd-tree to find the nearest neighbours to the target point.
+use rand::{Rng, thread_rng};
+
 use crate::point::Point;
 
 pub mod point;
+pub mod scalar;
 pub mod kd_tree;
 pub mod nearest_neighbour;
+pub mod bounding_box;
+pub mod closest_pair;
+pub mod knn_join;
+pub mod ball_tree;
+pub mod vp_tree;
+pub mod octree;
+pub mod rtree;
+pub mod spatial_hash;
+pub mod hnsw;
+pub mod brute_force;
+pub mod spatial_index;
+pub mod quantized_kd_tree;
+pub mod formats;
+#[cfg(feature = "serde")]
+pub mod serialization;
+#[cfg(feature = "mmap")]
+pub mod mapped_kd_tree;
+#[cfg(feature = "mmap")]
+pub mod out_of_core;
 
 /// Const for the number of nearest neighbours that we want to find for most of our tests.
 pub const NUM_OF_NEAREST_NEIGHBOURS: usize = 10;
@@ -19,4 +41,14 @@ pub fn generate_random_points(n: u32) -> Vec<Point> {
         points.push(Point::random());
     }
     points
-}
\ No newline at end of file
+}
+
+/// Function for generating n random vectors with the given number of dimensions, each coordinate between 0 and 1.
+pub fn generate_random_vectors(n: u32, dimensions: usize) -> Vec<Vec<f64>> {
+    let mut rng = thread_rng();
+    let mut vectors = vec![];
+    for _ in 0..n {
+        vectors.push((0..dimensions).map(|_| rng.gen_range(0.0..=1.0)).collect());
+    }
+    vectors
+}
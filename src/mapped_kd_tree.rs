@@ -0,0 +1,497 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::kd_tree::{KdTree, KdTreeNode};
+use crate::nearest_neighbour::{is_potential_nearer_neighbour, Neighbour, NeighbourSearch};
+use crate::point::{Dimension, Point};
+use crate::scalar::{Accumulation, Native, Scalar};
+
+/// Bytes that every mapped kd-tree file starts with.
+const MAGIC: &[u8; 8] = b"FCPMKDT\0";
+
+/// Version of the mapped kd-tree layout, which is bumped whenever the layout changes.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Number of bytes in the header, which keeps the records after it aligned to 16 bytes.
+pub const HEADER_LEN: usize = 48;
+
+/// Number of dimensions of the points, which is the only count this build supports.
+const DIMENSIONS: u32 = 3;
+
+/// Trait for the scalars that a mapped kd-tree can store its coordinates in, which are written little-endian.
+pub trait MappedScalar: Scalar {
+    /// Code for the scalar type in the header of a mapped kd-tree file.
+    const TYPE_CODE: u32;
+    const SIZE: usize;
+
+    fn read_le(bytes: &[u8]) -> Self;
+
+    fn write_le(self, buffer: &mut Vec<u8>);
+}
+
+impl MappedScalar for f32 {
+    const TYPE_CODE: u32 = 1;
+    const SIZE: usize = 4;
+
+    fn read_le(bytes: &[u8]) -> f32 {
+        f32::from_le_bytes(bytes[..4].try_into().unwrap())
+    }
+
+    fn write_le(self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl MappedScalar for f64 {
+    const TYPE_CODE: u32 = 2;
+    const SIZE: usize = 8;
+
+    fn read_le(bytes: &[u8]) -> f64 {
+        f64::from_le_bytes(bytes[..8].try_into().unwrap())
+    }
+
+    fn write_le(self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+/// Enum for the ways writing or opening a mapped kd-tree can fail.
+#[derive(Debug)]
+pub enum MappedTreeError {
+    Io(io::Error),
+    /// The file does not start with the bytes of a mapped kd-tree.
+    NotATree,
+    /// The file was written in a version of the layout that this build cannot read.
+    UnsupportedVersion(u32),
+    /// The points in the file have a number of dimensions that this build cannot search.
+    UnsupportedDimensions(u32),
+    /// The file stores its coordinates in another scalar type than it is opened with.
+    ScalarMismatch { expected: u32, found: u32 },
+    /// The file is not as long as its header says, so it was probably truncated.
+    WrongLength { expected: u64, found: u64 },
+    /// The records do not match the checksum in the header, so the file is corrupted.
+    ChecksumMismatch { expected: u32, found: u32 },
+    /// The records match their checksum but do not form a tree, so the file was written wrongly.
+    Corrupt(String),
+}
+
+impl fmt::Display for MappedTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MappedTreeError::Io(error) => write!(f, "I/O error: {error}"),
+            MappedTreeError::NotATree => write!(f, "not a mapped kd-tree"),
+            MappedTreeError::UnsupportedVersion(version) =>
+                write!(f, "unsupported mapped kd-tree version {version}, expected {FORMAT_VERSION}"),
+            MappedTreeError::UnsupportedDimensions(dimensions) =>
+                write!(f, "mapped kd-tree has {dimensions} dimensions but only {DIMENSIONS} are supported"),
+            MappedTreeError::ScalarMismatch { expected, found } =>
+                write!(f, "mapped kd-tree has scalar type {found} but {expected} was expected"),
+            MappedTreeError::WrongLength { expected, found } =>
+                write!(f, "mapped kd-tree should be {expected} bytes long but is {found}"),
+            MappedTreeError::ChecksumMismatch { expected, found } =>
+                write!(f, "mapped kd-tree checksum is {found:#010x} but {expected:#010x} was recorded"),
+            MappedTreeError::Corrupt(reason) => write!(f, "corrupt mapped kd-tree: {reason}"),
+        }
+    }
+}
+
+impl Error for MappedTreeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MappedTreeError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MappedTreeError {
+    fn from(error: io::Error) -> MappedTreeError {
+        MappedTreeError::Io(error)
+    }
+}
+
+/// Returns the number of bytes taken by one point, its coordinates followed by the size of its left subtree.
+fn record_len<S: MappedScalar>() -> usize {
+    DIMENSIONS as usize * S::SIZE + 8
+}
+
+impl<S: MappedScalar> KdTree<S> {
+    /// Writes the tree to a file that `MappedKdTree::open` can search in place.
+    ///
+    /// After a 48-byte header of the magic number, layout version, dimension count, scalar type code,
+    /// record length, point count and a CRC-32 of the records, every point is stored in pre-order
+    /// as its little-endian coordinates followed by the number of points in its left subtree as a `u64`.
+    pub fn save_mapped<P: AsRef<Path>>(&self, path: P) -> Result<(), MappedTreeError> {
+        let mut writer = MappedTreeWriter::create(path)?;
+        writer.write_subtree(&self.root)?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+/// Struct that writes the records of a mapped kd-tree in pre-order, and its header once they are all written.
+pub(crate) struct MappedTreeWriter<S> {
+    writer: BufWriter<File>,
+    hasher: crc32fast::Hasher,
+    record: Vec<u8>,
+    len: u64,
+    scalar: PhantomData<S>,
+}
+
+impl<S: MappedScalar> MappedTreeWriter<S> {
+    /// Creates the file at `path`, leaving room for the header.
+    pub(crate) fn create<P: AsRef<Path>>(path: P) -> io::Result<MappedTreeWriter<S>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&[0; HEADER_LEN])?;
+        Ok(MappedTreeWriter { writer, hasher: crc32fast::Hasher::new(), record: Vec::with_capacity(record_len::<S>()), len: 0, scalar: PhantomData })
+    }
+
+    /// Writes the next point in pre-order, whose left subtree holds `left_len` points.
+    pub(crate) fn write_point(&mut self, point: &Point<S>, left_len: u64) -> io::Result<()> {
+        self.record.clear();
+        point.x.write_le(&mut self.record);
+        point.y.write_le(&mut self.record);
+        point.z.write_le(&mut self.record);
+        self.record.extend_from_slice(&left_len.to_le_bytes());
+        self.hasher.update(&self.record);
+        self.len += 1;
+        self.writer.write_all(&self.record)
+    }
+
+    /// Writes every point of the subtree in pre-order.
+    pub(crate) fn write_subtree(&mut self, root: &Option<Box<KdTreeNode<S>>>) -> io::Result<()> {
+        let mut left_lens = vec![];
+        count_left_subtrees(root, &mut left_lens);
+        self.write_records(root, &mut left_lens.into_iter())
+    }
+
+    fn write_records(&mut self, curr_node: &Option<Box<KdTreeNode<S>>>, left_lens: &mut impl Iterator<Item=u64>) -> io::Result<()> {
+        let Some(curr_node) = curr_node else { return Ok(()); };
+        self.write_point(&curr_node.point, left_lens.next().unwrap())?;
+        self.write_records(&curr_node.left, left_lens)?;
+        self.write_records(&curr_node.right, left_lens)
+    }
+
+    /// Writes the header and returns the number of points in the tree.
+    pub(crate) fn finish(mut self) -> io::Result<u64> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        header.extend_from_slice(&DIMENSIONS.to_le_bytes());
+        header.extend_from_slice(&S::TYPE_CODE.to_le_bytes());
+        header.extend_from_slice(&(record_len::<S>() as u32).to_le_bytes());
+        header.extend_from_slice(&self.len.to_le_bytes());
+        header.extend_from_slice(&self.hasher.finalize().to_le_bytes());
+        header.resize(HEADER_LEN, 0);
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&header)?;
+        self.writer.flush()?;
+        Ok(self.len)
+    }
+}
+
+/// Pushes the size of the left subtree of every node in pre-order and returns the size of the tree.
+fn count_left_subtrees<S: MappedScalar>(curr_node: &Option<Box<KdTreeNode<S>>>, left_lens: &mut Vec<u64>) -> u64 {
+    let Some(curr_node) = curr_node else { return 0; };
+    let position = left_lens.len();
+    left_lens.push(0);
+    let left_len = count_left_subtrees(&curr_node.left, left_lens);
+    let right_len = count_left_subtrees(&curr_node.right, left_lens);
+    left_lens[position] = left_len;
+    left_len + right_len + 1
+}
+
+/// Struct that represents a kd-tree written by `KdTree::save_mapped` and memory-mapped from its file,
+/// which is searched in place without building any `KdTreeNode`s. Opening it reads the file once to check
+/// its checksum, but after that it only takes the memory of the pages that the searches touch.
+pub struct MappedKdTree<S = f64> {
+    mmap: Mmap,
+    len: usize,
+    scalar: PhantomData<S>,
+}
+
+impl<S: MappedScalar> MappedKdTree<S> {
+    /// Maps the file at `path`, checking its header, its length, the checksum of its records and
+    /// that the subtree sizes they record fit inside each other.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<MappedKdTree<S>, MappedTreeError> {
+        let file = File::open(path)?;
+        // SAFETY: the map is only read, and the file must not be changed while it is open,
+        // which is the contract of every memory-mapped file
+        let mmap = unsafe { Mmap::map(&file)? };
+        let read_u32 = |offset: usize| u32::from_le_bytes(mmap[offset..offset + 4].try_into().unwrap());
+
+        if mmap.len() < HEADER_LEN || &mmap[..8] != MAGIC {
+            return Err(MappedTreeError::NotATree);
+        }
+        if read_u32(8) != FORMAT_VERSION {
+            return Err(MappedTreeError::UnsupportedVersion(read_u32(8)));
+        }
+        if read_u32(12) != DIMENSIONS {
+            return Err(MappedTreeError::UnsupportedDimensions(read_u32(12)));
+        }
+        if read_u32(16) != S::TYPE_CODE || read_u32(20) as usize != record_len::<S>() {
+            return Err(MappedTreeError::ScalarMismatch { expected: S::TYPE_CODE, found: read_u32(16) });
+        }
+        let len = u64::from_le_bytes(mmap[24..32].try_into().unwrap());
+        let expected_len = (HEADER_LEN as u64).saturating_add(len.saturating_mul(record_len::<S>() as u64));
+        if mmap.len() as u64 != expected_len {
+            return Err(MappedTreeError::WrongLength { expected: expected_len, found: mmap.len() as u64 });
+        }
+        let checksum = crc32fast::hash(&mmap[HEADER_LEN..]);
+        if checksum != read_u32(32) {
+            return Err(MappedTreeError::ChecksumMismatch { expected: read_u32(32), found: checksum });
+        }
+        let tree = MappedKdTree { mmap, len: len as usize, scalar: PhantomData };
+        tree.check_subtrees()?;
+        Ok(tree)
+    }
+
+    /// Returns the number of points in the tree.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the tree holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the k nearest neighbours to the given point, sorted by distance,
+    /// with the distances accumulated as `A` decides.
+    pub fn nearest_with<A: Accumulation<S>>(&self, given_point: &Point<S>, k: usize) -> Vec<Neighbour<Point<S>, A::Output>>
+        where A::Output: Scalar {
+        let mut k_nearest_squared = BinaryHeap::new();
+        self.find_k_nearest_squared::<A>(0, self.len, given_point, &Dimension::X, &mut k_nearest_squared, k);
+        k_nearest_squared.into_sorted_vec().into_iter()
+            .map(|n| Neighbour { distance: n.distance.sqrt(), point: n.point })
+            .collect()
+    }
+
+    /// Returns every neighbour whose distance to the given point is at most `radius`, sorted by distance.
+    pub fn within_radius_of(&self, given_point: &Point<S>, radius: S) -> Vec<Neighbour<Point<S>, S>> {
+        let mut neighbours = vec![];
+        self.find_neighbours_within_radius(0, self.len, given_point, &Dimension::X, radius, &mut neighbours);
+        neighbours.sort();
+        neighbours
+    }
+
+    fn record(&self, position: usize) -> &[u8] {
+        let start = HEADER_LEN + position * record_len::<S>();
+        &self.mmap[start..start + record_len::<S>()]
+    }
+
+    fn point(&self, position: usize) -> Point<S> {
+        let record = self.record(position);
+        Point { x: S::read_le(record), y: S::read_le(&record[S::SIZE..]), z: S::read_le(&record[2 * S::SIZE..]) }
+    }
+
+    fn left_len(&self, position: usize) -> u64 {
+        u64::from_le_bytes(self.record(position)[3 * S::SIZE..].try_into().unwrap())
+    }
+
+    /// Returns the position and size of the left and right subtrees of the subtree of `len` points at `position`,
+    /// which `check_subtrees` has made sure lie within that subtree.
+    fn children(&self, position: usize, len: usize) -> ((usize, usize), (usize, usize)) {
+        let left_len = self.left_len(position) as usize;
+        ((position + 1, left_len), (position + 1 + left_len, len - 1 - left_len))
+    }
+
+    /// Walks the records once in pre-order, checking that the left subtree of every point is smaller
+    /// than the subtree the point roots, so that searches never step outside the records.
+    fn check_subtrees(&self) -> Result<(), MappedTreeError> {
+        let mut subtrees = vec![(0, self.len)];
+        while let Some((position, len)) = subtrees.pop() {
+            if len == 0 { continue; }
+            let left_len = self.left_len(position);
+            if left_len >= len as u64 {
+                return Err(MappedTreeError::Corrupt(
+                    format!("point {position} has {left_len} points on its left but only {} below it", len - 1)));
+            }
+            let left_len = left_len as usize;
+            subtrees.push((position + 1 + left_len, len - 1 - left_len));
+            subtrees.push((position + 1, left_len));
+        }
+        Ok(())
+    }
+
+    /// Keeps the k points with the smallest squared distance to the given point in `k_nearest_squared`,
+    /// visiting the subtrees in the same order as the search of a `KdTree`.
+    fn find_k_nearest_squared<A: Accumulation<S>>(&self, position: usize, len: usize, given_point: &Point<S>, curr_dimension: &Dimension,
+                                                  k_nearest_squared: &mut BinaryHeap<Neighbour<Point<S>, A::Output>>, k: usize) {
+        if len == 0 { return; }
+        let curr_point = self.point(position);
+        let ((left, left_len), (right, right_len)) = self.children(position, len);
+        let squared_distance_to_other_bounding_box = A::squared_difference(curr_point.get_dimension(curr_dimension), given_point.get_dimension(curr_dimension));
+
+        let ((near, near_len), (far, far_len)) = match given_point.compare_dimension(&curr_point, curr_dimension) {
+            Ordering::Equal | Ordering::Greater => ((right, right_len), (left, left_len)),
+            Ordering::Less => ((left, left_len), (right, right_len)),
+        };
+        self.find_k_nearest_squared::<A>(near, near_len, given_point, &curr_dimension.turn(), k_nearest_squared, k);
+        if is_potential_nearer_neighbour(squared_distance_to_other_bounding_box, k_nearest_squared, k) {
+            self.find_k_nearest_squared::<A>(far, far_len, given_point, &curr_dimension.turn(), k_nearest_squared, k);
+        }
+
+        let curr_squared_distance = given_point.squared_distance_with::<A>(&curr_point);
+        if is_potential_nearer_neighbour(curr_squared_distance, k_nearest_squared, k) {
+            if k_nearest_squared.len() >= k { k_nearest_squared.pop(); }
+            k_nearest_squared.push(Neighbour { distance: curr_squared_distance, point: curr_point });
+        }
+    }
+
+    fn find_neighbours_within_radius(&self, position: usize, len: usize, given_point: &Point<S>, curr_dimension: &Dimension,
+                                     radius: S, neighbours: &mut Vec<Neighbour<Point<S>, S>>) {
+        if len == 0 { return; }
+        let curr_point = self.point(position);
+        let ((left, left_len), (right, right_len)) = self.children(position, len);
+        let distance_to_split = curr_point.get_dimension(curr_dimension) - given_point.get_dimension(curr_dimension);
+
+        // the left subtree only holds points below the split and the right subtree the rest
+        if distance_to_split > -radius {
+            self.find_neighbours_within_radius(left, left_len, given_point, &curr_dimension.turn(), radius, neighbours);
+        }
+        if distance_to_split <= radius {
+            self.find_neighbours_within_radius(right, right_len, given_point, &curr_dimension.turn(), radius, neighbours);
+        }
+
+        let curr_distance = given_point.distance_to(&curr_point);
+        if curr_distance <= radius {
+            neighbours.push(Neighbour { distance: curr_distance, point: curr_point });
+        }
+    }
+}
+
+impl NeighbourSearch for MappedKdTree {
+    fn nearest(&self, given_point: &Point, k: usize) -> Vec<Neighbour> {
+        self.nearest_with::<Native>(given_point, k)
+    }
+
+    fn within_radius(&self, given_point: &Point, radius: f64) -> Vec<Neighbour> {
+        self.within_radius_of(given_point, radius)
+    }
+}
+
+#[cfg(test)]
+mod mapped_kd_tree_test {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::kd_tree::KdTree;
+    use crate::nearest_neighbour::NeighbourSearch;
+    use crate::point::Point;
+
+    use super::{HEADER_LEN, MappedKdTree, MappedTreeError};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("find_closest_points_{}_{name}.kdtree", std::process::id()))
+    }
+
+    fn points() -> Vec<Point> {
+        vec![
+            Point::new(0.1, 0.2, 0.3),
+            Point::new(0.4, 0.5, 0.6),
+            Point::new(0.7, 0.8, 0.9),
+            Point::new(0.2, 0.9, 0.1),
+            Point::new(0.9, 0.1, 0.5),
+        ]
+    }
+
+    #[test]
+    fn file_has_header_and_one_record_per_point() {
+        let path = temp_path("records");
+        KdTree::construct_tree(points()).save_mapped(&path).unwrap();
+        let output = fs::metadata(&path).unwrap().len();
+        fs::remove_file(&path).unwrap();
+        let expected = (HEADER_LEN + 5 * 32) as u64;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn nearest_in_mapped_tree() {
+        let path = temp_path("nearest");
+        let tree = KdTree::construct_tree(points());
+        tree.save_mapped(&path).unwrap();
+        let mapped = MappedKdTree::open(&path).unwrap();
+        let given_point = Point::new(0.5, 0.5, 0.5);
+        let output = mapped.nearest(&given_point, 3);
+        let expected = tree.nearest(&given_point, 3);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(mapped.len(), 5);
+        assert_eq!(output.into_iter().map(|n| n.point).collect::<Vec<Point>>(), expected.into_iter().map(|n| n.point).collect::<Vec<Point>>());
+    }
+
+    #[test]
+    fn open_empty_tree() {
+        let path = temp_path("empty");
+        KdTree::<f64>::construct_tree(vec![]).save_mapped(&path).unwrap();
+        let mapped = MappedKdTree::<f64>::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(mapped.is_empty());
+        assert!(mapped.nearest(&Point::random(), 3).is_empty());
+    }
+
+    #[test]
+    fn reject_other_scalar() {
+        let path = temp_path("scalar");
+        KdTree::construct_tree(points()).save_mapped(&path).unwrap();
+        let output = MappedKdTree::<f32>::open(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(output, Err(MappedTreeError::ScalarMismatch { expected: 1, found: 2 })));
+    }
+
+    #[test]
+    fn reject_truncated_file() {
+        let path = temp_path("truncated");
+        KdTree::construct_tree(points()).save_mapped(&path).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.pop();
+        fs::write(&path, bytes).unwrap();
+        let output = MappedKdTree::<f64>::open(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(output, Err(MappedTreeError::WrongLength { .. })));
+    }
+
+    #[test]
+    fn reject_corrupted_record() {
+        let path = temp_path("corrupted");
+        KdTree::construct_tree(points()).save_mapped(&path).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[HEADER_LEN + 3] ^= 1;
+        fs::write(&path, bytes).unwrap();
+        let output = MappedKdTree::<f64>::open(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(output, Err(MappedTreeError::ChecksumMismatch { .. })));
+    }
+
+    #[test]
+    fn reject_left_subtree_larger_than_its_parent() {
+        let path = temp_path("left_len");
+        KdTree::construct_tree(points()).save_mapped(&path).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        // the root's left subtree claims every point, and the checksum is recomputed so only the walk can tell
+        bytes[HEADER_LEN + 24..HEADER_LEN + 32].copy_from_slice(&5u64.to_le_bytes());
+        let checksum = crc32fast::hash(&bytes[HEADER_LEN..]);
+        bytes[32..36].copy_from_slice(&checksum.to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+        let output = MappedKdTree::<f64>::open(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(output, Err(MappedTreeError::Corrupt(_))));
+    }
+
+    #[test]
+    fn reject_other_files() {
+        let path = temp_path("other");
+        fs::write(&path, b"not a tree").unwrap();
+        let output = MappedKdTree::<f64>::open(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(output, Err(MappedTreeError::NotATree)));
+    }
+}
@@ -1,72 +1,195 @@
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
 
-use crate::kd_tree::KdTreeNode;
+use crate::kd_tree::{KdTree, KdTreeNode};
 use crate::point::{Dimension, Point};
+use crate::scalar::{Accumulation, Coordinate, Distance, Native, Scalar};
 
 /// Struct that keeps track of a current node in the tree
 /// and the distance of the node from the target point.
-#[derive(Debug)]
-pub struct Neighbour {
-    pub distance: f64,
-    pub point: Point,
+/// Indexes over items other than `Point` report their neighbours with the same struct,
+/// and the distance is an `f64` unless another `Distance` type is given.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Neighbour<T = Point, S = f64> {
+    pub distance: S,
+    pub point: T,
 }
 
-impl Eq for Neighbour {}
+impl<T, S: Distance> Eq for Neighbour<T, S> {}
 
-impl PartialEq<Self> for Neighbour {
+impl<T, S: Distance> PartialEq<Self> for Neighbour<T, S> {
     fn eq(&self, other: &Self) -> bool {
         self.distance.eq(&other.distance)
     }
 }
 
-impl PartialOrd<Self> for Neighbour {
+impl<T, S: Distance> PartialOrd<Self> for Neighbour<T, S> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.distance.partial_cmp(&other.distance)
+        Some(self.cmp(other))
     }
 }
 
-impl Ord for Neighbour {
+impl<T, S: Distance> Ord for Neighbour<T, S> {
     fn cmp(&self, other: &Self) -> Ordering {
         self.distance.total_cmp(&other.distance)
     }
 }
 
+/// Trait for spatial indexes that can answer nearest neighbour and radius queries,
+/// so that code can switch between index types without changing how it searches.
+pub trait NeighbourSearch {
+    /// Returns the k nearest neighbours to the given point, sorted by distance.
+    fn nearest(&self, given_point: &Point, k: usize) -> Vec<Neighbour>;
+
+    /// Returns every neighbour whose distance to the given point is at most `radius`, sorted by distance.
+    fn within_radius(&self, given_point: &Point, radius: f64) -> Vec<Neighbour>;
+}
+
+impl NeighbourSearch for KdTreeNode {
+    fn nearest(&self, given_point: &Point, k: usize) -> Vec<Neighbour> {
+        let mut k_nearest_squared = BinaryHeap::new();
+        find_k_nearest_squared_from_node::<f64, Native>(self, given_point, &Dimension::X, &mut k_nearest_squared, k);
+        k_nearest_squared.into_sorted_vec().into_iter().map(unsquare).collect()
+    }
+
+    fn within_radius(&self, given_point: &Point, radius: f64) -> Vec<Neighbour> {
+        let mut neighbours = vec![];
+        find_neighbours_within_radius_from_node(self, given_point, &Dimension::X, radius, &mut neighbours);
+        neighbours.sort();
+        neighbours
+    }
+}
+
+impl NeighbourSearch for KdTree {
+    fn nearest(&self, given_point: &Point, k: usize) -> Vec<Neighbour> {
+        self.nearest_with::<Native>(given_point, k)
+    }
+
+    fn within_radius(&self, given_point: &Point, radius: f64) -> Vec<Neighbour> {
+        let mut neighbours = vec![];
+        find_neighbours_within_radius(&self.root, given_point, &Dimension::X, radius, &mut neighbours);
+        neighbours.sort();
+        neighbours
+    }
+}
+
+impl<S: Coordinate> KdTree<S> {
+    /// Returns the k nearest neighbours to the given point, sorted by distance,
+    /// with the distances accumulated as `A` decides.
+    pub fn nearest_with<A: Accumulation<S>>(&self, given_point: &Point<S>, k: usize) -> Vec<Neighbour<Point<S>, A::Output>>
+        where A::Output: Scalar {
+        let mut k_nearest_neighbours = BinaryHeap::new();
+        find_k_nearest_neighbours_with::<S, A>(&self.root, given_point, &Dimension::X, &mut k_nearest_neighbours, k);
+        k_nearest_neighbours.into_sorted_vec()
+    }
+
+    /// Returns the k nearest neighbours to the given point, sorted by distance, reporting squared
+    /// distances accumulated as `A` decides. Points with integer coordinates are searched this way,
+    /// since the squared distances between them are exact integers.
+    pub fn nearest_squared_with<A: Accumulation<S>>(&self, given_point: &Point<S>, k: usize) -> Vec<Neighbour<Point<S>, A::Output>> {
+        let mut k_nearest_squared = BinaryHeap::new();
+        find_k_nearest_squared::<S, A>(&self.root, given_point, &Dimension::X, &mut k_nearest_squared, k);
+        k_nearest_squared.into_sorted_vec()
+    }
+}
+
 /// Function to find k nearest neighbours within kd-tree.
-pub fn find_k_nearest_neighbours(curr_node: &Option<Box<KdTreeNode>>, given_point: &Point, curr_dimension: &Dimension,
-                                 k_nearest_neighbours: &mut BinaryHeap<Neighbour>, k :usize) {
+pub fn find_k_nearest_neighbours<S: Scalar>(curr_node: &Option<Box<KdTreeNode<S>>>, given_point: &Point<S>, curr_dimension: &Dimension,
+                                            k_nearest_neighbours: &mut BinaryHeap<Neighbour<Point<S>, S>>, k :usize) {
+    find_k_nearest_neighbours_with::<S, Native>(curr_node, given_point, curr_dimension, k_nearest_neighbours, k);
+}
+
+/// Function to find k nearest neighbours within kd-tree, with the distances accumulated as `A` decides.
+pub fn find_k_nearest_neighbours_with<S: Coordinate, A: Accumulation<S>>(curr_node: &Option<Box<KdTreeNode<S>>>, given_point: &Point<S>,
+                                                                         curr_dimension: &Dimension,
+                                                                         k_nearest_neighbours: &mut BinaryHeap<Neighbour<Point<S>, A::Output>>, k :usize)
+    where A::Output: Scalar {
+    // the search compares squared distances, which order the points the same way, and only takes
+    // the square root of the neighbours it keeps
+    let mut k_nearest_squared = k_nearest_neighbours.drain()
+        .map(|n| Neighbour { distance: n.distance * n.distance, point: n.point })
+        .collect();
+    find_k_nearest_squared::<S, A>(curr_node, given_point, curr_dimension, &mut k_nearest_squared, k);
+    k_nearest_neighbours.extend(k_nearest_squared.into_iter().map(unsquare));
+}
+
+/// Function to find k nearest neighbours within kd-tree by their squared distance, accumulated as `A` decides.
+/// The neighbours in `k_nearest_squared` keep their squared distances.
+pub fn find_k_nearest_squared<S: Coordinate, A: Accumulation<S>>(curr_node: &Option<Box<KdTreeNode<S>>>, given_point: &Point<S>,
+                                                                 curr_dimension: &Dimension,
+                                                                 k_nearest_squared: &mut BinaryHeap<Neighbour<Point<S>, A::Output>>, k :usize) {
     if let Some(x) = curr_node {
-        let curr_node = x;
-        let curr_point = &curr_node.point;
-        let right_subtree = &curr_node.as_ref().right;
-        let left_subtree = &curr_node.as_ref().left;
-        let distance_to_other_bounding_box = (curr_point.get_dimension(curr_dimension) - given_point.get_dimension(curr_dimension)).abs();
-
-        match given_point.compare_dimension(&curr_point, curr_dimension) {
-            Ordering::Equal | Ordering::Greater => {
-                find_k_nearest_neighbours(right_subtree, given_point, &curr_dimension.turn(), k_nearest_neighbours, k);
-                if is_potential_nearer_neighbour(distance_to_other_bounding_box, k_nearest_neighbours, k) {
-                    find_k_nearest_neighbours(left_subtree, given_point, &curr_dimension.turn(), k_nearest_neighbours, k);
-                }
+        find_k_nearest_squared_from_node::<S, A>(x, given_point, curr_dimension, k_nearest_squared, k);
+    }
+}
+
+/// Keeps the k points with the smallest squared distance to the given point in `k_nearest_squared`.
+fn find_k_nearest_squared_from_node<S: Coordinate, A: Accumulation<S>>(curr_node: &KdTreeNode<S>, given_point: &Point<S>, curr_dimension: &Dimension,
+                                                                   k_nearest_squared: &mut BinaryHeap<Neighbour<Point<S>, A::Output>>, k :usize) {
+    let curr_point = &curr_node.point;
+    let right_subtree = &curr_node.right;
+    let left_subtree = &curr_node.left;
+    let squared_distance_to_other_bounding_box = A::squared_difference(curr_point.get_dimension(curr_dimension), given_point.get_dimension(curr_dimension));
+
+    match given_point.compare_dimension(curr_point, curr_dimension) {
+        Ordering::Equal | Ordering::Greater => {
+            find_k_nearest_squared::<S, A>(right_subtree, given_point, &curr_dimension.turn(), k_nearest_squared, k);
+            if is_potential_nearer_neighbour(squared_distance_to_other_bounding_box, k_nearest_squared, k) {
+                find_k_nearest_squared::<S, A>(left_subtree, given_point, &curr_dimension.turn(), k_nearest_squared, k);
             }
-            Ordering::Less => {
-                find_k_nearest_neighbours(left_subtree, given_point, &curr_dimension.turn(), k_nearest_neighbours, k);
-                if is_potential_nearer_neighbour(distance_to_other_bounding_box, k_nearest_neighbours, k) {
-                    find_k_nearest_neighbours(right_subtree, given_point, &curr_dimension.turn(), k_nearest_neighbours, k);
-                }
+        }
+        Ordering::Less => {
+            find_k_nearest_squared::<S, A>(left_subtree, given_point, &curr_dimension.turn(), k_nearest_squared, k);
+            if is_potential_nearer_neighbour(squared_distance_to_other_bounding_box, k_nearest_squared, k) {
+                find_k_nearest_squared::<S, A>(right_subtree, given_point, &curr_dimension.turn(), k_nearest_squared, k);
             }
         }
+    }
 
-        let curr_distance = given_point.distance_to(&curr_point);
+    let curr_squared_distance = given_point.squared_distance_with::<A>(curr_point);
 
-        if is_potential_nearer_neighbour(curr_distance, k_nearest_neighbours, k) {
-            if k_nearest_neighbours.len() >= k { k_nearest_neighbours.pop(); }
-            k_nearest_neighbours.push(Neighbour { distance: curr_distance, point: curr_point.clone() });
-        }
+    if is_potential_nearer_neighbour(curr_squared_distance, k_nearest_squared, k) {
+        if k_nearest_squared.len() >= k { k_nearest_squared.pop(); }
+        k_nearest_squared.push(Neighbour { distance: curr_squared_distance, point: curr_point.clone() });
+    }
+}
+
+/// Turns a neighbour found by squared distance into one with the actual distance.
+fn unsquare<T, S: Scalar>(neighbour: Neighbour<T, S>) -> Neighbour<T, S> {
+    Neighbour { distance: neighbour.distance.sqrt(), point: neighbour.point }
+}
+
+/// Function to find all neighbours within a given radius of the target point within kd-tree.
+/// The neighbours are appended to `neighbours` in no particular order.
+pub fn find_neighbours_within_radius<S: Scalar>(curr_node: &Option<Box<KdTreeNode<S>>>, given_point: &Point<S>, curr_dimension: &Dimension,
+                                                radius: S, neighbours: &mut Vec<Neighbour<Point<S>, S>>) {
+    if let Some(x) = curr_node {
+        find_neighbours_within_radius_from_node(x, given_point, curr_dimension, radius, neighbours);
+    }
+}
+
+fn find_neighbours_within_radius_from_node<S: Scalar>(curr_node: &KdTreeNode<S>, given_point: &Point<S>, curr_dimension: &Dimension,
+                                                      radius: S, neighbours: &mut Vec<Neighbour<Point<S>, S>>) {
+    let curr_point = &curr_node.point;
+    let distance_to_split = curr_point.get_dimension(curr_dimension) - given_point.get_dimension(curr_dimension);
+
+    // the left subtree only holds points below the split and the right subtree the rest
+    if distance_to_split > -radius {
+        find_neighbours_within_radius(&curr_node.left, given_point, &curr_dimension.turn(), radius, neighbours);
+    }
+    if distance_to_split <= radius {
+        find_neighbours_within_radius(&curr_node.right, given_point, &curr_dimension.turn(), radius, neighbours);
+    }
+
+    // the square root is only taken for the points that are kept
+    let curr_squared_distance = given_point.squared_distance_to(curr_point);
+    if curr_squared_distance <= radius * radius {
+        neighbours.push(Neighbour { distance: curr_squared_distance.sqrt(), point: curr_point.clone() });
     }
 }
 
-fn is_potential_nearer_neighbour(distance: f64, k_nearest_neighbours: &BinaryHeap<Neighbour>, k: usize) -> bool {
+pub(crate) fn is_potential_nearer_neighbour<T, S: Distance>(distance: S, k_nearest_neighbours: &BinaryHeap<Neighbour<T, S>>, k: usize) -> bool {
     if k_nearest_neighbours.len() < k { return true; }
     else {
         if let Some(n) = k_nearest_neighbours.peek() {
@@ -81,8 +204,9 @@ fn is_potential_nearer_neighbour(distance: f64, k_nearest_neighbours: &BinaryHea
 #[cfg(test)]
 mod neighbours_test {
     use std::collections::BinaryHeap;
-    use crate::nearest_neighbour::{is_potential_nearer_neighbour, Neighbour};
-    use crate::point::Point;
+    use crate::kd_tree::KdTreeNode;
+    use crate::nearest_neighbour::{find_k_nearest_neighbours, find_neighbours_within_radius, is_potential_nearer_neighbour, Neighbour, NeighbourSearch};
+    use crate::point::{Dimension, Point};
 
     #[test]
     fn less_than_k_neighbours(){
@@ -113,4 +237,47 @@ mod neighbours_test {
         let output = is_potential_nearer_neighbour(0.21, &neighbours, 2);
         assert!(output);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn find_neighbours_within_radius_of_point() {
+        let tree = Some(Box::new(KdTreeNode::construct_tree(vec![
+            Point::new(0.1, 0.1, 0.1),
+            Point::new(0.5, 0.5, 0.5),
+            Point::new(0.5, 0.5, 0.75),
+            Point::new(0.9, 0.9, 0.9),
+        ])));
+        let mut neighbours = vec![];
+        find_neighbours_within_radius(&tree, &Point::new(0.5, 0.5, 0.5), &Dimension::X, 0.25, &mut neighbours);
+        neighbours.sort();
+        let output = neighbours.into_iter().map(|n| n.point).collect::<Vec<Point>>();
+        let expected = vec![Point::new(0.5, 0.5, 0.5), Point::new(0.5, 0.5, 0.75)];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn nearest_in_kd_tree_is_sorted() {
+        let tree = KdTreeNode::construct_tree(vec![
+            Point::new(0.9, 0.9, 0.9),
+            Point::new(0.2, 0.2, 0.2),
+            Point::new(0.4, 0.4, 0.4),
+        ]);
+        let output = tree.nearest(&Point::new(0.5, 0.5, 0.5), 2).into_iter().map(|n| n.point).collect::<Vec<Point>>();
+        let expected = vec![Point::new(0.4, 0.4, 0.4), Point::new(0.2, 0.2, 0.2)];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn nearest_neighbours_have_actual_distances() {
+        let tree = Some(Box::new(KdTreeNode::construct_tree(vec![
+            Point::new(0.5, 0.5, 0.5),
+            Point::new(0.5, 0.5, 0.0),
+            Point::new(0.0, 0.0, 0.0),
+        ])));
+        let mut heap = BinaryHeap::new();
+        heap.push(Neighbour { distance: 0.6, point: Point::new(0.5, 0.5, 0.5) });
+        find_k_nearest_neighbours(&tree, &Point::new(0.5, 0.5, 0.5), &Dimension::X, &mut heap, 2);
+        let output = heap.into_sorted_vec().into_iter().map(|n| n.distance).collect::<Vec<f64>>();
+        let expected = vec![0.0, 0.5];
+        assert_eq!(output, expected);
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::BinaryHeap;
+
+use crate::bounding_box::BoundingBox;
+use crate::nearest_neighbour::{is_potential_nearer_neighbour, Neighbour, NeighbourSearch};
+use crate::point::Point;
+use crate::spatial_index::SpatialIndex;
+
+/// Default depth below which cells are never split.
+pub const DEFAULT_MAX_DEPTH: usize = 10;
+
+/// Default number of points a leaf holds before it is split into eight children.
+pub const DEFAULT_LEAF_CAPACITY: usize = 16;
+
+/// Struct that represents a cell of the octree.
+/// Only leaves hold points; every cell keeps the count and sum of the points below it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OctreeNode {
+    pub bounds: BoundingBox,
+    pub points: Vec<Point>,
+    pub children: Option<Box<[OctreeNode; 8]>>,
+    count: usize,
+    sum: [f64; 3],
+}
+
+/// Struct that represents an octree over a fixed box-shaped region of space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Octree {
+    pub root: OctreeNode,
+    max_depth: usize,
+    leaf_capacity: usize,
+}
+
+/// Struct that describes an occupied cell of the octree at some level of detail.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OctreeCell {
+    pub bounds: BoundingBox,
+    pub depth: usize,
+    /// Number of points inside the cell.
+    pub count: usize,
+    /// Mean of the points inside the cell, which stands in for them at this level of detail.
+    pub centroid: Point,
+}
+
+impl Octree {
+    /// Creates an empty octree covering the given region, using the default depth and leaf capacity.
+    pub fn new(bounds: BoundingBox) -> Octree {
+        Octree::with_limits(bounds, DEFAULT_MAX_DEPTH, DEFAULT_LEAF_CAPACITY)
+    }
+
+    /// Creates an empty octree covering the given region.
+    /// Leaves holding more than `leaf_capacity` points are split until they reach `max_depth`.
+    pub fn with_limits(bounds: BoundingBox, max_depth: usize, leaf_capacity: usize) -> Octree {
+        Octree { root: OctreeNode::new(bounds), max_depth, leaf_capacity: leaf_capacity.max(1) }
+    }
+
+    /// Constructs an octree over the smallest box containing the points, so that none of them is left out.
+    /// Without points it covers the unit cube.
+    pub fn construct_tree(points: Vec<Point>) -> Octree {
+        let bounds = BoundingBox::from_points(&points)
+            .unwrap_or_else(|| BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(1.0, 1.0, 1.0)));
+        let mut octree = Octree::new(bounds);
+        for point in points {
+            octree.insert(point);
+        }
+        octree
+    }
+
+    /// Inserts a point, returning false and leaving the octree unchanged if it lies outside its region.
+    pub fn insert(&mut self, point: Point) -> bool {
+        if !self.root.bounds.contains(&point) {
+            return false;
+        }
+        self.root.insert(point, 0, self.max_depth, self.leaf_capacity);
+        true
+    }
+
+    /// Returns the number of points in the octree.
+    pub fn len(&self) -> usize {
+        self.root.count
+    }
+
+    /// Returns true if the octree holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.root.count == 0
+    }
+
+    /// Returns every point inside the given box, including points on its boundary.
+    pub fn in_box(&self, bounding_box: &BoundingBox) -> Vec<Point> {
+        let mut points = vec![];
+        self.root.find_points_in_box(bounding_box, &mut points);
+        points
+    }
+
+    /// Iterates over the occupied cells at the given depth, in depth-first order.
+    /// Leaves above that depth are reported as they are, since they are not subdivided any further.
+    pub fn level_of_detail(&self, depth: usize) -> LevelOfDetail<'_> {
+        LevelOfDetail { stack: vec![(&self.root, 0)], depth }
+    }
+}
+
+impl NeighbourSearch for Octree {
+    fn nearest(&self, given_point: &Point, k: usize) -> Vec<Neighbour> {
+        let mut k_nearest_neighbours = BinaryHeap::new();
+        self.root.find_k_nearest_neighbours(given_point, &mut k_nearest_neighbours, k);
+        k_nearest_neighbours.into_sorted_vec()
+    }
+
+    fn within_radius(&self, given_point: &Point, radius: f64) -> Vec<Neighbour> {
+        let mut neighbours = vec![];
+        self.root.find_neighbours_within_radius(given_point, radius, &mut neighbours);
+        neighbours.sort();
+        neighbours
+    }
+}
+
+impl SpatialIndex for Octree {
+    fn build(points: Vec<Point>) -> Octree {
+        Octree::construct_tree(points)
+    }
+
+    fn in_box(&self, bounding_box: &BoundingBox) -> Vec<Point> {
+        Octree::in_box(self, bounding_box)
+    }
+
+    fn len(&self) -> usize {
+        Octree::len(self)
+    }
+}
+
+impl OctreeNode {
+    fn new(bounds: BoundingBox) -> OctreeNode {
+        OctreeNode { bounds, points: vec![], children: None, count: 0, sum: [0.0; 3] }
+    }
+
+    fn insert(&mut self, point: Point, depth: usize, max_depth: usize, leaf_capacity: usize) {
+        self.count += 1;
+        self.sum[0] += point.x;
+        self.sum[1] += point.y;
+        self.sum[2] += point.z;
+        match &mut self.children {
+            Some(children) => {
+                let octant = octant(&self.bounds, &point);
+                children[octant].insert(point, depth + 1, max_depth, leaf_capacity);
+            }
+            None => {
+                self.points.push(point);
+                if self.points.len() > leaf_capacity && depth < max_depth {
+                    self.split(depth, max_depth, leaf_capacity);
+                }
+            }
+        }
+    }
+
+    /// Turns a leaf into a cell with eight children and moves its points down into them.
+    fn split(&mut self, depth: usize, max_depth: usize, leaf_capacity: usize) {
+        let mut children = Box::new(std::array::from_fn(|octant| OctreeNode::new(octant_bounds(&self.bounds, octant))));
+        for point in std::mem::take(&mut self.points) {
+            let octant = octant(&self.bounds, &point);
+            children[octant].insert(point, depth + 1, max_depth, leaf_capacity);
+        }
+        self.children = Some(children);
+    }
+
+    fn centroid(&self) -> Point {
+        let count = self.count as f64;
+        Point { x: self.sum[0] / count, y: self.sum[1] / count, z: self.sum[2] / count }
+    }
+
+    fn find_points_in_box(&self, bounding_box: &BoundingBox, points: &mut Vec<Point>) {
+        if self.count == 0 || bounding_box.distance_to_box(&self.bounds) > 0.0 {
+            return;
+        }
+        points.extend(self.points.iter().filter(|p| bounding_box.contains(p)).cloned());
+        for child in self.children.iter().flat_map(|children| children.iter()) {
+            child.find_points_in_box(bounding_box, points);
+        }
+    }
+
+    fn find_k_nearest_neighbours(&self, given_point: &Point, k_nearest_neighbours: &mut BinaryHeap<Neighbour>, k: usize) {
+        if self.count == 0 || !is_potential_nearer_neighbour(self.bounds.distance_to_point(given_point), k_nearest_neighbours, k) {
+            return;
+        }
+        for point in &self.points {
+            let curr_distance = given_point.distance_to(point);
+            if is_potential_nearer_neighbour(curr_distance, k_nearest_neighbours, k) {
+                if k_nearest_neighbours.len() >= k { k_nearest_neighbours.pop(); }
+                k_nearest_neighbours.push(Neighbour { distance: curr_distance, point: point.clone() });
+            }
+        }
+        if let Some(children) = &self.children {
+            // visit the nearest cells first so the bound shrinks sooner
+            let mut children = children.iter().collect::<Vec<&OctreeNode>>();
+            children.sort_by(|a, b| a.bounds.distance_to_point(given_point).total_cmp(&b.bounds.distance_to_point(given_point)));
+            for child in children {
+                child.find_k_nearest_neighbours(given_point, k_nearest_neighbours, k);
+            }
+        }
+    }
+
+    fn find_neighbours_within_radius(&self, given_point: &Point, radius: f64, neighbours: &mut Vec<Neighbour>) {
+        if self.count == 0 || self.bounds.distance_to_point(given_point) > radius {
+            return;
+        }
+        for point in &self.points {
+            let curr_distance = given_point.distance_to(point);
+            if curr_distance <= radius {
+                neighbours.push(Neighbour { distance: curr_distance, point: point.clone() });
+            }
+        }
+        for child in self.children.iter().flat_map(|children| children.iter()) {
+            child.find_neighbours_within_radius(given_point, radius, neighbours);
+        }
+    }
+}
+
+/// Iterator over the occupied cells of an octree at one level of detail.
+pub struct LevelOfDetail<'a> {
+    stack: Vec<(&'a OctreeNode, usize)>,
+    depth: usize,
+}
+
+impl Iterator for LevelOfDetail<'_> {
+    type Item = OctreeCell;
+
+    fn next(&mut self) -> Option<OctreeCell> {
+        while let Some((node, depth)) = self.stack.pop() {
+            if node.count == 0 {
+                continue;
+            }
+            match &node.children {
+                Some(children) if depth < self.depth => {
+                    // pushed in reverse so that the cells come out in octant order
+                    self.stack.extend(children.iter().rev().map(|child| (child, depth + 1)));
+                }
+                _ => {
+                    return Some(OctreeCell { bounds: node.bounds.clone(), depth, count: node.count, centroid: node.centroid() });
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Returns the index of the child cell containing the point, with one bit per dimension
+/// set when the point is in the upper half of the cell along that dimension.
+fn octant(bounds: &BoundingBox, point: &Point) -> usize {
+    let middle = bounds.center();
+    (point.x >= middle.x) as usize | ((point.y >= middle.y) as usize) << 1 | ((point.z >= middle.z) as usize) << 2
+}
+
+fn octant_bounds(bounds: &BoundingBox, octant: usize) -> BoundingBox {
+    let middle = bounds.center();
+    let half = |bit: usize, min: f64, mid: f64, max: f64| match octant & bit {
+        0 => (min, mid),
+        _ => (mid, max),
+    };
+    let (min_x, max_x) = half(1, bounds.min.x, middle.x, bounds.max.x);
+    let (min_y, max_y) = half(2, bounds.min.y, middle.y, bounds.max.y);
+    let (min_z, max_z) = half(4, bounds.min.z, middle.z, bounds.max.z);
+    BoundingBox::new(Point { x: min_x, y: min_y, z: min_z }, Point { x: max_x, y: max_y, z: max_z })
+}
+
+#[cfg(test)]
+mod octree_test {
+    use crate::bounding_box::BoundingBox;
+    use crate::nearest_neighbour::NeighbourSearch;
+    use crate::point::Point;
+    use crate::spatial_index::SpatialIndex;
+
+    use super::{Octree, octant, octant_bounds};
+
+    fn unit_cube() -> BoundingBox {
+        BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn octant_of_point() {
+        let output = octant(&unit_cube(), &Point::new(0.75, 0.25, 0.5));
+        let expected = 0b101;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn bounds_of_octant() {
+        let output = octant_bounds(&unit_cube(), 0b110);
+        let expected = BoundingBox::new(Point::new(0.0, 0.5, 0.5), Point::new(0.5, 1.0, 1.0));
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn insert_outside_bounds() {
+        let mut octree = Octree::new(BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(0.5, 0.5, 0.5)));
+        assert!(!octree.insert(Point::new(0.75, 0.25, 0.25)));
+        assert!(octree.is_empty());
+    }
+
+    #[test]
+    fn build_covers_every_point() {
+        let low = Point { x: -1.0, y: 0.5, z: 0.5 };
+        let high = Point { x: 2.0, y: 0.5, z: 3.0 };
+        let octree = <Octree as SpatialIndex>::build(vec![low.clone(), high.clone()]);
+        let output = octree.root.bounds.clone();
+        let expected = BoundingBox::new(low, high);
+        assert_eq!(output, expected);
+        assert_eq!(octree.len(), 2);
+    }
+
+    #[test]
+    fn construct_tree_keeps_points_outside_unit_cube() {
+        let points = vec![Point { x: -0.5, y: 0.5, z: 0.5 }, Point::new(0.5, 0.5, 0.5), Point { x: 0.5, y: 4.0, z: 0.5 }];
+        let octree = Octree::construct_tree(points.clone());
+        let output = octree.len();
+        let expected = 3;
+        assert_eq!(output, expected);
+        assert_eq!(octree.root.bounds, BoundingBox::from_points(&points).unwrap());
+    }
+
+    #[test]
+    fn construct_empty_tree_covers_unit_cube() {
+        let output = Octree::construct_tree(vec![]).root.bounds;
+        let expected = unit_cube();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn leaf_splits_over_capacity() {
+        let mut octree = Octree::with_limits(unit_cube(), 4, 2);
+        octree.insert(Point::new(0.1, 0.1, 0.1));
+        octree.insert(Point::new(0.9, 0.9, 0.9));
+        assert!(octree.root.children.is_none());
+        octree.insert(Point::new(0.1, 0.9, 0.1));
+        let children = octree.root.children.as_ref().unwrap();
+        assert!(octree.root.points.is_empty());
+        assert_eq!(children[0].points, vec![Point::new(0.1, 0.1, 0.1)]);
+        assert_eq!(children[0b010].points, vec![Point::new(0.1, 0.9, 0.1)]);
+        assert_eq!(children[0b111].points, vec![Point::new(0.9, 0.9, 0.9)]);
+        assert_eq!(octree.len(), 3);
+    }
+
+    #[test]
+    fn leaf_at_max_depth_is_not_split() {
+        let mut octree = Octree::with_limits(unit_cube(), 0, 1);
+        for _ in 0..5 {
+            octree.insert(Point::new(0.3, 0.3, 0.3));
+        }
+        assert!(octree.root.children.is_none());
+        assert_eq!(octree.root.points.len(), 5);
+    }
+
+    #[test]
+    fn points_in_box() {
+        let octree = Octree::construct_tree(vec![
+            Point::new(0.1, 0.1, 0.1),
+            Point::new(0.4, 0.5, 0.6),
+            Point::new(0.9, 0.9, 0.9),
+        ]);
+        let output = octree.in_box(&BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(0.5, 0.5, 0.6)));
+        let expected = vec![Point::new(0.1, 0.1, 0.1), Point::new(0.4, 0.5, 0.6)];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn nearest_in_octree() {
+        let octree = Octree::construct_tree(vec![
+            Point::new(0.1, 0.1, 0.1),
+            Point::new(0.4, 0.5, 0.6),
+            Point::new(0.9, 0.9, 0.9),
+        ]);
+        let output = octree.nearest(&Point::new(0.8, 0.8, 0.8), 1)[0].point.clone();
+        let expected = Point::new(0.9, 0.9, 0.9);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn level_of_detail_cells() {
+        let mut octree = Octree::with_limits(unit_cube(), 4, 1);
+        octree.insert(Point::new(0.125, 0.125, 0.125));
+        octree.insert(Point::new(0.25, 0.25, 0.25));
+        octree.insert(Point::new(0.875, 0.875, 0.875));
+
+        let output = octree.level_of_detail(0).map(|cell| (cell.count, cell.centroid)).collect::<Vec<_>>();
+        let expected = vec![(3, Point::new(1.25 / 3.0, 1.25 / 3.0, 1.25 / 3.0))];
+        assert_eq!(output, expected);
+
+        let output = octree.level_of_detail(1).map(|cell| (cell.depth, cell.count)).collect::<Vec<_>>();
+        let expected = vec![(1, 2), (1, 1)];
+        assert_eq!(output, expected);
+    }
+}
@@ -0,0 +1,224 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::kd_tree::{construct_kd_tree, KdTreeNode};
+use crate::mapped_kd_tree::{MappedScalar, MappedTreeError, MappedTreeWriter};
+use crate::point::{Dimension, Point};
+
+/// Number of the next chunk file, shared by every build in the process so that builds running at the
+/// same time in one scratch directory never pick the same name.
+static NEXT_CHUNK: AtomicU64 = AtomicU64::new(0);
+
+/// Builds a kd-tree over points that need not fit in memory and writes it to a file that
+/// `MappedKdTree::open` can search in place, returning the number of points in the tree.
+///
+/// The points are streamed once into a chunk file in `scratch_dir`. While a chunk holds more points than
+/// `memory_budget` bytes of `KdTreeNode`s, its pivot is chosen the way `KdTree` chooses one, as the point
+/// nearest to the mean, and the rest of the points are streamed into a left and a right chunk.
+/// Smaller chunks are built into subtrees in memory. Either way the tree is written in pre-order,
+/// so the output file is written from start to end, and every chunk file is removed once it has been read.
+pub fn build_mapped_tree<S: MappedScalar, P: AsRef<Path>>(points: impl IntoIterator<Item=Point<S>>, path: P, scratch_dir: &Path,
+                                                          memory_budget: usize) -> Result<u64, MappedTreeError> {
+    let scratch = Scratch { dir: scratch_dir.to_path_buf() };
+    let mut chunk = scratch.create_chunk()?;
+    for point in points {
+        chunk.push(&point)?;
+    }
+    let chunk = chunk.finish()?;
+
+    let max_points_in_memory = (memory_budget / size_of::<KdTreeNode<S>>()).max(1) as u64;
+    let mut writer = MappedTreeWriter::<S>::create(path)?;
+    build_chunk(chunk, &Dimension::X, max_points_in_memory, &scratch, &mut writer)?;
+    Ok(writer.finish()?)
+}
+
+fn build_chunk<S: MappedScalar>(chunk: Chunk, curr_dimension: &Dimension, max_points_in_memory: u64, scratch: &Scratch,
+                                writer: &mut MappedTreeWriter<S>) -> io::Result<()> {
+    if chunk.len <= max_points_in_memory {
+        let points = chunk.read_points()?.collect::<io::Result<Vec<Point<S>>>>()?;
+        drop(chunk);
+        // the subtree splits from the dimension of its depth, so that it continues the tree above it
+        return writer.write_subtree(&construct_kd_tree(points, curr_dimension));
+    }
+
+    // the first pass finds the point nearest to the mean, like `get_pivot` does in memory
+    let mean = chunk.sums[curr_dimension.index()] / chunk.len as f64;
+    let mut min_diff = f64::MAX;
+    let mut pivot_index = 0;
+    let mut pivot = Point::default();
+    for (i, point) in chunk.read_points::<S>()?.enumerate() {
+        let point = point?;
+        let diff = (point.get_dimension(curr_dimension).to_f64() - mean).abs();
+        if diff < min_diff {
+            min_diff = diff;
+            pivot_index = i;
+            pivot = point;
+        }
+    }
+
+    // the second pass partitions the other points around the pivot, like `partition` does in memory
+    let mut left = scratch.create_chunk()?;
+    let mut right = scratch.create_chunk()?;
+    for (i, point) in chunk.read_points::<S>()?.enumerate() {
+        let point = point?;
+        if i == pivot_index { continue; }
+        match point.compare_dimension(&pivot, curr_dimension).is_lt() {
+            true => left.push(&point)?,
+            false => right.push(&point)?,
+        }
+    }
+    drop(chunk);
+    let (left, right) = (left.finish()?, right.finish()?);
+
+    writer.write_point(&pivot, left.len)?;
+    build_chunk(left, &curr_dimension.turn(), max_points_in_memory, scratch, writer)?;
+    build_chunk(right, &curr_dimension.turn(), max_points_in_memory, scratch, writer)
+}
+
+/// Struct that names the chunk files in the scratch directory.
+struct Scratch {
+    dir: PathBuf,
+}
+
+impl Scratch {
+    /// Creates a new chunk file, failing rather than truncating a file that already has its name.
+    fn create_chunk(&self) -> io::Result<ChunkWriter> {
+        let chunk = NEXT_CHUNK.fetch_add(1, Ordering::Relaxed);
+        let path = self.dir.join(format!("find_closest_points_{}_{chunk}.chunk", std::process::id()));
+        let writer = BufWriter::new(File::create_new(&path)?);
+        Ok(ChunkWriter { writer, chunk: Chunk { path, len: 0, sums: [0.0; 3] } })
+    }
+}
+
+/// Struct that represents a file of points, stored as their little-endian coordinates, which is removed when dropped.
+struct Chunk {
+    path: PathBuf,
+    len: u64,
+    /// The sums of the coordinates in each dimension, which give the mean for choosing a pivot.
+    sums: [f64; 3],
+}
+
+impl Chunk {
+    fn read_points<S: MappedScalar>(&self) -> io::Result<impl Iterator<Item=io::Result<Point<S>>>> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let mut record = vec![0; 3 * S::SIZE];
+        Ok((0..self.len).map(move |_| {
+            reader.read_exact(&mut record)?;
+            Ok(Point { x: S::read_le(&record), y: S::read_le(&record[S::SIZE..]), z: S::read_le(&record[2 * S::SIZE..]) })
+        }))
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+struct ChunkWriter {
+    writer: BufWriter<File>,
+    chunk: Chunk,
+}
+
+impl ChunkWriter {
+    fn push<S: MappedScalar>(&mut self, point: &Point<S>) -> io::Result<()> {
+        let mut record = Vec::with_capacity(3 * S::SIZE);
+        point.x.write_le(&mut record);
+        point.y.write_le(&mut record);
+        point.z.write_le(&mut record);
+        self.chunk.len += 1;
+        self.chunk.sums[0] += point.x.to_f64();
+        self.chunk.sums[1] += point.y.to_f64();
+        self.chunk.sums[2] += point.z.to_f64();
+        self.writer.write_all(&record)
+    }
+
+    fn finish(mut self) -> io::Result<Chunk> {
+        self.writer.flush()?;
+        Ok(self.chunk)
+    }
+}
+
+#[cfg(test)]
+mod out_of_core_test {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::kd_tree::KdTree;
+    use crate::mapped_kd_tree::MappedKdTree;
+    use crate::nearest_neighbour::NeighbourSearch;
+    use crate::point::Point;
+
+    use super::build_mapped_tree;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("find_closest_points_{}_{name}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn points() -> Vec<Point> {
+        (0..100).map(|i| Point::new((i * 37 % 100) as f64 / 100.0, (i * 11 % 100) as f64 / 100.0, i as f64 / 100.0)).collect()
+    }
+
+    #[test]
+    fn build_within_budget_matches_kd_tree() {
+        let dir = scratch_dir("within_budget");
+        let path = dir.join("tree");
+        build_mapped_tree(points(), &path, &dir, usize::MAX).unwrap();
+        let output = fs::read(&path).unwrap();
+        KdTree::construct_tree(points()).save_mapped(&path).unwrap();
+        let expected = fs::read(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn build_one_point_at_a_time() {
+        let dir = scratch_dir("one_point");
+        let path = dir.join("tree");
+        let output = build_mapped_tree(points(), &path, &dir, 0).unwrap();
+        let tree = MappedKdTree::<f64>::open(&path).unwrap();
+        let remaining = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(output, 100);
+        assert_eq!(tree.len(), 100);
+        assert_eq!(remaining, 1);
+    }
+
+    #[test]
+    fn build_without_points() {
+        let dir = scratch_dir("no_points");
+        let path = dir.join("tree");
+        let output = build_mapped_tree(Vec::<Point>::new(), &path, &dir, 0).unwrap();
+        let tree = MappedKdTree::<f64>::open(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(output, 0);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn concurrent_builds_share_a_scratch_dir() {
+        let dir = scratch_dir("concurrent");
+        let point_sets = [points(), points().into_iter().map(|p| Point::new(p.z, p.x, p.y)).collect::<Vec<Point>>()];
+        std::thread::scope(|scope| {
+            for (i, points) in point_sets.iter().enumerate() {
+                let (dir, path) = (dir.as_path(), dir.join(format!("tree_{i}")));
+                scope.spawn(move || build_mapped_tree(points.clone(), path, dir, 0).unwrap());
+            }
+        });
+        for (i, points) in point_sets.iter().enumerate() {
+            let tree = MappedKdTree::<f64>::open(dir.join(format!("tree_{i}"))).unwrap();
+            assert_eq!(tree.len(), points.len());
+            for point in points {
+                assert_eq!(&tree.nearest(point, 1)[0].point, point);
+            }
+        }
+        let remaining = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(remaining, 2);
+    }
+}
@@ -2,17 +2,22 @@ use std::cmp::Ordering;
 
 use rand::{Rng, thread_rng};
 
+use crate::scalar::{Accumulation, Coordinate, Native, Scalar};
+
 /// Struct which contains dimensions in a 3D space.
-#[derive(Clone, Debug, Default, PartialEq)]
-pub struct Point {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
+/// The coordinates are `f64` unless another `Coordinate` type such as `f32` or `i64` is given.
+/// Integer points can be compared and hashed exactly, which makes duplicates easy to find.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point<S = f64> {
+    pub x: S,
+    pub y: S,
+    pub z: S,
 }
 
-impl Point {
+impl<S: Scalar> Point<S> {
     /// Create a new point where each dimension is within [0.0, 0.1].
-    pub fn new(x: f64, y: f64, z: f64) -> Point {
+    pub fn new(x: S, y: S, z: S) -> Point<S> {
         Point {
             x: clamp(x),
             y: clamp(y),
@@ -21,20 +26,33 @@ impl Point {
     }
 
     /// Creates a new point using random values.
-    pub fn random() -> Point {
+    pub fn random() -> Point<S> {
         let mut rng = thread_rng();
-        Point::new(rng.gen(), rng.gen(), rng.gen())
+        Point::new(S::from_f64(rng.gen()), S::from_f64(rng.gen()), S::from_f64(rng.gen()))
     }
 
     /// Calculate the Euclidean distance of a given point to another point.
-    pub fn distance_to(&self, other: &Point) -> f64 {
-        ((self.x - other.x).powf(2.0)
-            + (self.y - other.y).powf(2.0)
-            + (self.z - other.z).powf(2.0)).sqrt()
+    pub fn distance_to(&self, other: &Point<S>) -> S {
+        self.squared_distance_to(other).sqrt()
+    }
+
+    /// Calculate the squared Euclidean distance of a given point to another point, which orders points
+    /// the same way as `distance_to` without taking a square root.
+    pub fn squared_distance_to(&self, other: &Point<S>) -> S {
+        self.squared_distance_with::<Native>(other)
+    }
+
+}
+
+impl<S: Coordinate> Point<S> {
+    /// Calculate the squared Euclidean distance of a given point to another point, accumulated as `A` decides.
+    pub fn squared_distance_with<A: Accumulation<S>>(&self, other: &Point<S>) -> A::Output {
+        let xy = A::sum(A::squared_difference(self.x, other.x), A::squared_difference(self.y, other.y));
+        A::sum(xy, A::squared_difference(self.z, other.z))
     }
 
     /// Return the value of the dimension for a point.
-    pub fn get_dimension(&self, dimension: &Dimension) -> f64 {
+    pub fn get_dimension(&self, dimension: &Dimension) -> S {
         match dimension {
             Dimension::X => self.x,
             Dimension::Y => self.y,
@@ -43,18 +61,30 @@ impl Point {
     }
 
     /// Returns an `Ordering` comparing a particular dimension from both points.
-    pub fn compare_dimension(&self, other: &Point, dimension: &Dimension) -> Ordering {
+    pub fn compare_dimension(&self, other: &Point<S>, dimension: &Dimension) -> Ordering {
         self.get_dimension(dimension).total_cmp(&other.get_dimension(dimension))
     }
+
+    /// Converts the coordinates to another scalar type, rounding them to the nearest value it holds.
+    pub fn cast<T: Scalar>(&self) -> Point<T> {
+        Point { x: T::from_f64(self.x.to_f64()), y: T::from_f64(self.y.to_f64()), z: T::from_f64(self.z.to_f64()) }
+    }
 }
 
-fn clamp(coordinate: f64) -> f64 {
-    coordinate.clamp(0.0, 1.0)
+fn clamp<S: Scalar>(coordinate: S) -> S {
+    match coordinate {
+        c if c < S::ZERO => S::ZERO,
+        c if c > S::ONE => S::ONE,
+        c => c,
+    }
 }
 
 #[cfg(test)]
 mod point_test {
     use std::cmp::Ordering;
+    use std::collections::HashSet;
+
+    use crate::scalar::Exact;
 
     use super::{clamp, Dimension, Point};
 
@@ -109,6 +139,30 @@ mod point_test {
         assert_eq!(output, expected);
     }
 
+    #[test]
+    fn cast_to_f32() {
+        let output = Point::new(0.1, 0.5, 0.9).cast::<f32>();
+        let expected = Point::new(0.1f32, 0.5f32, 0.9f32);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn integer_points_are_hashed_exactly() {
+        let points = [Point { x: 1i64 << 60, y: 0, z: 0 }, Point { x: (1i64 << 60) + 1, y: 0, z: 0 }, Point { x: 1i64 << 60, y: 0, z: 0 }];
+        let output = points.iter().collect::<HashSet<&Point<i64>>>().len();
+        let expected = 2;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn squared_distance_to() {
+        let point_one = Point::new(0.0, 0.0, 0.0);
+        let point_two = Point::new(0.25, 0.5, 0.5);
+        let output = point_one.squared_distance_to(&point_two);
+        let expected = 0.5625;
+        assert_eq!(output, expected);
+    }
+
     #[test]
     fn get_x_dimension() {
         let point = Point::new(0.1, 0.2, 0.3);
@@ -162,6 +216,15 @@ mod point_test {
         let expected = Ordering::Greater;
         assert_eq!(output, expected);
     }
+
+    #[test]
+    fn exact_distance_between_far_i64_points_saturates() {
+        let low = Point { x: i64::MIN, y: i64::MIN, z: i64::MIN };
+        let high = Point { x: i64::MAX, y: i64::MAX, z: i64::MAX };
+        let output = low.squared_distance_with::<Exact>(&high);
+        let expected = u128::MAX;
+        assert_eq!(output, expected);
+    }
 }
 
 /// Struct for keeping track of the current dimension in building the kd-tree
@@ -182,6 +245,15 @@ impl Dimension {
             Z => X
         }
     }
+
+    /// Returns the position of the dimension among the coordinates of a point.
+    pub fn index(&self) -> usize {
+        match self {
+            Dimension::X => 0,
+            Dimension::Y => 1,
+            Dimension::Z => 2,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -202,4 +274,4 @@ mod dimension_test {
     fn turn_z() {
         assert_eq!(Dimension::Z.turn(), Dimension::X);
     }
-}
\ No newline at end of file
+}
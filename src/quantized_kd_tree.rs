@@ -0,0 +1,298 @@
+use std::collections::BinaryHeap;
+use std::mem::size_of;
+
+use crate::bounding_box::BoundingBox;
+use crate::nearest_neighbour::{is_potential_nearer_neighbour, Neighbour};
+use crate::point::{Dimension, Point};
+
+/// Maximum number of points kept in a leaf of the quantized kd-tree.
+const LEAF_SIZE: usize = 32;
+
+/// Largest quantized coordinate, which stands for the upper edge of a leaf's box.
+const QUANTIZED_MAX: f64 = u16::MAX as f64;
+
+/// Struct that represents a node of the quantized kd-tree, stored in a flat vector.
+#[derive(Debug, PartialEq)]
+enum QuantizedNode {
+    /// Points below `split` along `dimension` are in the subtree at `left`, the rest in the one at `right`.
+    Internal { dimension: Dimension, split: f64, left: usize, right: usize },
+    /// Holds the points from `start` to `end`, quantized relative to `bounds`.
+    Leaf { bounds: BoundingBox, start: usize, end: usize },
+}
+
+/// Struct that represents a read-only kd-tree whose leaves store each coordinate as a 16-bit offset
+/// within the leaf's bounding box, which takes 6 bytes per point instead of the 24 of a `Point`.
+/// Searches compare the decompressed, approximate points and then re-rank the best candidates
+/// exactly against the full-precision points, which are kept on the side in the same order.
+#[derive(Debug, PartialEq)]
+pub struct QuantizedKdTree {
+    nodes: Vec<QuantizedNode>,
+    quantized: Vec<[u16; 3]>,
+    points: Vec<Point>,
+}
+
+impl QuantizedKdTree {
+    /// Constructs a quantized kd-tree from a vector of `Point` objects.
+    pub fn construct_tree(mut points: Vec<Point>) -> QuantizedKdTree {
+        let mut tree = QuantizedKdTree { nodes: vec![], quantized: Vec::with_capacity(points.len()), points: vec![] };
+        if !points.is_empty() {
+            let len = points.len();
+            tree.construct_node(&mut points, 0, len);
+        }
+        tree.points = points;
+        tree
+    }
+
+    /// Returns the number of points in the tree.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns true if the tree holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Returns the full-precision points, in the order of the leaves.
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    /// Returns the number of bytes taken by the nodes and the quantized points,
+    /// leaving out the full-precision points kept on the side.
+    pub fn memory_usage(&self) -> usize {
+        self.nodes.len() * size_of::<QuantizedNode>() + self.quantized.len() * size_of::<[u16; 3]>()
+    }
+
+    /// Returns the approximate k nearest neighbours to the given point, sorted by their exact distance.
+    pub fn nearest(&self, given_point: &Point, k: usize) -> Vec<Neighbour> {
+        self.nearest_with_candidates(given_point, k, k)
+    }
+
+    /// Returns the approximate k nearest neighbours to the given point, sorted by their exact distance,
+    /// after re-ranking the `candidates` points nearest by their quantized coordinates.
+    /// More candidates than `k` make it likelier that a point moved away by quantization is still found.
+    pub fn nearest_with_candidates(&self, given_point: &Point, k: usize, candidates: usize) -> Vec<Neighbour> {
+        let candidates = candidates.max(k);
+        let mut nearest_quantized = BinaryHeap::new();
+        if !self.nodes.is_empty() && candidates > 0 {
+            self.find_k_nearest_quantized(0, given_point, &mut nearest_quantized, candidates);
+        }
+        let mut neighbours = nearest_quantized.into_iter()
+            .map(|neighbour| {
+                let point = &self.points[neighbour.point];
+                Neighbour { distance: given_point.distance_to(point), point: point.clone() }
+            })
+            .collect::<Vec<Neighbour>>();
+        neighbours.sort();
+        neighbours.truncate(k);
+        neighbours
+    }
+
+    /// Returns every neighbour whose distance to the given point is at most `radius`, sorted by distance.
+    /// Unlike the nearest neighbours these are exact, since the quantization error of each leaf is bounded.
+    pub fn within_radius(&self, given_point: &Point, radius: f64) -> Vec<Neighbour> {
+        let mut neighbours = vec![];
+        if !self.nodes.is_empty() {
+            self.find_neighbours_within_radius(0, given_point, radius, &mut neighbours);
+        }
+        neighbours.sort();
+        neighbours
+    }
+
+    /// Builds the subtree over `points[start..end]`, which it reorders into the order of its leaves,
+    /// and returns the position of its root.
+    fn construct_node(&mut self, points: &mut [Point], start: usize, end: usize) -> usize {
+        let position = self.nodes.len();
+        let bounds = BoundingBox::from_points(&points[start..end]).unwrap();
+        if end - start <= LEAF_SIZE {
+            let scale = leaf_scale(&bounds);
+            self.quantized.extend(points[start..end].iter().map(|point| quantize(point, &bounds, &scale)));
+            self.nodes.push(QuantizedNode::Leaf { bounds, start, end });
+            return position;
+        }
+
+        // split at the median of the dimension with the widest spread
+        let dimension = widest_dimension(&bounds);
+        let middle = (start + end) / 2;
+        points[start..end].select_nth_unstable_by(middle - start, |a, b| a.compare_dimension(b, &dimension));
+        let split = points[middle].get_dimension(&dimension);
+        self.nodes.push(QuantizedNode::Internal { dimension, split, left: 0, right: 0 });
+        let left_child = self.construct_node(points, start, middle);
+        let right_child = self.construct_node(points, middle, end);
+        if let QuantizedNode::Internal { left, right, .. } = &mut self.nodes[position] {
+            *left = left_child;
+            *right = right_child;
+        }
+        position
+    }
+
+    /// Keeps the `candidates` points with the smallest quantized squared distance in `nearest_quantized`.
+    fn find_k_nearest_quantized(&self, position: usize, given_point: &Point, nearest_quantized: &mut BinaryHeap<Neighbour<usize>>,
+                                candidates: usize) {
+        match &self.nodes[position] {
+            QuantizedNode::Internal { dimension, split, left, right } => {
+                let distance_to_split = split - given_point.get_dimension(dimension);
+                let (near, far) = match distance_to_split > 0.0 {
+                    true => (*left, *right),
+                    false => (*right, *left),
+                };
+                self.find_k_nearest_quantized(near, given_point, nearest_quantized, candidates);
+                if is_potential_nearer_neighbour(distance_to_split * distance_to_split, nearest_quantized, candidates) {
+                    self.find_k_nearest_quantized(far, given_point, nearest_quantized, candidates);
+                }
+            }
+            QuantizedNode::Leaf { bounds, start, end } => {
+                let distance_to_leaf = bounds.distance_to_point(given_point);
+                if !is_potential_nearer_neighbour(distance_to_leaf * distance_to_leaf, nearest_quantized, candidates) {
+                    return;
+                }
+                let scale = leaf_scale(bounds);
+                for i in *start..*end {
+                    let curr_squared_distance = given_point.squared_distance_to(&dequantize(&self.quantized[i], bounds, &scale));
+                    if is_potential_nearer_neighbour(curr_squared_distance, nearest_quantized, candidates) {
+                        if nearest_quantized.len() >= candidates { nearest_quantized.pop(); }
+                        nearest_quantized.push(Neighbour { distance: curr_squared_distance, point: i });
+                    }
+                }
+            }
+        }
+    }
+
+    fn find_neighbours_within_radius(&self, position: usize, given_point: &Point, radius: f64, neighbours: &mut Vec<Neighbour>) {
+        match &self.nodes[position] {
+            QuantizedNode::Internal { dimension, split, left, right } => {
+                let distance_to_split = split - given_point.get_dimension(dimension);
+                // the partition may leave points equal to the split on the left, so a point exactly `radius` away is still visited
+                if distance_to_split >= -radius {
+                    self.find_neighbours_within_radius(*left, given_point, radius, neighbours);
+                }
+                if distance_to_split <= radius {
+                    self.find_neighbours_within_radius(*right, given_point, radius, neighbours);
+                }
+            }
+            QuantizedNode::Leaf { bounds, start, end } => {
+                if bounds.distance_to_point(given_point) > radius {
+                    return;
+                }
+                let scale = leaf_scale(bounds);
+                // a quantized coordinate is at most half a step away from the real one, so a whole step leaves room for rounding
+                let error = (scale[0] * scale[0] + scale[1] * scale[1] + scale[2] * scale[2]).sqrt();
+                for i in *start..*end {
+                    if given_point.distance_to(&dequantize(&self.quantized[i], bounds, &scale)) - error > radius {
+                        continue;
+                    }
+                    let curr_distance = given_point.distance_to(&self.points[i]);
+                    if curr_distance <= radius {
+                        neighbours.push(Neighbour { distance: curr_distance, point: self.points[i].clone() });
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn widest_dimension(bounds: &BoundingBox) -> Dimension {
+    let spread = |dimension: &Dimension| bounds.max.get_dimension(dimension) - bounds.min.get_dimension(dimension);
+    [Dimension::Y, Dimension::Z].into_iter()
+        .fold(Dimension::X, |widest, dimension| if spread(&dimension) > spread(&widest) { dimension } else { widest })
+}
+
+/// Returns the size of one quantization step along each dimension of the box.
+fn leaf_scale(bounds: &BoundingBox) -> [f64; 3] {
+    [
+        (bounds.max.x - bounds.min.x) / QUANTIZED_MAX,
+        (bounds.max.y - bounds.min.y) / QUANTIZED_MAX,
+        (bounds.max.z - bounds.min.z) / QUANTIZED_MAX,
+    ]
+}
+
+fn quantize(point: &Point, bounds: &BoundingBox, scale: &[f64; 3]) -> [u16; 3] {
+    let quantize_coordinate = |value: f64, min: f64, scale: f64| match scale > 0.0 {
+        true => ((value - min) / scale).round().clamp(0.0, QUANTIZED_MAX) as u16,
+        false => 0,
+    };
+    [
+        quantize_coordinate(point.x, bounds.min.x, scale[0]),
+        quantize_coordinate(point.y, bounds.min.y, scale[1]),
+        quantize_coordinate(point.z, bounds.min.z, scale[2]),
+    ]
+}
+
+fn dequantize(quantized: &[u16; 3], bounds: &BoundingBox, scale: &[f64; 3]) -> Point {
+    Point {
+        x: bounds.min.x + quantized[0] as f64 * scale[0],
+        y: bounds.min.y + quantized[1] as f64 * scale[1],
+        z: bounds.min.z + quantized[2] as f64 * scale[2],
+    }
+}
+
+#[cfg(test)]
+mod quantized_kd_tree_test {
+    use crate::bounding_box::BoundingBox;
+    use crate::point::Point;
+
+    use super::{dequantize, leaf_scale, LEAF_SIZE, quantize, QuantizedKdTree};
+
+    #[test]
+    fn quantization_error_is_at_most_half_a_step() {
+        let bounds = BoundingBox::new(Point::new(0.1, 0.2, 0.3), Point::new(0.9, 0.4, 0.3));
+        let scale = leaf_scale(&bounds);
+        let point = Point::new(0.123456789, 0.3333333, 0.3);
+        let output = dequantize(&quantize(&point, &bounds, &scale), &bounds, &scale);
+        assert!((output.x - point.x).abs() <= scale[0] / 2.0 + f64::EPSILON);
+        assert!((output.y - point.y).abs() <= scale[1] / 2.0 + f64::EPSILON);
+        assert_eq!(output.z, point.z);
+    }
+
+    #[test]
+    fn corners_of_the_box_are_exact() {
+        let bounds = BoundingBox::new(Point::new(0.1, 0.2, 0.3), Point::new(0.9, 0.4, 0.5));
+        let scale = leaf_scale(&bounds);
+        let output = quantize(&bounds.max, &bounds, &scale);
+        let expected = [u16::MAX; 3];
+        assert_eq!(output, expected);
+        assert_eq!(quantize(&bounds.min, &bounds, &scale), [0; 3]);
+    }
+
+    #[test]
+    fn leaves_hold_at_most_leaf_size_points() {
+        let points = (0..10 * LEAF_SIZE).map(|i| Point::new(i as f64 / 1000.0, 0.5, 0.5)).collect::<Vec<Point>>();
+        let tree = QuantizedKdTree::construct_tree(points);
+        assert_eq!(tree.len(), 10 * LEAF_SIZE);
+        assert_eq!(tree.quantized.len(), 10 * LEAF_SIZE);
+        for node in &tree.nodes {
+            if let super::QuantizedNode::Leaf { start, end, .. } = node {
+                assert!(end - start <= LEAF_SIZE);
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_in_empty_tree() {
+        let output = QuantizedKdTree::construct_tree(vec![]).nearest(&Point::new(0.5, 0.5, 0.5), 3);
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn nearest_reports_exact_distances() {
+        let tree = QuantizedKdTree::construct_tree(vec![
+            Point::new(0.5, 0.5, 0.5),
+            Point::new(0.5, 0.5, 0.0),
+            Point::new(0.0, 0.0, 0.0),
+        ]);
+        let output = tree.nearest(&Point::new(0.5, 0.5, 0.5), 2).into_iter().map(|n| n.distance).collect::<Vec<f64>>();
+        let expected = vec![0.0, 0.5];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn within_radius_keeps_points_equal_to_the_split() {
+        let mut points = vec![Point::new(0.5, 0.5, 0.25); 10];
+        points.extend(vec![Point::new(0.5, 0.5, 0.5); 30]);
+        let tree = QuantizedKdTree::construct_tree(points);
+        let output = tree.within_radius(&Point::new(0.5, 0.5, 0.75), 0.25).len();
+        let expected = 30;
+        assert_eq!(output, expected);
+    }
+}
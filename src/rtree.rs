@@ -0,0 +1,638 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::bounding_box::BoundingBox;
+use crate::nearest_neighbour::{is_potential_nearer_neighbour, Neighbour, NeighbourSearch};
+use crate::point::{Dimension, Point};
+use crate::spatial_index::SpatialIndex;
+
+/// Default number of children a node holds before it overflows.
+pub const DEFAULT_MAX_ENTRIES: usize = 16;
+
+/// Share of a node's children that are reinserted the first time a level overflows during an insertion.
+const REINSERT_FRACTION: f64 = 0.3;
+
+/// Struct that represents an item stored in the R-tree together with the box covering it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RTreeEntry<T> {
+    pub bounds: BoundingBox,
+    pub item: T,
+}
+
+/// Struct that represents a node of the R-tree, whose box covers everything below it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RTreeNode<T> {
+    pub bounds: BoundingBox,
+    pub children: RTreeChildren<T>,
+}
+
+/// Enum for the children of an R-tree node: leaves hold entries and every other node holds nodes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RTreeChildren<T> {
+    Leaf(Vec<RTreeEntry<T>>),
+    Internal(Vec<RTreeNode<T>>),
+}
+
+/// Struct that represents an R-tree over axis-aligned boxes, each carrying an item of type `T`.
+/// Points are stored as boxes whose corners are both the point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RTree<T> {
+    pub root: Option<RTreeNode<T>>,
+    len: usize,
+    max_entries: usize,
+    min_entries: usize,
+}
+
+/// Enum for something waiting to be inserted: an entry goes into a leaf, while a node that was
+/// removed for reinsertion goes back in at the level it came from.
+enum Child<T> {
+    Entry(RTreeEntry<T>),
+    Node(RTreeNode<T>),
+}
+
+/// Struct that keeps track of an insertion into the R-tree, including the children that were
+/// removed from overflowing nodes and are waiting to be reinserted at their level.
+struct Insertion<T> {
+    max_entries: usize,
+    min_entries: usize,
+    reinserted: Vec<bool>,
+    pending: Vec<(Child<T>, usize)>,
+}
+
+/// Trait for the things grouped by the R-tree, which are only ever compared through their boxes.
+trait Bounded {
+    fn bounds(&self) -> &BoundingBox;
+}
+
+impl<T> Bounded for RTreeEntry<T> {
+    fn bounds(&self) -> &BoundingBox {
+        &self.bounds
+    }
+}
+
+impl<T> Bounded for RTreeNode<T> {
+    fn bounds(&self) -> &BoundingBox {
+        &self.bounds
+    }
+}
+
+impl<T> Bounded for Child<T> {
+    fn bounds(&self) -> &BoundingBox {
+        match self {
+            Child::Entry(entry) => &entry.bounds,
+            Child::Node(node) => &node.bounds,
+        }
+    }
+}
+
+impl<T> RTreeEntry<T> {
+    /// Creates a new entry covering the given box.
+    pub fn new(bounds: BoundingBox, item: T) -> RTreeEntry<T> {
+        RTreeEntry { bounds, item }
+    }
+
+    /// Creates a new entry for a single point.
+    pub fn from_point(point: &Point, item: T) -> RTreeEntry<T> {
+        RTreeEntry { bounds: BoundingBox::new(point.clone(), point.clone()), item }
+    }
+}
+
+impl<T> Default for RTree<T> {
+    fn default() -> Self {
+        RTree::new()
+    }
+}
+
+impl<T> RTree<T> {
+    /// Creates an empty R-tree using the default node size.
+    pub fn new() -> RTree<T> {
+        RTree::with_max_entries(DEFAULT_MAX_ENTRIES)
+    }
+
+    /// Creates an empty R-tree whose nodes hold at most `max_entries` children.
+    /// Nodes split by insertion keep at least two fifths of that, and every node may hold at least 4 children.
+    pub fn with_max_entries(max_entries: usize) -> RTree<T> {
+        let max_entries = max_entries.max(4);
+        RTree { root: None, len: 0, max_entries, min_entries: max_entries * 2 / 5 }
+    }
+
+    /// Constructs an R-tree from all the entries at once with Sort-Tile-Recursive packing, using the default node size.
+    pub fn bulk_load(entries: Vec<RTreeEntry<T>>) -> RTree<T> {
+        RTree::bulk_load_with_max_entries(entries, DEFAULT_MAX_ENTRIES)
+    }
+
+    /// Constructs an R-tree from all the entries at once with Sort-Tile-Recursive packing.
+    /// Nodes are filled up to `max_entries`, so the last node of each tile may hold fewer children
+    /// than an R-tree built by insertion would.
+    pub fn bulk_load_with_max_entries(entries: Vec<RTreeEntry<T>>, max_entries: usize) -> RTree<T> {
+        let mut tree = RTree::with_max_entries(max_entries);
+        tree.len = entries.len();
+        if entries.is_empty() {
+            return tree;
+        }
+        let mut nodes = sort_tile_recursive(entries, tree.max_entries).into_iter()
+            .map(|entries| RTreeNode::new(RTreeChildren::Leaf(entries)))
+            .collect::<Vec<RTreeNode<T>>>();
+        while nodes.len() > 1 {
+            nodes = sort_tile_recursive(nodes, tree.max_entries).into_iter()
+                .map(|children| RTreeNode::new(RTreeChildren::Internal(children)))
+                .collect();
+        }
+        tree.root = nodes.pop();
+        tree
+    }
+
+    /// Inserts an entry, choosing the subtree and splitting overflowing nodes like the R*-tree.
+    pub fn insert(&mut self, entry: RTreeEntry<T>) {
+        self.len += 1;
+        let Some(root) = &mut self.root else {
+            self.root = Some(RTreeNode::new(RTreeChildren::Leaf(vec![entry])));
+            return;
+        };
+
+        let mut insertion = Insertion {
+            max_entries: self.max_entries,
+            min_entries: self.min_entries,
+            reinserted: vec![false; root.height() + 1],
+            pending: vec![(Child::Entry(entry), 0)],
+        };
+        while let Some((child, level)) = insertion.pending.pop() {
+            let root = self.root.as_mut().unwrap();
+            let root_level = root.height();
+            // a root split adds a level, which may overflow once before it splits too
+            insertion.reinserted.resize(root_level + 1, false);
+            if let Some(sibling) = root.insert(child, level, root_level, true, &mut insertion) {
+                let root = self.root.take().unwrap();
+                self.root = Some(RTreeNode::new(RTreeChildren::Internal(vec![root, sibling])));
+            }
+        }
+    }
+
+    /// Returns the number of entries in the R-tree.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the R-tree holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns every entry whose box intersects the given box, including boxes that only touch it.
+    pub fn intersecting(&self, bounding_box: &BoundingBox) -> Vec<&RTreeEntry<T>> {
+        self.search(|bounds| bounds.intersects(bounding_box), |bounds| bounds.intersects(bounding_box))
+    }
+
+    /// Returns every entry whose box lies entirely inside the given box.
+    pub fn contained_in(&self, bounding_box: &BoundingBox) -> Vec<&RTreeEntry<T>> {
+        self.search(|bounds| bounds.intersects(bounding_box), |bounds| bounding_box.contains_box(bounds))
+    }
+
+    /// Returns every entry whose box contains the given point, including points on its boundary.
+    pub fn containing(&self, given_point: &Point) -> Vec<&RTreeEntry<T>> {
+        self.search(|bounds| bounds.contains(given_point), |bounds| bounds.contains(given_point))
+    }
+
+    /// Returns the k entries whose boxes are nearest to the given point, sorted by distance.
+    /// The distance to a box is zero when the point is inside it.
+    pub fn nearest(&self, given_point: &Point, k: usize) -> Vec<Neighbour<&RTreeEntry<T>>> {
+        let mut k_nearest_neighbours = BinaryHeap::new();
+        if let Some(root) = &self.root {
+            root.find_k_nearest_neighbours(given_point, &mut k_nearest_neighbours, k);
+        }
+        k_nearest_neighbours.into_sorted_vec()
+    }
+
+    /// Returns every entry whose box is at most `radius` away from the given point, sorted by distance.
+    pub fn within_radius(&self, given_point: &Point, radius: f64) -> Vec<Neighbour<&RTreeEntry<T>>> {
+        let mut neighbours = vec![];
+        if let Some(root) = &self.root {
+            root.find_neighbours_within_radius(given_point, radius, &mut neighbours);
+        }
+        neighbours.sort();
+        neighbours
+    }
+
+    /// Collects the entries accepted by `accept`, only visiting nodes whose boxes pass `descend`.
+    fn search(&self, descend: impl Fn(&BoundingBox) -> bool, accept: impl Fn(&BoundingBox) -> bool) -> Vec<&RTreeEntry<T>> {
+        let mut entries = vec![];
+        let mut stack = self.root.iter().filter(|root| descend(&root.bounds)).collect::<Vec<&RTreeNode<T>>>();
+        while let Some(node) = stack.pop() {
+            match &node.children {
+                RTreeChildren::Leaf(leaf_entries) => entries.extend(leaf_entries.iter().filter(|entry| accept(&entry.bounds))),
+                RTreeChildren::Internal(children) => stack.extend(children.iter().filter(|child| descend(&child.bounds))),
+            }
+        }
+        entries
+    }
+}
+
+impl RTree<Point> {
+    /// Constructs an R-tree holding each point as its own item, with Sort-Tile-Recursive packing.
+    pub fn from_points(points: Vec<Point>) -> RTree<Point> {
+        RTree::bulk_load(points.into_iter().map(|point| RTreeEntry::from_point(&point, point.clone())).collect())
+    }
+}
+
+/// Searches by the distance to each entry's box, which is the distance to the point itself
+/// when the tree was built with `RTree::from_points`.
+impl NeighbourSearch for RTree<Point> {
+    fn nearest(&self, given_point: &Point, k: usize) -> Vec<Neighbour> {
+        RTree::nearest(self, given_point, k).into_iter()
+            .map(|neighbour| Neighbour { distance: neighbour.distance, point: neighbour.point.item.clone() })
+            .collect()
+    }
+
+    fn within_radius(&self, given_point: &Point, radius: f64) -> Vec<Neighbour> {
+        RTree::within_radius(self, given_point, radius).into_iter()
+            .map(|neighbour| Neighbour { distance: neighbour.distance, point: neighbour.point.item.clone() })
+            .collect()
+    }
+}
+
+impl SpatialIndex for RTree<Point> {
+    fn build(points: Vec<Point>) -> RTree<Point> {
+        RTree::from_points(points)
+    }
+
+    fn in_box(&self, bounding_box: &BoundingBox) -> Vec<Point> {
+        self.contained_in(bounding_box).into_iter().map(|entry| entry.item.clone()).collect()
+    }
+
+    fn len(&self) -> usize {
+        RTree::len(self)
+    }
+}
+
+impl<T> RTreeNode<T> {
+    fn new(children: RTreeChildren<T>) -> RTreeNode<T> {
+        let bounds = bounds_of(&children).expect("nodes are only created with children");
+        RTreeNode { bounds, children }
+    }
+
+    /// Returns the number of children of the node.
+    pub fn len(&self) -> usize {
+        match &self.children {
+            RTreeChildren::Leaf(entries) => entries.len(),
+            RTreeChildren::Internal(children) => children.len(),
+        }
+    }
+
+    /// Returns true if the node has no children, which only happens for the root of an empty tree.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of levels below the node, which is zero for a leaf.
+    /// Every leaf of an R-tree is at the same depth.
+    pub fn height(&self) -> usize {
+        match &self.children {
+            RTreeChildren::Leaf(_) => 0,
+            RTreeChildren::Internal(children) => children[0].height() + 1,
+        }
+    }
+
+    /// Shrinks the node's box to the smallest box covering its children.
+    fn update_bounds(&mut self) {
+        if let Some(bounds) = bounds_of(&self.children) {
+            self.bounds = bounds;
+        }
+    }
+
+    /// Inserts the child at `target_level`, the level of the nodes that hold it, and returns the new
+    /// sibling of this node if it had to be split.
+    /// The first node to overflow at each level has its farthest children queued for reinsertion
+    /// instead, which lets the tree fix poor early choices before resorting to a split.
+    fn insert(&mut self, child: Child<T>, target_level: usize, curr_level: usize, is_root: bool,
+              insertion: &mut Insertion<T>) -> Option<RTreeNode<T>> {
+        if curr_level == target_level {
+            match (&mut self.children, child) {
+                (RTreeChildren::Leaf(entries), Child::Entry(entry)) => entries.push(entry),
+                (RTreeChildren::Internal(children), Child::Node(node)) => children.push(node),
+                _ => unreachable!("children are always inserted at the level they were taken from"),
+            }
+        } else {
+            let RTreeChildren::Internal(children) = &mut self.children else {
+                unreachable!("leaves are at level zero, below every target level");
+            };
+            let index = choose_subtree(children, child.bounds(), curr_level == 1);
+            if let Some(sibling) = children[index].insert(child, target_level, curr_level - 1, false, insertion) {
+                children.push(sibling);
+            }
+        }
+        self.update_bounds();
+
+        if self.len() <= insertion.max_entries {
+            return None;
+        }
+        if !is_root && !insertion.reinserted[curr_level] {
+            insertion.reinserted[curr_level] = true;
+            let count = ((insertion.max_entries as f64 * REINSERT_FRACTION).round() as usize).max(1);
+            insertion.pending.extend(self.take_farthest(count).into_iter().map(|child| (child, curr_level)));
+            return None;
+        }
+        Some(self.split(insertion.min_entries))
+    }
+
+    /// Removes the `count` children whose centers are farthest from the node's center, ordered from
+    /// the farthest to the nearest so that popping them reinserts the nearest first.
+    fn take_farthest(&mut self, count: usize) -> Vec<Child<T>> {
+        let center = self.bounds.center();
+        let by_distance = |a: &BoundingBox, b: &BoundingBox| center.distance_to(&b.center()).total_cmp(&center.distance_to(&a.center()));
+        let removed = match &mut self.children {
+            RTreeChildren::Leaf(entries) => {
+                entries.sort_by(|a, b| by_distance(&a.bounds, &b.bounds));
+                entries.drain(..count).map(Child::Entry).collect()
+            }
+            RTreeChildren::Internal(children) => {
+                children.sort_by(|a, b| by_distance(&a.bounds, &b.bounds));
+                children.drain(..count).map(Child::Node).collect()
+            }
+        };
+        self.update_bounds();
+        removed
+    }
+
+    /// Splits the node's children in two with the R* split, keeping the first group and returning
+    /// a new node holding the second.
+    fn split(&mut self, min_entries: usize) -> RTreeNode<T> {
+        let sibling = match &mut self.children {
+            RTreeChildren::Leaf(entries) => RTreeChildren::Leaf(split_children(entries, min_entries)),
+            RTreeChildren::Internal(children) => RTreeChildren::Internal(split_children(children, min_entries)),
+        };
+        self.update_bounds();
+        RTreeNode::new(sibling)
+    }
+
+    fn find_k_nearest_neighbours<'a>(&'a self, given_point: &Point, k_nearest_neighbours: &mut BinaryHeap<Neighbour<&'a RTreeEntry<T>>>, k: usize) {
+        match &self.children {
+            RTreeChildren::Leaf(entries) => {
+                for entry in entries {
+                    let curr_distance = entry.bounds.distance_to_point(given_point);
+                    if is_potential_nearer_neighbour(curr_distance, k_nearest_neighbours, k) {
+                        if k_nearest_neighbours.len() >= k { k_nearest_neighbours.pop(); }
+                        k_nearest_neighbours.push(Neighbour { distance: curr_distance, point: entry });
+                    }
+                }
+            }
+            RTreeChildren::Internal(children) => {
+                // visit the nearest boxes first so the bound shrinks sooner
+                let mut children = children.iter()
+                    .map(|child| (child.bounds.distance_to_point(given_point), child))
+                    .collect::<Vec<(f64, &RTreeNode<T>)>>();
+                children.sort_by(|a, b| a.0.total_cmp(&b.0));
+                for (distance_to_child, child) in children {
+                    if !is_potential_nearer_neighbour(distance_to_child, k_nearest_neighbours, k) {
+                        break;
+                    }
+                    child.find_k_nearest_neighbours(given_point, k_nearest_neighbours, k);
+                }
+            }
+        }
+    }
+
+    fn find_neighbours_within_radius<'a>(&'a self, given_point: &Point, radius: f64, neighbours: &mut Vec<Neighbour<&'a RTreeEntry<T>>>) {
+        match &self.children {
+            RTreeChildren::Leaf(entries) => {
+                for entry in entries {
+                    let curr_distance = entry.bounds.distance_to_point(given_point);
+                    if curr_distance <= radius {
+                        neighbours.push(Neighbour { distance: curr_distance, point: entry });
+                    }
+                }
+            }
+            RTreeChildren::Internal(children) => {
+                for child in children {
+                    if child.bounds.distance_to_point(given_point) <= radius {
+                        child.find_neighbours_within_radius(given_point, radius, neighbours);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the smallest box covering every item, or `None` if there are no items.
+fn union_of<E: Bounded>(items: &[E]) -> Option<BoundingBox> {
+    let (first, rest) = items.split_first()?;
+    Some(rest.iter().fold(first.bounds().clone(), |union, item| union.union(item.bounds())))
+}
+
+fn bounds_of<T>(children: &RTreeChildren<T>) -> Option<BoundingBox> {
+    match children {
+        RTreeChildren::Leaf(entries) => union_of(entries),
+        RTreeChildren::Internal(children) => union_of(children),
+    }
+}
+
+/// Compares lists of costs lexicographically, so later costs only break ties between earlier ones.
+fn compare_costs(a: &[f64], b: &[f64]) -> Ordering {
+    a.iter().zip(b).map(|(a, b)| a.total_cmp(b)).find(|ordering| ordering.is_ne()).unwrap_or(Ordering::Equal)
+}
+
+/// Picks the child that the R*-tree would insert the box into: the one whose overlap with its
+/// siblings grows least when the children are leaves, and otherwise the one whose volume grows least.
+fn choose_subtree<T>(children: &[RTreeNode<T>], bounds: &BoundingBox, children_are_leaves: bool) -> usize {
+    let cost = |index: usize| {
+        let child = &children[index].bounds;
+        let enlarged = child.union(bounds);
+        let overlap_enlargement = match children_are_leaves {
+            true => children.iter().enumerate()
+                .filter(|(other, _)| *other != index)
+                .map(|(_, other)| enlarged.overlap(&other.bounds) - child.overlap(&other.bounds))
+                .sum(),
+            false => 0.0,
+        };
+        [overlap_enlargement, enlarged.volume() - child.volume(), child.volume()]
+    };
+    (0..children.len())
+        .map(|index| (cost(index), index))
+        .min_by(|a, b| compare_costs(&a.0, &b.0))
+        .map(|(_, index)| index)
+        .unwrap()
+}
+
+/// Sorts the items by the lower or upper side of their boxes along a dimension.
+fn sort_along<E: Bounded>(items: &mut [E], dimension: &Dimension, by_upper: bool) {
+    let side = |item: &E| match by_upper {
+        true => item.bounds().max.get_dimension(dimension),
+        false => item.bounds().min.get_dimension(dimension),
+    };
+    items.sort_by(|a, b| side(a).total_cmp(&side(b)));
+}
+
+/// Returns the boxes of both groups for every way of cutting the sorted items in two groups
+/// of at least `min_entries`, together with the size of the first group.
+fn distributions<E: Bounded>(items: &[E], min_entries: usize) -> Vec<(usize, BoundingBox, BoundingBox)> {
+    (min_entries..=items.len() - min_entries)
+        .map(|split| (split, union_of(&items[..split]).unwrap(), union_of(&items[split..]).unwrap()))
+        .collect()
+}
+
+/// Splits the items with the R* split and returns the second group, leaving the first in `items`.
+/// The split axis is the one with the smallest total margin over all its distributions, and along it
+/// the distribution with the least overlap between the groups wins, then the one with the least volume.
+fn split_children<E: Bounded>(items: &mut Vec<E>, min_entries: usize) -> Vec<E> {
+    let dimensions = [Dimension::X, Dimension::Y, Dimension::Z];
+    let margin = |dimension: &Dimension, items: &mut [E]| -> f64 {
+        [false, true].into_iter().map(|by_upper| {
+            sort_along(items, dimension, by_upper);
+            distributions(items, min_entries).iter().map(|(_, first, second)| first.margin() + second.margin()).sum::<f64>()
+        }).sum()
+    };
+    let margins = dimensions.iter().map(|dimension| margin(dimension, items)).collect::<Vec<f64>>();
+    let dimension = (0..dimensions.len())
+        .min_by(|&a, &b| margins[a].total_cmp(&margins[b]))
+        .map(|index| &dimensions[index])
+        .unwrap();
+
+    let mut best: Option<([f64; 2], bool, usize)> = None;
+    for by_upper in [false, true] {
+        sort_along(items, dimension, by_upper);
+        for (split, first, second) in distributions(items, min_entries) {
+            let cost = [first.overlap(&second), first.volume() + second.volume()];
+            if best.as_ref().is_none_or(|(best_cost, _, _)| compare_costs(&cost, best_cost).is_lt()) {
+                best = Some((cost, by_upper, split));
+            }
+        }
+    }
+    let (_, by_upper, split) = best.unwrap();
+    sort_along(items, dimension, by_upper);
+    items.split_off(split)
+}
+
+/// Groups the items into nodes of at most `max_entries` with Sort-Tile-Recursive packing: the items
+/// are cut into slabs along x, each slab into runs along y, and each run into nodes along z.
+fn sort_tile_recursive<E: Bounded>(mut items: Vec<E>, max_entries: usize) -> Vec<Vec<E>> {
+    let node_count = items.len().div_ceil(max_entries);
+    let tiles_per_dimension = (node_count as f64).cbrt().ceil() as usize;
+    let center = |item: &E, dimension: &Dimension| item.bounds().center().get_dimension(dimension);
+
+    let mut groups = vec![];
+    items.sort_by(|a, b| center(a, &Dimension::X).total_cmp(&center(b, &Dimension::X)));
+    for mut slab in into_chunks(items, tiles_per_dimension * tiles_per_dimension * max_entries) {
+        slab.sort_by(|a, b| center(a, &Dimension::Y).total_cmp(&center(b, &Dimension::Y)));
+        for mut run in into_chunks(slab, tiles_per_dimension * max_entries) {
+            run.sort_by(|a, b| center(a, &Dimension::Z).total_cmp(&center(b, &Dimension::Z)));
+            groups.extend(into_chunks(run, max_entries));
+        }
+    }
+    groups
+}
+
+fn into_chunks<E>(items: Vec<E>, size: usize) -> Vec<Vec<E>> {
+    let mut chunks = vec![];
+    let mut items = items.into_iter().peekable();
+    while items.peek().is_some() {
+        chunks.push(items.by_ref().take(size).collect());
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod rtree_test {
+    use crate::bounding_box::BoundingBox;
+    use crate::nearest_neighbour::NeighbourSearch;
+    use crate::point::Point;
+
+    use super::{compare_costs, into_chunks, RTree, RTreeChildren, RTreeEntry, sort_tile_recursive, split_children};
+
+    fn cube(x: f64, y: f64, z: f64, size: f64) -> BoundingBox {
+        BoundingBox::new(Point { x, y, z }, Point { x: x + size, y: y + size, z: z + size })
+    }
+
+    #[test]
+    fn chunks_keep_order() {
+        let output = into_chunks(vec![1, 2, 3, 4, 5], 2);
+        let expected = vec![vec![1, 2], vec![3, 4], vec![5]];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn later_costs_break_ties() {
+        assert!(compare_costs(&[0.0, 2.0], &[0.0, 1.0]).is_gt());
+        assert!(compare_costs(&[0.0, 2.0], &[1.0, 1.0]).is_lt());
+    }
+
+    #[test]
+    fn sort_tile_recursive_fills_nodes() {
+        let entries = (0..100).map(|i| RTreeEntry::new(cube(i as f64 / 100.0, 0.0, 0.0, 0.01), i)).collect::<Vec<_>>();
+        let output = sort_tile_recursive(entries, 10);
+        assert_eq!(output.len(), 10);
+        assert!(output.iter().all(|group| group.len() == 10));
+    }
+
+    #[test]
+    fn split_separates_clusters() {
+        let mut entries = (0..5).map(|i| RTreeEntry::new(cube(0.0, 0.1 * i as f64, 0.0, 0.05), i))
+            .chain((5..10).map(|i| RTreeEntry::new(cube(0.8, 0.1 * i as f64, 0.0, 0.05), i)))
+            .collect::<Vec<_>>();
+        let second = split_children(&mut entries, 2);
+        let mut output = [entries.iter().map(|e| e.item).collect::<Vec<i32>>(), second.iter().map(|e| e.item).collect()];
+        output.sort();
+        let expected = [vec![0, 1, 2, 3, 4], vec![5, 6, 7, 8, 9]];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn small_max_entries_is_raised() {
+        let tree = RTree::<()>::with_max_entries(1);
+        assert_eq!((tree.max_entries, tree.min_entries), (4, 1));
+    }
+
+    #[test]
+    fn bulk_load_empty() {
+        let tree = RTree::<()>::bulk_load(vec![]);
+        assert!(tree.is_empty());
+        assert_eq!(tree.root, None);
+    }
+
+    #[test]
+    fn insert_splits_root() {
+        let mut tree = RTree::with_max_entries(4);
+        for i in 0..5 {
+            tree.insert(RTreeEntry::new(cube(i as f64 / 5.0, 0.0, 0.0, 0.1), i));
+        }
+        let root = tree.root.as_ref().unwrap();
+        assert_eq!(root.height(), 1);
+        let RTreeChildren::Internal(children) = &root.children else { panic!("root should have been split") };
+        assert_eq!(children.iter().map(|child| child.len()).sum::<usize>(), 5);
+        assert_eq!(root.bounds, BoundingBox::new(Point::new(0.0, 0.0, 0.0), Point::new(0.9, 0.1, 0.1)));
+    }
+
+    #[test]
+    fn queries_on_boxes() {
+        let tree = RTree::bulk_load(vec![
+            RTreeEntry::new(cube(0.0, 0.0, 0.0, 0.5), "low"),
+            RTreeEntry::new(cube(0.25, 0.25, 0.25, 0.5), "middle"),
+            RTreeEntry::new(cube(0.5, 0.5, 0.5, 0.5), "high"),
+        ]);
+        let items = |entries: Vec<&RTreeEntry<&'static str>>| {
+            let mut items = entries.into_iter().map(|entry| entry.item).collect::<Vec<&str>>();
+            items.sort();
+            items
+        };
+        assert_eq!(items(tree.intersecting(&cube(0.6, 0.6, 0.6, 0.1))), vec!["high", "middle"]);
+        assert_eq!(items(tree.contained_in(&cube(0.2, 0.2, 0.2, 0.8))), vec!["high", "middle"]);
+        assert_eq!(items(tree.containing(&Point::new(0.1, 0.1, 0.1))), vec!["low"]);
+    }
+
+    #[test]
+    fn nearest_box_to_point() {
+        let tree = RTree::bulk_load(vec![
+            RTreeEntry::new(cube(0.0, 0.0, 0.0, 0.25), 0),
+            RTreeEntry::new(cube(0.5, 0.0, 0.0, 0.25), 1),
+        ]);
+        let output = tree.nearest(&Point::new(1.0, 0.125, 0.125), 1);
+        assert_eq!(output[0].distance, 0.25);
+        assert_eq!(output[0].point.item, 1);
+    }
+
+    #[test]
+    fn points_behind_neighbour_search() {
+        let tree = RTree::from_points(vec![Point::new(0.1, 0.1, 0.1), Point::new(0.9, 0.9, 0.9)]);
+        let output = NeighbourSearch::nearest(&tree, &Point::new(0.2, 0.2, 0.2), 1)[0].point.clone();
+        let expected = Point::new(0.1, 0.1, 0.1);
+        assert_eq!(output, expected);
+    }
+}
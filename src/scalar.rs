@@ -0,0 +1,259 @@
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// Trait for the types that coordinates are stored in, which are either floating point `Scalar`s or integers.
+pub trait Coordinate: Copy + Debug + Default + PartialEq + PartialOrd + Send + Sync + 'static {
+    /// Converts the value to an `f64`, which is exact for `f32`, `f64` and `i32` but rounds large `i64`s.
+    fn to_f64(self) -> f64;
+
+    /// Compares two values with a total order, which for floating point types also orders NaN.
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+/// Trait for the types that squared distances are accumulated and compared in.
+pub trait Distance: Coordinate + Add<Output=Self> + Mul<Output=Self> {}
+
+/// Trait for the floating point types that coordinates and distances are stored in.
+/// Points with `f32` coordinates take half the memory of `f64` ones, at the cost of precision.
+pub trait Scalar: Distance + Sub<Output=Self> + Div<Output=Self> + Neg<Output=Self> {
+    /// The scalar that values of this type are widened to when they are accumulated with more precision.
+    type Wide: Scalar;
+
+    const ZERO: Self;
+    const ONE: Self;
+
+    /// Converts an `f64`, rounding it to the nearest value of this type.
+    fn from_f64(value: f64) -> Self;
+
+    /// Converts the value to the wider scalar, which is exact.
+    fn widen(self) -> Self::Wide;
+
+    fn sqrt(self) -> Self;
+
+    fn abs(self) -> Self;
+}
+
+impl Coordinate for f32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn total_cmp(&self, other: &f32) -> Ordering {
+        f32::total_cmp(self, other)
+    }
+}
+
+impl Coordinate for f64 {
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn total_cmp(&self, other: &f64) -> Ordering {
+        f64::total_cmp(self, other)
+    }
+}
+
+impl Coordinate for i32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn total_cmp(&self, other: &i32) -> Ordering {
+        self.cmp(other)
+    }
+}
+
+impl Coordinate for i64 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn total_cmp(&self, other: &i64) -> Ordering {
+        self.cmp(other)
+    }
+}
+
+impl Coordinate for u128 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn total_cmp(&self, other: &u128) -> Ordering {
+        self.cmp(other)
+    }
+}
+
+impl Distance for f32 {}
+
+impl Distance for f64 {}
+
+impl Distance for u128 {}
+
+impl Scalar for f32 {
+    type Wide = f64;
+
+    const ZERO: f32 = 0.0;
+    const ONE: f32 = 1.0;
+
+    fn from_f64(value: f64) -> f32 {
+        value as f32
+    }
+
+    fn widen(self) -> f64 {
+        self as f64
+    }
+
+    fn sqrt(self) -> f32 {
+        f32::sqrt(self)
+    }
+
+    fn abs(self) -> f32 {
+        f32::abs(self)
+    }
+}
+
+impl Scalar for f64 {
+    type Wide = f64;
+
+    const ZERO: f64 = 0.0;
+    const ONE: f64 = 1.0;
+
+    fn from_f64(value: f64) -> f64 {
+        value
+    }
+
+    fn widen(self) -> f64 {
+        self
+    }
+
+    fn sqrt(self) -> f64 {
+        f64::sqrt(self)
+    }
+
+    fn abs(self) -> f64 {
+        f64::abs(self)
+    }
+}
+
+/// Trait for the ways of accumulating the squared distance between two points with coordinates of type `S`,
+/// which decides the precision of the distances a search compares and reports.
+pub trait Accumulation<S: Coordinate> {
+    /// The type the squared distance is accumulated in.
+    type Output: Distance;
+
+    /// Returns the square of the difference between two coordinates.
+    fn squared_difference(a: S, b: S) -> Self::Output;
+
+    /// Returns the sum of two accumulated squared differences.
+    fn sum(a: Self::Output, b: Self::Output) -> Self::Output {
+        a + b
+    }
+}
+
+/// Accumulates distances in the type of the coordinates, which is the fastest.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Native;
+
+/// Widens the coordinates before subtracting them, so that distances between `f32` points are
+/// accumulated in `f64` and keep differences that `f32` would round away.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Widened;
+
+impl<S: Scalar> Accumulation<S> for Native {
+    type Output = S;
+
+    fn squared_difference(a: S, b: S) -> S {
+        let difference = a - b;
+        difference * difference
+    }
+}
+
+impl<S: Scalar> Accumulation<S> for Widened {
+    type Output = S::Wide;
+
+    fn squared_difference(a: S, b: S) -> S::Wide {
+        let difference = a.widen() - b.widen();
+        difference * difference
+    }
+}
+
+/// Accumulates the squared distance between integer coordinates exactly in a `u128`.
+/// Any two `i32` points work. Between `i64` points the sum of the three squared differences only
+/// fits while the coordinates stay within ±2^62; beyond that it saturates at `u128::MAX`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Exact;
+
+impl Accumulation<i32> for Exact {
+    type Output = u128;
+
+    fn squared_difference(a: i32, b: i32) -> u128 {
+        let difference = a.abs_diff(b) as u128;
+        difference * difference
+    }
+}
+
+impl Accumulation<i64> for Exact {
+    type Output = u128;
+
+    fn squared_difference(a: i64, b: i64) -> u128 {
+        let difference = a.abs_diff(b) as u128;
+        difference * difference
+    }
+
+    fn sum(a: u128, b: u128) -> u128 {
+        a.saturating_add(b)
+    }
+}
+
+#[cfg(test)]
+mod scalar_test {
+    use super::{Accumulation, Coordinate, Exact, Native, Scalar, Widened};
+
+    #[test]
+    fn round_trip_through_f64() {
+        let output = f32::from_f64(0.1).to_f64();
+        let expected = 0.1f32 as f64;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn native_accumulation_rounds_to_f32() {
+        // the exact square is 1 + 2^-11 + 2^-24, which needs one bit more than an f32 has
+        let output = <Native as Accumulation<f32>>::squared_difference(1.0 + 2f32.powi(-12), 0.0);
+        let expected = 1.0 + 2f32.powi(-11);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn widened_accumulation_keeps_every_bit() {
+        let output = <Widened as Accumulation<f32>>::squared_difference(1.0 + 2f32.powi(-12), 0.0);
+        let expected = 1.0 + 2f64.powi(-11) + 2f64.powi(-24);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn exact_accumulation_of_extreme_i32s() {
+        let output = <Exact as Accumulation<i32>>::squared_difference(i32::MIN, i32::MAX);
+        let expected = (u32::MAX as u128).pow(2);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn exact_accumulation_of_i64s_beyond_f64_precision() {
+        // both values round to the same f64, but their squared difference is still exact
+        let output = <Exact as Accumulation<i64>>::squared_difference(1 << 62, (1 << 62) + 3);
+        let expected = 9;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn exact_accumulation_of_extreme_i64s_saturates() {
+        let output = <Exact as Accumulation<i64>>::sum(
+            <Exact as Accumulation<i64>>::squared_difference(i64::MIN, i64::MAX),
+            <Exact as Accumulation<i64>>::squared_difference(i64::MIN, i64::MAX),
+        );
+        let expected = u128::MAX;
+        assert_eq!(output, expected);
+    }
+}
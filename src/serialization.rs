@@ -0,0 +1,382 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::kd_tree::{KdTree, KdTreeNode};
+use crate::point::{Dimension, Point};
+use crate::scalar::Coordinate;
+
+/// Bytes that every saved kd-tree starts with.
+const MAGIC: &[u8; 4] = b"FCPK";
+
+/// Version of the saved kd-tree format, which is bumped whenever the layout changes.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Deepest tree that can be saved and loaded, which keeps the searches and dropping the nodes,
+/// which both recurse once per level, well within the stack of any thread.
+pub const MAX_DEPTH: usize = 4096;
+
+/// Flags of a saved node that tell whether it has a left and a right subtree.
+const HAS_LEFT: u8 = 1;
+const HAS_RIGHT: u8 = 2;
+
+/// Trait for the coordinates that a kd-tree can be saved with.
+pub trait SavedCoordinate: Coordinate + Serialize + DeserializeOwned {
+    /// Code for the coordinate type in the header of a saved kd-tree, which never changes once assigned.
+    const TYPE_CODE: u32;
+}
+
+impl SavedCoordinate for f32 {
+    const TYPE_CODE: u32 = 1;
+}
+
+impl SavedCoordinate for f64 {
+    const TYPE_CODE: u32 = 2;
+}
+
+impl SavedCoordinate for i32 {
+    const TYPE_CODE: u32 = 3;
+}
+
+impl SavedCoordinate for i64 {
+    const TYPE_CODE: u32 = 4;
+}
+
+/// Enum for the ways saving or loading a kd-tree can fail.
+#[derive(Debug)]
+pub enum TreeFileError {
+    Io(io::Error),
+    /// The data does not start with the bytes of a saved kd-tree.
+    NotATree,
+    /// The tree was saved in a version of the format that this build cannot read.
+    UnsupportedVersion(u32),
+    /// The tree was saved with different coordinates than it is loaded with.
+    CoordinateMismatch { expected: u32, found: u32 },
+    Encoding(bincode::Error),
+    /// The tree was decoded but its nodes do not form a single tree of at most `MAX_DEPTH` levels,
+    /// its points are not ordered as a kd-tree, or its length is wrong.
+    InvalidTree(String),
+}
+
+impl fmt::Display for TreeFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeFileError::Io(error) => write!(f, "I/O error: {error}"),
+            TreeFileError::NotATree => write!(f, "not a saved kd-tree"),
+            TreeFileError::UnsupportedVersion(version) =>
+                write!(f, "unsupported kd-tree format version {version}, expected {FORMAT_VERSION}"),
+            TreeFileError::CoordinateMismatch { expected, found } =>
+                write!(f, "kd-tree has coordinate type {found} but {expected} was expected"),
+            TreeFileError::Encoding(error) => write!(f, "could not encode or decode kd-tree: {error}"),
+            TreeFileError::InvalidTree(reason) => write!(f, "invalid kd-tree: {reason}"),
+        }
+    }
+}
+
+impl Error for TreeFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TreeFileError::Io(error) => Some(error),
+            TreeFileError::Encoding(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TreeFileError {
+    fn from(error: io::Error) -> TreeFileError {
+        TreeFileError::Io(error)
+    }
+}
+
+impl From<bincode::Error> for TreeFileError {
+    fn from(error: bincode::Error) -> TreeFileError {
+        TreeFileError::Encoding(error)
+    }
+}
+
+impl<S: SavedCoordinate> KdTree<S> {
+    /// Writes the tree in a versioned binary format: a magic number, the format version, the code of
+    /// the coordinate type, the length of the tree and the number of its nodes, and then every node in
+    /// pre-order as its encoded point followed by flags telling which subtrees it has.
+    /// Trees deeper than `MAX_DEPTH` levels are refused before anything is written.
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<(), TreeFileError> {
+        let mut nodes = 0u64;
+        let mut stack = self.root.as_deref().map(|root| (root, 1)).into_iter().collect::<Vec<(&KdTreeNode<S>, usize)>>();
+        while let Some((node, depth)) = stack.pop() {
+            if depth > MAX_DEPTH {
+                return Err(TreeFileError::InvalidTree(format!("tree is deeper than {MAX_DEPTH} levels")));
+            }
+            nodes += 1;
+            stack.extend([&node.right, &node.left].into_iter().flatten().map(|child| (child.as_ref(), depth + 1)));
+        }
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&S::TYPE_CODE.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, &(self.len as u64, nodes))?;
+        let mut stack = self.root.as_deref().into_iter().collect::<Vec<&KdTreeNode<S>>>();
+        while let Some(node) = stack.pop() {
+            let flags = if node.left.is_some() { HAS_LEFT } else { 0 } | if node.right.is_some() { HAS_RIGHT } else { 0 };
+            bincode::serialize_into(&mut writer, &(&node.point, flags))?;
+            stack.extend([&node.right, &node.left].into_iter().flatten().map(|child| child.as_ref()));
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Reads a tree written by `save`, checking its header, that its nodes form a single tree of at most
+    /// `MAX_DEPTH` levels and that its points are still ordered as a kd-tree.
+    pub fn load<R: Read>(mut reader: R) -> Result<KdTree<S>, TreeFileError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(TreeFileError::NotATree);
+        }
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != FORMAT_VERSION {
+            return Err(TreeFileError::UnsupportedVersion(version));
+        }
+        let mut coordinate = [0; 4];
+        reader.read_exact(&mut coordinate)?;
+        let coordinate = u32::from_le_bytes(coordinate);
+        if coordinate != S::TYPE_CODE {
+            return Err(TreeFileError::CoordinateMismatch { expected: S::TYPE_CODE, found: coordinate });
+        }
+
+        let (len, nodes): (u64, u64) = bincode::deserialize_from(&mut reader)?;
+        if nodes != len {
+            return Err(TreeFileError::InvalidTree(format!("tree holds {nodes} points but records {len}")));
+        }
+        let mut records = Vec::with_capacity(nodes.min(1 << 20) as usize);
+        for _ in 0..nodes {
+            let record: (Point<S>, u8) = bincode::deserialize_from(&mut reader)?;
+            records.push(record);
+        }
+        let root = build_from_pre_order(records)?;
+        let tree = KdTree { root, len: len as usize };
+        let len = validate_tree(&tree.root)?;
+        if len != tree.len {
+            return Err(TreeFileError::InvalidTree(format!("tree holds {len} points but records {}", tree.len)));
+        }
+        Ok(tree)
+    }
+}
+
+/// Rebuilds a tree from its nodes in pre-order without recursing. Going backwards, the subtrees of a
+/// node are complete by the time it is reached, with its left subtree on top of the stack and its right
+/// one below, so every node takes its children off the stack and pushes itself with its depth.
+fn build_from_pre_order<S: Coordinate>(records: Vec<(Point<S>, u8)>) -> Result<Option<Box<KdTreeNode<S>>>, TreeFileError> {
+    let missing = || TreeFileError::InvalidTree("a node has a subtree that is missing".to_string());
+    let mut subtrees: Vec<(Box<KdTreeNode<S>>, usize)> = vec![];
+    for (point, flags) in records.into_iter().rev() {
+        if flags & !(HAS_LEFT | HAS_RIGHT) != 0 {
+            return Err(TreeFileError::InvalidTree(format!("node of {point:?} has unknown flags {flags:#04x}")));
+        }
+        let left = if flags & HAS_LEFT != 0 { Some(subtrees.pop().ok_or_else(missing)?) } else { None };
+        let right = if flags & HAS_RIGHT != 0 { Some(subtrees.pop().ok_or_else(missing)?) } else { None };
+        let depth = 1 + left.as_ref().map_or(0, |(_, depth)| *depth).max(right.as_ref().map_or(0, |(_, depth)| *depth));
+        if depth > MAX_DEPTH {
+            return Err(TreeFileError::InvalidTree(format!("tree is deeper than {MAX_DEPTH} levels")));
+        }
+        let node = KdTreeNode { point, left: left.map(|(node, _)| node), right: right.map(|(node, _)| node) };
+        subtrees.push((Box::new(node), depth));
+    }
+    match subtrees.len() {
+        0 | 1 => Ok(subtrees.pop().map(|(root, _)| root)),
+        roots => Err(TreeFileError::InvalidTree(format!("nodes form {roots} trees instead of one"))),
+    }
+}
+
+/// Checks that every point lies within the bounds its ancestors leave it, where each dimension has an
+/// inclusive lower bound from the right turns and an exclusive upper bound from the left turns,
+/// and returns the number of points in the tree. The subtrees still to check are kept on a stack
+/// with their bounds, rather than recursing once per level.
+fn validate_tree<S: Coordinate>(root: &Option<Box<KdTreeNode<S>>>) -> Result<usize, TreeFileError> {
+    let mut len = 0;
+    let mut subtrees = root.as_deref().map(|root| (root, Dimension::X, [(None, None); 3])).into_iter().collect::<Vec<_>>();
+    while let Some((curr_node, curr_dimension, bounds)) = subtrees.pop() {
+        for dimension in [Dimension::X, Dimension::Y, Dimension::Z] {
+            let value = curr_node.point.get_dimension(&dimension);
+            let (low, high): (Option<S>, Option<S>) = bounds[dimension.index()];
+            if low.is_some_and(|low| value.total_cmp(&low).is_lt()) || high.is_some_and(|high| value.total_cmp(&high).is_ge()) {
+                return Err(TreeFileError::InvalidTree(format!("{:?} is on the wrong side of an ancestor", curr_node.point)));
+            }
+        }
+        len += 1;
+
+        let index = curr_dimension.index();
+        let split = curr_node.point.get_dimension(&curr_dimension);
+        if let Some(right) = &curr_node.right {
+            let mut right_bounds = bounds;
+            right_bounds[index].0 = Some(split);
+            subtrees.push((right, curr_dimension.turn(), right_bounds));
+        }
+        if let Some(left) = &curr_node.left {
+            let mut left_bounds = bounds;
+            left_bounds[index].1 = Some(split);
+            subtrees.push((left, curr_dimension.turn(), left_bounds));
+        }
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod serialization_test {
+    use crate::kd_tree::{KdTree, KdTreeNode};
+    use crate::point::Point;
+
+    use super::{FORMAT_VERSION, HAS_LEFT, MAX_DEPTH, TreeFileError};
+
+    /// Returns a tree whose points each lie below the previous one in every dimension, so that they form a chain of left subtrees.
+    fn left_chain(len: usize) -> KdTree {
+        let mut root = None;
+        for i in 0..len {
+            let coordinate = i as f64 / len as f64;
+            root = Some(Box::new(KdTreeNode { point: Point::new(coordinate, coordinate, coordinate), left: root, right: None }));
+        }
+        KdTree { root, len }
+    }
+
+    fn points() -> Vec<Point> {
+        vec![
+            Point::new(0.1, 0.2, 0.3),
+            Point::new(0.4, 0.5, 0.6),
+            Point::new(0.7, 0.8, 0.9),
+            Point::new(0.2, 0.9, 0.1),
+        ]
+    }
+
+    #[test]
+    fn save_and_load() {
+        let tree = KdTree::construct_tree(points());
+        let mut bytes = vec![];
+        tree.save(&mut bytes).unwrap();
+        let output = KdTree::load(bytes.as_slice()).unwrap();
+        assert_eq!(output, tree);
+    }
+
+    #[test]
+    fn save_and_load_empty_tree() {
+        let tree = KdTree::<f64>::construct_tree(vec![]);
+        let mut bytes = vec![];
+        tree.save(&mut bytes).unwrap();
+        let output = KdTree::load(bytes.as_slice()).unwrap();
+        assert_eq!(output, tree);
+    }
+
+    #[test]
+    fn reject_other_data() {
+        let output = KdTree::<f64>::load(&b"not a tree at all"[..]);
+        assert!(matches!(output, Err(TreeFileError::NotATree)));
+    }
+
+    #[test]
+    fn reject_newer_version() {
+        let mut bytes = vec![];
+        KdTree::construct_tree(points()).save(&mut bytes).unwrap();
+        bytes[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        let output = KdTree::<f64>::load(bytes.as_slice());
+        assert!(matches!(output, Err(TreeFileError::UnsupportedVersion(version)) if version == FORMAT_VERSION + 1));
+    }
+
+    #[test]
+    fn reject_other_coordinates() {
+        let mut bytes = vec![];
+        KdTree::construct_tree(points()).save(&mut bytes).unwrap();
+        let output = KdTree::<f32>::load(bytes.as_slice());
+        assert!(matches!(output, Err(TreeFileError::CoordinateMismatch { expected: 1, found: 2 })));
+    }
+
+    #[test]
+    fn save_and_load_integer_coordinates() {
+        let tree = KdTree::construct_tree(vec![Point { x: 1i64, y: -2, z: 3 }, Point { x: 4, y: 5, z: -6 }]);
+        let mut bytes = vec![];
+        tree.save(&mut bytes).unwrap();
+        assert_eq!(bytes[8..12], 4u32.to_le_bytes());
+        let output = KdTree::<i64>::load(bytes.as_slice()).unwrap();
+        assert_eq!(output, tree);
+    }
+
+    #[test]
+    fn reject_truncated_tree() {
+        let mut bytes = vec![];
+        KdTree::construct_tree(points()).save(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 1);
+        let output = KdTree::<f64>::load(bytes.as_slice());
+        assert!(matches!(output, Err(TreeFileError::Encoding(_))));
+    }
+
+    #[test]
+    fn reject_points_out_of_order() {
+        let mut tree = KdTree::construct_tree(points());
+        let root = tree.root.as_mut().unwrap();
+        std::mem::swap(&mut root.left, &mut root.right);
+        let mut bytes = vec![];
+        tree.save(&mut bytes).unwrap();
+        let output = KdTree::<f64>::load(bytes.as_slice());
+        assert!(matches!(output, Err(TreeFileError::InvalidTree(_))));
+    }
+
+    #[test]
+    fn reject_wrong_length() {
+        let mut tree = KdTree::construct_tree(points());
+        tree.len += 1;
+        let mut bytes = vec![];
+        tree.save(&mut bytes).unwrap();
+        let output = KdTree::<f64>::load(bytes.as_slice());
+        assert!(matches!(output, Err(TreeFileError::InvalidTree(_))));
+    }
+
+    #[test]
+    fn save_and_load_tree_of_max_depth() {
+        let tree = left_chain(MAX_DEPTH);
+        let mut bytes = vec![];
+        tree.save(&mut bytes).unwrap();
+        let output = KdTree::<f64>::load(bytes.as_slice()).unwrap();
+        assert_eq!(output.len, MAX_DEPTH);
+    }
+
+    #[test]
+    fn refuse_to_save_too_deep_tree() {
+        let mut bytes = vec![];
+        let output = left_chain(MAX_DEPTH + 1).save(&mut bytes);
+        assert!(matches!(output, Err(TreeFileError::InvalidTree(_))));
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn reject_too_deep_tree() {
+        // a file written by hand, since `save` refuses such trees, holding a chain of 200,000 left subtrees
+        let len = 200_000u64;
+        let mut bytes = vec![];
+        KdTree::<f64>::construct_tree(vec![]).save(&mut bytes).unwrap();
+        bytes.truncate(12);
+        bincode::serialize_into(&mut bytes, &(len, len)).unwrap();
+        for i in 0..len {
+            let coordinate = 1.0 - i as f64 / len as f64;
+            let flags = if i + 1 < len { HAS_LEFT } else { 0 };
+            bincode::serialize_into(&mut bytes, &(Point::new(coordinate, coordinate, coordinate), flags)).unwrap();
+        }
+        let output = KdTree::<f64>::load(bytes.as_slice());
+        assert!(matches!(output, Err(TreeFileError::InvalidTree(message)) if message.contains("deeper")));
+    }
+
+    #[test]
+    fn reject_nodes_that_do_not_form_one_tree() {
+        let mut bytes = vec![];
+        KdTree::<f64>::construct_tree(vec![]).save(&mut bytes).unwrap();
+        bytes.truncate(12);
+        bincode::serialize_into(&mut bytes, &(2u64, 2u64)).unwrap();
+        for point in points().into_iter().take(2) {
+            bincode::serialize_into(&mut bytes, &(point, 0u8)).unwrap();
+        }
+        let output = KdTree::<f64>::load(bytes.as_slice());
+        assert!(matches!(output, Err(TreeFileError::InvalidTree(_))));
+    }
+}
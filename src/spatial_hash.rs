@@ -0,0 +1,409 @@
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::bounding_box::BoundingBox;
+use crate::nearest_neighbour::{is_potential_nearer_neighbour, Neighbour, NeighbourSearch};
+use crate::point::Point;
+use crate::spatial_index::SpatialIndex;
+
+/// Integer coordinates of a cell of the grid.
+type CellKey = (i64, i64, i64);
+
+/// Struct that identifies a point stored in a `SpatialHash`.
+/// A handle stops being valid once its point is removed, even if the slot is reused by a later insertion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Handle {
+    index: usize,
+    generation: u32,
+}
+
+/// Struct that represents a slot of the spatial hash, which remembers where its point sits in its cell.
+#[derive(Debug, Clone, PartialEq)]
+struct Slot {
+    generation: u32,
+    occupant: Option<Occupant>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Occupant {
+    point: Point,
+    cell: CellKey,
+    position: usize,
+}
+
+/// Struct that represents a uniform grid of cubic cells, hashed by their integer coordinates.
+/// Points can be inserted, removed and moved in constant time, and a query for neighbours within a
+/// radius close to the cell size only has to look at the few cells around the given point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpatialHash {
+    cell_size: f64,
+    cells: HashMap<CellKey, Vec<usize>>,
+    slots: Vec<Slot>,
+    free_slots: Vec<usize>,
+    len: usize,
+}
+
+impl SpatialHash {
+    /// Creates an empty spatial hash whose cells have the given edge length,
+    /// which works best when it is about the radius used by queries.
+    pub fn new(cell_size: f64) -> SpatialHash {
+        assert!(cell_size > 0.0, "the cell size must be positive");
+        SpatialHash { cell_size, cells: HashMap::new(), slots: vec![], free_slots: vec![], len: 0 }
+    }
+
+    /// Creates a spatial hash holding all the given points.
+    pub fn from_points(points: Vec<Point>, cell_size: f64) -> SpatialHash {
+        let mut spatial_hash = SpatialHash::new(cell_size);
+        for point in points {
+            spatial_hash.insert(point);
+        }
+        spatial_hash
+    }
+
+    /// Returns the edge length of the cells.
+    pub fn cell_size(&self) -> f64 {
+        self.cell_size
+    }
+
+    /// Returns the number of points in the spatial hash.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the spatial hash holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Inserts a point and returns the handle used to move or remove it later.
+    pub fn insert(&mut self, point: Point) -> Handle {
+        let index = match self.free_slots.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot { generation: 0, occupant: None });
+                self.slots.len() - 1
+            }
+        };
+        let cell = self.cell_of(&point);
+        let position = self.add_to_cell(cell, index);
+        self.slots[index].occupant = Some(Occupant { point, cell, position });
+        self.len += 1;
+        Handle { index, generation: self.slots[index].generation }
+    }
+
+    /// Returns the point behind the handle, or `None` if it has been removed.
+    pub fn get(&self, handle: Handle) -> Option<&Point> {
+        self.occupant(handle).map(|occupant| &occupant.point)
+    }
+
+    /// Removes the point behind the handle and returns it, or `None` if it was already removed.
+    pub fn remove(&mut self, handle: Handle) -> Option<Point> {
+        let occupant = self.occupant(handle)?.clone();
+        self.remove_from_cell(occupant.cell, occupant.position);
+        let slot = &mut self.slots[handle.index];
+        slot.occupant = None;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(handle.index);
+        self.len -= 1;
+        Some(occupant.point)
+    }
+
+    /// Moves the point behind the handle to a new position, returning false if it has been removed.
+    pub fn move_to(&mut self, handle: Handle, point: Point) -> bool {
+        let Some(occupant) = self.occupant(handle) else { return false; };
+        let (old_cell, old_position) = (occupant.cell, occupant.position);
+        let cell = self.cell_of(&point);
+        let position = match cell == old_cell {
+            true => old_position,
+            false => {
+                self.remove_from_cell(old_cell, old_position);
+                self.add_to_cell(cell, handle.index)
+            }
+        };
+        self.slots[handle.index].occupant = Some(Occupant { point, cell, position });
+        true
+    }
+
+    /// Returns the handle and distance of every point at most `radius` away from the given point, sorted by distance.
+    pub fn handles_within_radius(&self, given_point: &Point, radius: f64) -> Vec<Neighbour<Handle>> {
+        let mut neighbours = vec![];
+        let low = self.cell_of(&Point { x: given_point.x - radius, y: given_point.y - radius, z: given_point.z - radius });
+        let high = self.cell_of(&Point { x: given_point.x + radius, y: given_point.y + radius, z: given_point.z + radius });
+        self.for_each_cell_between(low, high, |indices| {
+            for &index in indices {
+                let occupant = self.slots[index].occupant.as_ref().unwrap();
+                let curr_distance = given_point.distance_to(&occupant.point);
+                if curr_distance <= radius {
+                    let handle = Handle { index, generation: self.slots[index].generation };
+                    neighbours.push(Neighbour { distance: curr_distance, point: handle });
+                }
+            }
+        });
+        neighbours.sort();
+        neighbours
+    }
+
+    /// Returns the handle of every point inside the given box, including points on its boundary, in no particular order.
+    pub fn handles_in_box(&self, bounding_box: &BoundingBox) -> Vec<Handle> {
+        let mut handles = vec![];
+        self.for_each_cell_between(self.cell_of(&bounding_box.min), self.cell_of(&bounding_box.max), |indices| {
+            for &index in indices {
+                if bounding_box.contains(&self.slots[index].occupant.as_ref().unwrap().point) {
+                    handles.push(Handle { index, generation: self.slots[index].generation });
+                }
+            }
+        });
+        handles
+    }
+
+    /// Returns the handle and distance of the k nearest points, sorted by distance.
+    /// Cells are visited in growing cubic rings around the given point until no unvisited cell can hold a nearer point.
+    pub fn nearest_handles(&self, given_point: &Point, k: usize) -> Vec<Neighbour<Handle>> {
+        let mut k_nearest_neighbours = BinaryHeap::new();
+        if k == 0 {
+            return vec![];
+        }
+        let center = self.cell_of(given_point);
+        let visit = |indices: &Vec<usize>, k_nearest_neighbours: &mut BinaryHeap<Neighbour<Handle>>| {
+            for &index in indices {
+                let occupant = self.slots[index].occupant.as_ref().unwrap();
+                let curr_distance = given_point.distance_to(&occupant.point);
+                if is_potential_nearer_neighbour(curr_distance, k_nearest_neighbours, k) {
+                    if k_nearest_neighbours.len() >= k { k_nearest_neighbours.pop(); }
+                    let handle = Handle { index, generation: self.slots[index].generation };
+                    k_nearest_neighbours.push(Neighbour { distance: curr_distance, point: handle });
+                }
+            }
+        };
+
+        for ring in 0i64.. {
+            // every cell outside the ring is at least this far from the given point
+            let distance_beyond_ring = ring as f64 * self.cell_size;
+            let ring_cell_count = ((2 * ring + 1).pow(3) - (2 * ring - 1).max(0).pow(3)) as usize;
+            if ring_cell_count > self.cells.len() {
+                // the ring has more cells than are occupied, so visit the occupied cells still outside the rings directly
+                self.cells.iter()
+                    .filter(|(key, _)| chebyshev_distance(key, &center) >= ring)
+                    .for_each(|(_, indices)| visit(indices, &mut k_nearest_neighbours));
+                break;
+            }
+            for_each_cell_in_ring(center, ring, |key| {
+                if let Some(indices) = self.cells.get(&key) {
+                    visit(indices, &mut k_nearest_neighbours);
+                }
+            });
+            if !is_potential_nearer_neighbour(distance_beyond_ring, &k_nearest_neighbours, k) {
+                break;
+            }
+        }
+        k_nearest_neighbours.into_sorted_vec()
+    }
+
+    /// Calls `f` with the slots of every occupied cell between the two cells, both included.
+    fn for_each_cell_between(&self, low: CellKey, high: CellKey, mut f: impl FnMut(&Vec<usize>)) {
+        let cell_count = (high.0.abs_diff(low.0) as u128 + 1) * (high.1.abs_diff(low.1) as u128 + 1) * (high.2.abs_diff(low.2) as u128 + 1);
+        if cell_count > self.cells.len() as u128 {
+            // a range much larger than the cells covers more cells than are occupied
+            let in_range = |key: &CellKey| (low.0..=high.0).contains(&key.0) && (low.1..=high.1).contains(&key.1) && (low.2..=high.2).contains(&key.2);
+            self.cells.iter().filter(|(key, _)| in_range(key)).for_each(|(_, indices)| f(indices));
+        } else {
+            for x in low.0..=high.0 {
+                for y in low.1..=high.1 {
+                    for z in low.2..=high.2 {
+                        if let Some(indices) = self.cells.get(&(x, y, z)) {
+                            f(indices);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn cell_of(&self, point: &Point) -> CellKey {
+        (
+            (point.x / self.cell_size).floor() as i64,
+            (point.y / self.cell_size).floor() as i64,
+            (point.z / self.cell_size).floor() as i64,
+        )
+    }
+
+    fn occupant(&self, handle: Handle) -> Option<&Occupant> {
+        let slot = self.slots.get(handle.index)?;
+        match slot.generation == handle.generation {
+            true => slot.occupant.as_ref(),
+            false => None,
+        }
+    }
+
+    /// Adds the slot to the cell and returns its position within the cell.
+    fn add_to_cell(&mut self, cell: CellKey, index: usize) -> usize {
+        let indices = self.cells.entry(cell).or_default();
+        indices.push(index);
+        indices.len() - 1
+    }
+
+    /// Removes the slot at the given position of the cell by swapping the last slot of the cell into its place.
+    fn remove_from_cell(&mut self, cell: CellKey, position: usize) {
+        let indices = self.cells.get_mut(&cell).unwrap();
+        indices.swap_remove(position);
+        if let Some(&moved) = indices.get(position) {
+            self.slots[moved].occupant.as_mut().unwrap().position = position;
+        }
+        if indices.is_empty() {
+            self.cells.remove(&cell);
+        }
+    }
+}
+
+impl NeighbourSearch for SpatialHash {
+    fn nearest(&self, given_point: &Point, k: usize) -> Vec<Neighbour> {
+        self.nearest_handles(given_point, k).into_iter()
+            .map(|neighbour| Neighbour { distance: neighbour.distance, point: self.get(neighbour.point).unwrap().clone() })
+            .collect()
+    }
+
+    fn within_radius(&self, given_point: &Point, radius: f64) -> Vec<Neighbour> {
+        self.handles_within_radius(given_point, radius).into_iter()
+            .map(|neighbour| Neighbour { distance: neighbour.distance, point: self.get(neighbour.point).unwrap().clone() })
+            .collect()
+    }
+}
+
+/// Picks the cell size so that the points would hold about one point per cell if they were spread evenly over their box.
+impl SpatialIndex for SpatialHash {
+    fn build(points: Vec<Point>) -> SpatialHash {
+        let cell_size = match BoundingBox::from_points(&points) {
+            Some(bounds) => {
+                let extent = (bounds.max.x - bounds.min.x).max(bounds.max.y - bounds.min.y).max(bounds.max.z - bounds.min.z);
+                extent / (points.len() as f64).cbrt()
+            }
+            None => 1.0,
+        };
+        SpatialHash::from_points(points, if cell_size > 0.0 { cell_size } else { 1.0 })
+    }
+
+    fn in_box(&self, bounding_box: &BoundingBox) -> Vec<Point> {
+        self.handles_in_box(bounding_box).into_iter().map(|handle| self.get(handle).unwrap().clone()).collect()
+    }
+
+    fn len(&self) -> usize {
+        SpatialHash::len(self)
+    }
+}
+
+/// Returns the number of cells between two cells along the axis where they are furthest apart.
+fn chebyshev_distance(a: &CellKey, b: &CellKey) -> i64 {
+    (a.0 - b.0).abs().max((a.1 - b.1).abs()).max((a.2 - b.2).abs())
+}
+
+/// Calls `f` for every cell whose Chebyshev distance to the center is exactly `ring`.
+fn for_each_cell_in_ring(center: CellKey, ring: i64, mut f: impl FnMut(CellKey)) {
+    for x in -ring..=ring {
+        for y in -ring..=ring {
+            let on_side = x.abs() == ring || y.abs() == ring;
+            // inside the ring only the top and bottom faces along z belong to it
+            let step = if on_side || ring == 0 { 1 } else { 2 * ring };
+            let mut z = -ring;
+            while z <= ring {
+                f((center.0 + x, center.1 + y, center.2 + z));
+                z += step;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod spatial_hash_test {
+    use crate::bounding_box::BoundingBox;
+    use crate::nearest_neighbour::NeighbourSearch;
+    use crate::point::Point;
+    use crate::spatial_index::SpatialIndex;
+
+    use super::{chebyshev_distance, for_each_cell_in_ring, SpatialHash};
+
+    #[test]
+    fn cells_in_ring() {
+        for ring in 0..4 {
+            let mut cells = vec![];
+            for_each_cell_in_ring((1, -2, 3), ring, |cell| cells.push(cell));
+            let expected = (2 * ring + 1).pow(3) - (2 * ring - 1).max(0).pow(3);
+            assert_eq!(cells.len() as i64, expected);
+            assert!(cells.iter().all(|cell| chebyshev_distance(cell, &(1, -2, 3)) == ring));
+        }
+    }
+
+    #[test]
+    fn points_are_hashed_by_cell() {
+        let spatial_hash = SpatialHash::new(0.25);
+        assert_eq!(spatial_hash.cell_of(&Point::new(0.3, 0.0, 0.99)), (1, 0, 3));
+        assert_eq!(spatial_hash.cell_of(&Point { x: -0.1, y: 0.25, z: 0.5 }), (-1, 1, 2));
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut spatial_hash = SpatialHash::new(0.1);
+        let first = spatial_hash.insert(Point::new(0.1, 0.1, 0.1));
+        let second = spatial_hash.insert(Point::new(0.15, 0.1, 0.1));
+        assert_eq!(spatial_hash.len(), 2);
+        assert_eq!(spatial_hash.remove(first), Some(Point::new(0.1, 0.1, 0.1)));
+        assert_eq!(spatial_hash.remove(first), None);
+        assert_eq!(spatial_hash.get(second), Some(&Point::new(0.15, 0.1, 0.1)));
+        assert_eq!(spatial_hash.len(), 1);
+    }
+
+    #[test]
+    fn reused_slot_does_not_revive_old_handle() {
+        let mut spatial_hash = SpatialHash::new(0.1);
+        let old = spatial_hash.insert(Point::new(0.1, 0.1, 0.1));
+        spatial_hash.remove(old);
+        let new = spatial_hash.insert(Point::new(0.5, 0.5, 0.5));
+        assert_ne!(old, new);
+        assert_eq!(spatial_hash.get(old), None);
+        assert!(!spatial_hash.move_to(old, Point::new(0.2, 0.2, 0.2)));
+    }
+
+    #[test]
+    fn move_between_cells() {
+        let mut spatial_hash = SpatialHash::new(0.1);
+        let handle = spatial_hash.insert(Point::new(0.05, 0.05, 0.05));
+        let other = spatial_hash.insert(Point::new(0.06, 0.05, 0.05));
+        assert!(spatial_hash.move_to(handle, Point::new(0.95, 0.95, 0.95)));
+        assert_eq!(spatial_hash.within_radius(&Point::new(0.05, 0.05, 0.05), 0.05).len(), 1);
+        assert_eq!(spatial_hash.within_radius(&Point::new(0.95, 0.95, 0.95), 0.05).len(), 1);
+        assert_eq!(spatial_hash.remove(other), Some(Point::new(0.06, 0.05, 0.05)));
+        assert_eq!(spatial_hash.cells.len(), 1);
+    }
+
+    #[test]
+    fn nearest_beyond_many_empty_rings() {
+        let spatial_hash = SpatialHash::from_points(vec![Point::new(0.0, 0.0, 0.0), Point::new(1.0, 1.0, 1.0)], 0.001);
+        let output = spatial_hash.nearest(&Point::new(0.9, 0.9, 0.9), 1)[0].point.clone();
+        let expected = Point::new(1.0, 1.0, 1.0);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn nearest_in_empty_spatial_hash() {
+        let output = SpatialHash::new(0.1).nearest(&Point::new(0.5, 0.5, 0.5), 3);
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn handles_in_box_across_cells() {
+        let mut spatial_hash = SpatialHash::new(0.1);
+        let inside = spatial_hash.insert(Point::new(0.15, 0.3, 0.3));
+        let on_boundary = spatial_hash.insert(Point::new(0.4, 0.4, 0.4));
+        spatial_hash.insert(Point::new(0.45, 0.3, 0.3));
+        let output = spatial_hash.handles_in_box(&BoundingBox::new(Point::new(0.1, 0.2, 0.2), Point::new(0.4, 0.4, 0.4)));
+        let expected = [inside, on_boundary];
+        assert_eq!(output.len(), 2);
+        assert!(expected.iter().all(|handle| output.contains(handle)));
+    }
+
+    #[test]
+    fn build_picks_cell_size_from_points() {
+        let points = (0..1000).map(|i| Point::new((i % 10) as f64 / 9.0, (i / 10 % 10) as f64 / 9.0, (i / 100) as f64 / 9.0)).collect();
+        let output = <SpatialHash as SpatialIndex>::build(points).cell_size();
+        let expected = 0.1;
+        assert_eq!(output, expected);
+    }
+}
@@ -0,0 +1,23 @@
+use crate::bounding_box::BoundingBox;
+use crate::nearest_neighbour::NeighbourSearch;
+use crate::point::Point;
+
+/// Trait for exact spatial indexes over `Point`s, which on top of the neighbour queries of
+/// `NeighbourSearch` can be built from a vector of points, report the points inside a box and count them.
+/// Every implementation returns the same points for the same queries, so code and tests can be
+/// written once for all of them.
+pub trait SpatialIndex: NeighbourSearch {
+    /// Constructs the index from a vector of `Point` objects.
+    fn build(points: Vec<Point>) -> Self where Self: Sized;
+
+    /// Returns every point inside the given box, including points on its boundary, in no particular order.
+    fn in_box(&self, bounding_box: &BoundingBox) -> Vec<Point>;
+
+    /// Returns the number of points in the index.
+    fn len(&self) -> usize;
+
+    /// Returns true if the index holds no points.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::BinaryHeap;
+
+use rand::{Rng, thread_rng};
+
+use crate::bounding_box::BoundingBox;
+use crate::nearest_neighbour::{is_potential_nearer_neighbour, Neighbour, NeighbourSearch};
+use crate::point::Point;
+use crate::spatial_index::SpatialIndex;
+
+/// Trait for distance functions between two items of type `T`.
+///
+/// The vantage-point tree only relies on the distance being a metric: it must be non-negative,
+/// symmetric, zero between identical items and satisfy the triangle inequality.
+pub trait Metric<T> {
+    fn distance(&self, a: &T, b: &T) -> f64;
+}
+
+impl<T, F> Metric<T> for F where F: Fn(&T, &T) -> f64 {
+    fn distance(&self, a: &T, b: &T) -> f64 {
+        self(a, b)
+    }
+}
+
+/// The Euclidean distance between two `Point`s, or between two vectors with the same number of dimensions.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Euclidean;
+
+impl Metric<Point> for Euclidean {
+    fn distance(&self, a: &Point, b: &Point) -> f64 {
+        a.distance_to(b)
+    }
+}
+
+impl Metric<Vec<f64>> for Euclidean {
+    fn distance(&self, a: &Vec<f64>, b: &Vec<f64>) -> f64 {
+        a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum::<f64>().sqrt()
+    }
+}
+
+/// Struct that represents a node in the vantage-point tree.
+/// Items of the `inside` subtree are at most `threshold` away from the vantage point,
+/// and items of the `outside` subtree are at least `threshold` away.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VpTreeNode<T> {
+    pub vantage_point: T,
+    pub threshold: f64,
+    pub inside: Option<Box<VpTreeNode<T>>>,
+    pub outside: Option<Box<VpTreeNode<T>>>,
+}
+
+/// Struct that represents a vantage-point tree over items of any metric space.
+#[derive(Debug, Clone)]
+pub struct VpTree<T, M> {
+    pub root: Option<Box<VpTreeNode<T>>>,
+    metric: M,
+}
+
+impl<T, M: Metric<T>> VpTree<T, M> {
+    /// Constructs a vantage-point tree from a vector of items and the metric used to compare them.
+    pub fn construct_tree(items: Vec<T>, metric: M) -> VpTree<T, M> {
+        VpTree { root: construct_vp_tree(items, &metric), metric }
+    }
+
+    /// Returns the metric used by the tree.
+    pub fn metric(&self) -> &M {
+        &self.metric
+    }
+}
+
+impl<T: Clone, M: Metric<T>> VpTree<T, M> {
+    /// Returns the k nearest neighbours to the given item, sorted by distance.
+    pub fn nearest(&self, given_item: &T, k: usize) -> Vec<Neighbour<T>> {
+        let mut k_nearest_neighbours = BinaryHeap::new();
+        if let Some(root) = &self.root {
+            self.find_k_nearest_neighbours(root, given_item, &mut k_nearest_neighbours, k);
+        }
+        k_nearest_neighbours.into_sorted_vec()
+    }
+
+    /// Returns every neighbour whose distance to the given item is at most `radius`, sorted by distance.
+    pub fn within_radius(&self, given_item: &T, radius: f64) -> Vec<Neighbour<T>> {
+        let mut neighbours = vec![];
+        if let Some(root) = &self.root {
+            self.find_neighbours_within_radius(root, given_item, radius, &mut neighbours);
+        }
+        neighbours.sort();
+        neighbours
+    }
+
+    fn find_k_nearest_neighbours(&self, curr_node: &VpTreeNode<T>, given_item: &T,
+                                 k_nearest_neighbours: &mut BinaryHeap<Neighbour<T>>, k: usize) {
+        let curr_distance = self.metric.distance(given_item, &curr_node.vantage_point);
+        if is_potential_nearer_neighbour(curr_distance, k_nearest_neighbours, k) {
+            if k_nearest_neighbours.len() >= k { k_nearest_neighbours.pop(); }
+            k_nearest_neighbours.push(Neighbour { distance: curr_distance, point: curr_node.vantage_point.clone() });
+        }
+
+        // by the triangle inequality, these are the smallest distances to any item of each subtree
+        let distance_to_inside = (curr_distance - curr_node.threshold).max(0.0);
+        let distance_to_outside = (curr_node.threshold - curr_distance).max(0.0);
+        let subtrees = match curr_distance < curr_node.threshold {
+            true => [(&curr_node.inside, distance_to_inside), (&curr_node.outside, distance_to_outside)],
+            false => [(&curr_node.outside, distance_to_outside), (&curr_node.inside, distance_to_inside)],
+        };
+        for (subtree, distance_to_subtree) in subtrees {
+            if let Some(subtree) = subtree {
+                if is_potential_nearer_neighbour(distance_to_subtree, k_nearest_neighbours, k) {
+                    self.find_k_nearest_neighbours(subtree, given_item, k_nearest_neighbours, k);
+                }
+            }
+        }
+    }
+
+    fn find_neighbours_within_radius(&self, curr_node: &VpTreeNode<T>, given_item: &T, radius: f64,
+                                     neighbours: &mut Vec<Neighbour<T>>) {
+        let curr_distance = self.metric.distance(given_item, &curr_node.vantage_point);
+        if curr_distance <= radius {
+            neighbours.push(Neighbour { distance: curr_distance, point: curr_node.vantage_point.clone() });
+        }
+        if let Some(inside) = &curr_node.inside {
+            if curr_distance - curr_node.threshold <= radius {
+                self.find_neighbours_within_radius(inside, given_item, radius, neighbours);
+            }
+        }
+        if let Some(outside) = &curr_node.outside {
+            if curr_node.threshold - curr_distance <= radius {
+                self.find_neighbours_within_radius(outside, given_item, radius, neighbours);
+            }
+        }
+    }
+}
+
+impl<M: Metric<Point>> NeighbourSearch for VpTree<Point, M> {
+    fn nearest(&self, given_point: &Point, k: usize) -> Vec<Neighbour> {
+        VpTree::nearest(self, given_point, k)
+    }
+
+    fn within_radius(&self, given_point: &Point, radius: f64) -> Vec<Neighbour> {
+        VpTree::within_radius(self, given_point, radius)
+    }
+}
+
+/// Box queries compare the box with the shells around each vantage point, which relies on the distance being Euclidean.
+impl SpatialIndex for VpTree<Point, Euclidean> {
+    fn build(points: Vec<Point>) -> VpTree<Point, Euclidean> {
+        VpTree::construct_tree(points, Euclidean)
+    }
+
+    fn in_box(&self, bounding_box: &BoundingBox) -> Vec<Point> {
+        let mut points = vec![];
+        if let Some(root) = &self.root {
+            find_points_in_box(root, bounding_box, &mut points);
+        }
+        points
+    }
+
+    fn len(&self) -> usize {
+        self.root.as_deref().map_or(0, count_items)
+    }
+}
+
+fn find_points_in_box(curr_node: &VpTreeNode<Point>, bounding_box: &BoundingBox, points: &mut Vec<Point>) {
+    if bounding_box.contains(&curr_node.vantage_point) {
+        points.push(curr_node.vantage_point.clone());
+    }
+    // the inside subtree is within the threshold of the vantage point and the outside subtree beyond it,
+    // so a subtree can only hold points of the box if the box reaches that part of space
+    if let Some(inside) = &curr_node.inside {
+        if bounding_box.distance_to_point(&curr_node.vantage_point) <= curr_node.threshold {
+            find_points_in_box(inside, bounding_box, points);
+        }
+    }
+    if let Some(outside) = &curr_node.outside {
+        if bounding_box.max_distance_to_point(&curr_node.vantage_point) >= curr_node.threshold {
+            find_points_in_box(outside, bounding_box, points);
+        }
+    }
+}
+
+fn count_items<T>(curr_node: &VpTreeNode<T>) -> usize {
+    1 + [&curr_node.inside, &curr_node.outside].into_iter().flatten().map(|child| count_items(child)).sum::<usize>()
+}
+
+fn construct_vp_tree<T, M: Metric<T>>(mut items: Vec<T>, metric: &M) -> Option<Box<VpTreeNode<T>>> {
+    if items.is_empty() {
+        return None;
+    }
+    // a random vantage point keeps the tree balanced on average whatever the order of the items
+    let vantage_point = items.swap_remove(thread_rng().gen_range(0..items.len()));
+    if items.is_empty() {
+        return Some(Box::new(VpTreeNode { vantage_point, threshold: 0.0, inside: None, outside: None }));
+    }
+
+    // split the remaining items at the median distance from the vantage point
+    let mut items = items.into_iter()
+        .map(|item| (metric.distance(&vantage_point, &item), item))
+        .collect::<Vec<(f64, T)>>();
+    let middle = items.len() / 2;
+    items.select_nth_unstable_by(middle, |a, b| a.0.total_cmp(&b.0));
+    let threshold = items[middle].0;
+    let outside = items.split_off(middle);
+    let strip = |items: Vec<(f64, T)>| items.into_iter().map(|(_, item)| item).collect::<Vec<T>>();
+
+    Some(Box::new(VpTreeNode {
+        vantage_point,
+        threshold,
+        inside: construct_vp_tree(strip(items), metric),
+        outside: construct_vp_tree(strip(outside), metric),
+    }))
+}
+
+#[cfg(test)]
+mod vp_tree_test {
+    use crate::bounding_box::BoundingBox;
+    use crate::point::Point;
+    use crate::spatial_index::SpatialIndex;
+
+    use super::{construct_vp_tree, Euclidean, Metric, VpTree, VpTreeNode};
+
+    fn absolute_difference(a: &i64, b: &i64) -> f64 {
+        (a - b).abs() as f64
+    }
+
+    #[test]
+    fn euclidean_metric() {
+        let output = Euclidean.distance(&Point::new(0.0, 0.0, 0.0), &Point::new(0.2, 0.3, 0.6));
+        let expected = 0.7;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn euclidean_metric_on_vectors() {
+        let output = Euclidean.distance(&vec![1.0, 2.0, 3.0, 4.0], &vec![2.0, 1.0, 4.0, 3.0]);
+        let expected = 2.0;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn closure_metric() {
+        let metric = |a: &i64, b: &i64| absolute_difference(a, b);
+        let output = metric.distance(&3, &-4);
+        let expected = 7.0;
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn build_vp_tree_empty_vec() {
+        let output = construct_vp_tree(Vec::<i64>::new(), &absolute_difference);
+        assert_eq!(output, None);
+    }
+
+    #[test]
+    fn build_vp_tree_one_item() {
+        let output = construct_vp_tree(vec![5], &absolute_difference);
+        let expected = Some(Box::new(VpTreeNode { vantage_point: 5, threshold: 0.0, inside: None, outside: None }));
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn subtrees_respect_threshold() {
+        fn check(node: &VpTreeNode<i64>) {
+            let mut stack = node.inside.iter().collect::<Vec<_>>();
+            while let Some(inside) = stack.pop() {
+                assert!(absolute_difference(&node.vantage_point, &inside.vantage_point) <= node.threshold);
+                stack.extend(inside.inside.iter().chain(inside.outside.iter()));
+            }
+            let mut stack = node.outside.iter().collect::<Vec<_>>();
+            while let Some(outside) = stack.pop() {
+                assert!(absolute_difference(&node.vantage_point, &outside.vantage_point) >= node.threshold);
+                stack.extend(outside.inside.iter().chain(outside.outside.iter()));
+            }
+            for child in node.inside.iter().chain(node.outside.iter()) {
+                check(child);
+            }
+        }
+        let tree = VpTree::construct_tree((0..100).collect(), absolute_difference);
+        check(tree.root.as_ref().unwrap());
+    }
+
+    #[test]
+    fn nearest_integers() {
+        let tree = VpTree::construct_tree(vec![1, 10, 20, 30, 40], absolute_difference);
+        let output = tree.nearest(&22, 2).into_iter().map(|n| n.point).collect::<Vec<i64>>();
+        let expected = vec![20, 30];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn integers_within_radius() {
+        let tree = VpTree::construct_tree(vec![1, 10, 20, 30, 40], absolute_difference);
+        let output = tree.within_radius(&25, 5.0).into_iter().map(|n| n.distance).collect::<Vec<f64>>();
+        let expected = vec![5.0, 5.0];
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn points_in_box() {
+        let points = (0..100).map(|i| Point::new(i as f64 / 100.0, (i % 10) as f64 / 10.0, 0.5)).collect::<Vec<Point>>();
+        let tree = VpTree::construct_tree(points.clone(), Euclidean);
+        let bounding_box = BoundingBox::new(Point::new(0.2, 0.3, 0.5), Point::new(0.6, 0.5, 0.5));
+        let mut output = tree.in_box(&bounding_box);
+        output.sort_by(|a, b| a.x.total_cmp(&b.x));
+        let expected = points.into_iter().filter(|p| bounding_box.contains(p)).collect::<Vec<Point>>();
+        assert_eq!(output, expected);
+        assert_eq!(tree.len(), 100);
+    }
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "arrow")]
+
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, UInt64Array};
+
+use find_closest_points::{generate_random_points, NUM_OF_NEAREST_NEIGHBOURS};
+use find_closest_points::brute_force::BruteForce;
+use find_closest_points::formats::arrow::{neighbours_to_batch, points_from_batches};
+use find_closest_points::kd_tree::KdTree;
+use find_closest_points::nearest_neighbour::NeighbourSearch;
+use find_closest_points::point::Point;
+
+fn to_batch(points: &[Point]) -> RecordBatch {
+    let column = |coordinate: fn(&Point) -> f64| Arc::new(Float64Array::from(points.iter().map(coordinate).collect::<Vec<f64>>())) as ArrayRef;
+    RecordBatch::try_from_iter([("x", column(|point| point.x)), ("y", column(|point| point.y)), ("z", column(|point| point.z))]).unwrap()
+}
+
+#[test]
+fn tree_built_from_batches_returns_neighbour_ids_of_their_rows() {
+    let points = generate_random_points(100_000);
+    let batches = points.chunks(8192).map(to_batch).collect::<Vec<RecordBatch>>();
+    let tree = KdTree::construct_tree_from_batches(&batches).unwrap();
+    let queries = generate_random_points(100);
+    let neighbours = queries.iter().map(|query| tree.nearest(query, NUM_OF_NEAREST_NEIGHBOURS)).collect::<Vec<_>>();
+    let output = neighbours_to_batch(&points_from_batches(&batches).unwrap(), &neighbours).unwrap();
+    assert_eq!(output.num_rows(), 100 * NUM_OF_NEAREST_NEIGHBOURS);
+
+    let brute_force = BruteForce::construct_index(points);
+    let query_ids = output.column(0).as_any().downcast_ref::<UInt64Array>().unwrap();
+    let neighbour_ids = output.column(1).as_any().downcast_ref::<UInt64Array>().unwrap();
+    let distances = output.column(2).as_any().downcast_ref::<Float64Array>().unwrap();
+    for row in 0..output.num_rows() {
+        let query = &queries[query_ids.value(row) as usize];
+        let point = brute_force.point(neighbour_ids.value(row) as usize);
+        assert_eq!(query_ids.value(row) as usize, row / NUM_OF_NEAREST_NEIGHBOURS);
+        assert_eq!(point.distance_to(query), distances.value(row));
+    }
+    for (i, query) in queries.iter().enumerate() {
+        let expected = brute_force.nearest(query, NUM_OF_NEAREST_NEIGHBOURS);
+        let output = &distances.values()[i * NUM_OF_NEAREST_NEIGHBOURS..(i + 1) * NUM_OF_NEAREST_NEIGHBOURS];
+        assert_eq!(output, expected.iter().map(|neighbour| neighbour.distance).collect::<Vec<f64>>());
+    }
+}
@@ -0,0 +1,139 @@
+#![cfg(feature = "cli")]
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"))
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("find_closest_points_cli_{}_{name}", std::process::id()))
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_find_closest_points")).args(args).output().unwrap()
+}
+
+fn stdout(args: &[&str]) -> String {
+    let output = run(args);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn build_then_query_and_stats_of_the_index() {
+    let index = temp_path("cube.fcpk");
+    let index = index.to_str().unwrap();
+    stdout(&["build", &fixture("cube_binary.pcd"), index]);
+    let output = stdout(&["query", "--index", index, "--point", "0.6,0.7,0.6", "--point", "0.1,0.2,0", "--k", "1"]);
+    let from_input = stdout(&["query", "--input", &fixture("cube_binary.pcd"), "--point", "0.6,0.7,0.6", "--point", "0.1,0.2,0", "--k", "1"]);
+    let stats = stdout(&["stats", "--index", index]);
+    std::fs::remove_file(index).unwrap();
+
+    let lines = output.lines().collect::<Vec<&str>>();
+    assert_eq!(lines[0], "query,rank,x,y,z,distance");
+    assert!(lines[1].starts_with("0,0,0.625,0.75,0.5625,"));
+    assert!(lines[2].starts_with("1,0,0.125,0.25,0.0625,"));
+    assert_eq!(lines.len(), 3);
+    assert_eq!(from_input, output);
+    assert!(stats.contains("points: 8"));
+    assert!(stats.contains("max: 0.625 0.75 0.5625"));
+}
+
+#[test]
+fn query_radius_and_box_as_xyz() {
+    let input = fixture("cube_ascii.ply");
+    let output = stdout(&["query", "--input", &input, "--point", "0.125,0.25,0.0625", "--radius", "0.5", "--output-format", "xyz"]);
+    let mut output = output.lines().collect::<Vec<&str>>();
+    // the three neighbours at the same distance may come in any order
+    output[1..].sort();
+    assert_eq!(output, vec!["0.125 0.25 0.0625 0", "0.125 0.25 0.5625 0.5", "0.125 0.75 0.0625 0.5", "0.625 0.25 0.0625 0.5"]);
+    let output = stdout(&["query", "--input", &input, "--box", "0,0,0,0.5,0.5,1", "--output-format", "xyz"]);
+    let mut output = output.lines().collect::<Vec<&str>>();
+    output.sort();
+    assert_eq!(output, vec!["0.125 0.25 0.0625", "0.125 0.25 0.5625"]);
+}
+
+#[test]
+fn write_query_results_to_a_file() {
+    let path = temp_path("neighbours.csv");
+    stdout(&["query", "--input", &fixture("cube_f8.npy"), "--point", "0,0,0", "--k", "3", "--output", path.to_str().unwrap()]);
+    let output = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(output.lines().count(), 4);
+}
+
+#[test]
+fn query_points_read_from_a_csv_file_as_json_lines() {
+    let path = temp_path("queries.csv");
+    std::fs::write(&path, "x,y,z\n0.6,0.7,0.6\n0.1,0.2,0\n").unwrap();
+    let output = stdout(&["query", "--input", &fixture("cube_f8.npy"), "--queries", path.to_str().unwrap(), "--k", "2", "--output-format", "jsonl"]);
+    std::fs::remove_file(&path).unwrap();
+    let lines = output.lines().collect::<Vec<&str>>();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], r#"{"query":0,"rank":0,"x":0.625,"y":0.75,"z":0.5625,"distance":0.06731456008918132}"#);
+    assert!(lines[3].starts_with(r#"{"query":1,"rank":1,"#));
+}
+
+#[test]
+fn stream_results_of_points_piped_in() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_find_closest_points"))
+        .args(["query", "--input", &fixture("cube_ascii.pcd"), "--queries", "-", "--k", "1", "--output-format", "xyz"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    // each result is read back before the next point is written, so the results cannot wait for the end of the input
+    let mut line = String::new();
+    for (query, expected) in [("0.6 0.7 0.6\n", "0.625 0.75 0.5625 "), ("0.1 0.2 0\n", "0.125 0.25 0.0625 ")] {
+        stdin.write_all(query.as_bytes()).unwrap();
+        stdin.flush().unwrap();
+        line.clear();
+        stdout.read_line(&mut line).unwrap();
+        assert!(line.starts_with(expected), "{line}");
+    }
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn report_line_of_a_bad_query_point() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_find_closest_points"))
+        .args(["query", "--input", &fixture("cube_ascii.pcd"), "--queries", "-", "--k", "1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"0 0 0\n1 1\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 2);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("line 2"));
+}
+
+#[test]
+fn report_bad_arguments_and_files() {
+    let output = run(&["query", "--input", &fixture("cube_ascii.ply"), "--point", "0,0,0", "--k", "1", "--radius", "1"]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = run(&["query", "--input", &fixture("cube_ascii.ply"), "--point", "0,0", "--k", "1"]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = run(&["query", "--input", &fixture("cube_ascii.ply"), "--point", "0,0,0", "--queries", "-", "--k", "1"]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = run(&["stats", "--input", &fixture("cube.npz")]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--input-format"));
+    let output = run(&["stats", "--index", &fixture("cube_ascii.ply")]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not a saved kd-tree"));
+}
+
+#[test]
+fn bench_agrees_with_brute_force() {
+    let output = stdout(&["bench", "--points", "10000", "--queries", "50"]);
+    assert!(output.contains("the kd-tree and brute force found the same neighbours"));
+}
@@ -0,0 +1,62 @@
+use find_closest_points::closest_pair::{find_closest_pair, find_k_bichromatic_closest_pairs, find_k_closest_pairs};
+use find_closest_points::generate_random_points;
+use find_closest_points::kd_tree::KdTreeNode;
+use find_closest_points::point::Point;
+
+fn brute_force_pair_distances(first: &[Point], second: &[Point]) -> Vec<f64> {
+    let mut distances = first.iter()
+        .flat_map(|a| second.iter().map(move |b| a.distance_to(b)))
+        .collect::<Vec<f64>>();
+    distances.sort_by(f64::total_cmp);
+    distances
+}
+
+fn brute_force_self_pair_distances(points: &[Point]) -> Vec<f64> {
+    let mut distances = vec![];
+    for (i, a) in points.iter().enumerate() {
+        for b in &points[i + 1..] {
+            distances.push(a.distance_to(b));
+        }
+    }
+    distances.sort_by(f64::total_cmp);
+    distances
+}
+
+#[test]
+fn find_closest_pair_within_1000_points() {
+    let points = generate_random_points(1000);
+    let tree = Some(Box::new(KdTreeNode::construct_tree(points.clone())));
+    let output = find_closest_pair(&tree).unwrap().distance;
+
+    let expected = brute_force_self_pair_distances(&points)[0];
+
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn find_10_closest_pairs_within_1000_points() {
+    let points = generate_random_points(1000);
+    let tree = Some(Box::new(KdTreeNode::construct_tree(points.clone())));
+    let output = find_k_closest_pairs(&tree, 10).into_iter().map(|pair| pair.distance).collect::<Vec<f64>>();
+
+    let expected = brute_force_self_pair_distances(&points);
+
+    assert_eq!(output, &expected[0..10]);
+}
+
+#[test]
+fn find_10_bichromatic_closest_pairs_between_1000_and_2000_points() {
+    let first_points = generate_random_points(1000);
+    let second_points = generate_random_points(2000);
+    let first_tree = Some(Box::new(KdTreeNode::construct_tree(first_points.clone())));
+    let second_tree = Some(Box::new(KdTreeNode::construct_tree(second_points.clone())));
+    let output = find_k_bichromatic_closest_pairs(&first_tree, &second_tree, 10);
+
+    let expected = brute_force_pair_distances(&first_points, &second_points);
+
+    assert_eq!(output.iter().map(|pair| pair.distance).collect::<Vec<f64>>(), &expected[0..10]);
+    for pair in output {
+        assert!(first_points.contains(&pair.first));
+        assert!(second_points.contains(&pair.second));
+    }
+}
@@ -1,98 +1,94 @@
-use std::collections::BinaryHeap;
 use find_closest_points::{generate_random_points, NUM_OF_NEAREST_NEIGHBOURS};
-use find_closest_points::kd_tree::KdTreeNode;
-use find_closest_points::nearest_neighbour::{find_k_nearest_neighbours, Neighbour};
-use find_closest_points::point::{Dimension, Point};
+use find_closest_points::ball_tree::BallTree;
+use find_closest_points::bounding_box::BoundingBox;
+use find_closest_points::brute_force::BruteForce;
+use find_closest_points::kd_tree::KdTree;
+use find_closest_points::nearest_neighbour::NeighbourSearch;
+use find_closest_points::octree::Octree;
+use find_closest_points::point::Point;
+use find_closest_points::rtree::RTree;
+use find_closest_points::spatial_hash::SpatialHash;
+use find_closest_points::spatial_index::SpatialIndex;
+use find_closest_points::vp_tree::{Euclidean, VpTree};
+
+fn check_index<I: SpatialIndex>(points: &[Point], given_point: &Point) {
+    let index = I::build(points.to_vec());
+    let brute_force = BruteForce::construct_index(points.to_vec());
+    let name = std::any::type_name::<I>();
+
+    let output = index.nearest(given_point, NUM_OF_NEAREST_NEIGHBOURS);
+    let expected = brute_force.nearest(given_point, NUM_OF_NEAREST_NEIGHBOURS);
+    assert_eq!(output, expected, "nearest neighbours of {name}");
+
+    let output = index.within_radius(given_point, 0.1);
+    let expected = brute_force.within_radius(given_point, 0.1);
+    assert_eq!(output, expected, "neighbours within radius of {name}");
+
+    let corner = Point::random();
+    let bounding_box = BoundingBox::from_points([given_point, &corner]).unwrap();
+    let mut output = index.in_box(&bounding_box);
+    output.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)).then(a.z.total_cmp(&b.z)));
+    let mut expected = brute_force.in_box(&bounding_box);
+    expected.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)).then(a.z.total_cmp(&b.z)));
+    assert_eq!(output, expected, "points in box of {name}");
+
+    assert_eq!(index.len(), points.len(), "number of points of {name}");
+}
 
-fn get_nearest_neighbours_sorted_using_kd_tree(points: &Vec<Point>, given_point: &Point) -> Vec<Neighbour>{
-    let tree = KdTreeNode::construct_tree(points.clone());
-    let mut heap = BinaryHeap::new();
-    find_k_nearest_neighbours(&Some(Box::new(tree)), &given_point, &Dimension::X, &mut heap, NUM_OF_NEAREST_NEIGHBOURS);
-    let mut heap_vec = heap.into_iter().collect::<Vec<Neighbour>>();
-    heap_vec.sort();
-    heap_vec
+fn check_every_index(points: &[Point], given_point: &Point) {
+    check_index::<KdTree>(points, given_point);
+    check_index::<BallTree>(points, given_point);
+    check_index::<VpTree<Point, Euclidean>>(points, given_point);
+    check_index::<Octree>(points, given_point);
+    check_index::<RTree<Point>>(points, given_point);
+    check_index::<SpatialHash>(points, given_point);
+    check_index::<BruteForce>(points, given_point);
+}
+
+#[test]
+fn find_10_closest_points_within_no_points() {
+    check_every_index(&[], &Point::random());
 }
 
 #[test]
 fn find_10_closest_points_within_10_points() {
     let points = generate_random_points(10);
-    let given_point = Point::random();
-    let output = get_nearest_neighbours_sorted_using_kd_tree(&points, &given_point);
-
-    let mut expected = points.into_iter().map(|p| Neighbour { distance: given_point.distance_to(&p), point: p }).collect::<Vec<Neighbour>>();
-    expected.sort();
-
-    assert_eq!(output, expected);
+    check_every_index(&points, &Point::random());
 }
 
 #[test]
 fn find_10_closest_points_within_20_points() {
     let points = generate_random_points(20);
-    let given_point = Point::random();
-    let output = get_nearest_neighbours_sorted_using_kd_tree(&points, &given_point);
-
-    let mut expected = points.into_iter().map(|p| Neighbour { distance: given_point.distance_to(&p), point: p }).collect::<Vec<Neighbour>>();
-    expected.sort();
-
-    assert_eq!(output, &expected[0..10]);
+    check_every_index(&points, &Point::random());
 }
 
 #[test]
 fn find_10_closest_points_within_50_points() {
     let points = generate_random_points(50);
-    let given_point = Point::random();
-    let output = get_nearest_neighbours_sorted_using_kd_tree(&points, &given_point);
-
-    let mut expected = points.into_iter().map(|p| Neighbour { distance: given_point.distance_to(&p), point: p }).collect::<Vec<Neighbour>>();
-    expected.sort();
-
-    assert_eq!(output, &expected[0..10]);
+    check_every_index(&points, &Point::random());
 }
 
 #[test]
 fn find_10_closest_points_within_1000_points() {
     let points = generate_random_points(1000);
-    let given_point = Point::random();
-    let output = get_nearest_neighbours_sorted_using_kd_tree(&points, &given_point);
-
-    let mut expected = points.into_iter().map(|p| Neighbour { distance: given_point.distance_to(&p), point: p }).collect::<Vec<Neighbour>>();
-    expected.sort();
-
-    assert_eq!(output, &expected[0..10]);
+    check_every_index(&points, &Point::random());
 }
 
 #[test]
 fn find_10_closest_points_within_10000_points() {
     let points = generate_random_points(10000);
-    let given_point = Point::random();
-    let output = get_nearest_neighbours_sorted_using_kd_tree(&points, &given_point);
-
-    let mut expected = points.into_iter().map(|p| Neighbour { distance: given_point.distance_to(&p), point: p }).collect::<Vec<Neighbour>>();
-    expected.sort();
-
-    assert_eq!(output, &expected[0..10]);
+    check_every_index(&points, &Point::random());
 }
 
 #[test]
 fn find_10_closest_points_within_1000000_points() {
     let points = generate_random_points(1_000_000);
-    let given_point = Point::random();
-    let output = get_nearest_neighbours_sorted_using_kd_tree(&points, &given_point);
-
-    let mut expected = points.into_iter().map(|p| Neighbour { distance: given_point.distance_to(&p), point: p }).collect::<Vec<Neighbour>>();
-    expected.sort();
-
-    assert_eq!(output, &expected[0..10]);
+    check_every_index(&points, &Point::random());
 }
 
 #[test]
 fn find_10_closest_points_within_10_000_000_points() {
+    // building every index over ten million points takes too long, so only the kd-tree is checked at this size
     let points = generate_random_points(10_000_000);
-    let given_point = Point::random();
-    let output = get_nearest_neighbours_sorted_using_kd_tree(&points, &given_point);
-
-    let mut expected = points.into_iter().map(|p| Neighbour { distance: given_point.distance_to(&p), point: p }).collect::<Vec<Neighbour>>();
-    expected.sort();
-
-    assert_eq!(output, &expected[0..10]);
-}
\ No newline at end of file
+    check_index::<KdTree>(&points, &Point::random());
+}
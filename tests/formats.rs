@@ -0,0 +1,195 @@
+use std::fs::File;
+use std::io::BufReader;
+
+use find_closest_points::{generate_random_points, NUM_OF_NEAREST_NEIGHBOURS};
+use find_closest_points::formats::las::read_las;
+use find_closest_points::formats::npy::{read_npy_points, write_npy_neighbour_distances, write_npy_neighbour_indices};
+use find_closest_points::formats::pcd::{PcdCloud, PcdEncoding, read_pcd, write_pcd};
+use find_closest_points::formats::ply::{PlyCloud, PlyEncoding, read_ply, write_ply};
+use find_closest_points::formats::text::{self, TextFormat, TextPoints};
+use find_closest_points::kd_tree::KdTree;
+use find_closest_points::nearest_neighbour::NeighbourSearch;
+use find_closest_points::point::Point;
+
+fn fixture(name: &str) -> BufReader<File> {
+    BufReader::new(File::open(format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"))).unwrap())
+}
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("find_closest_points_{}_{name}", std::process::id()))
+}
+
+#[test]
+fn search_points_read_back_from_csv_and_xyz() {
+    let points = generate_random_points(10_000);
+    let given_point = Point::random();
+    let expected = KdTree::construct_tree(points.clone()).nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+    for (name, format) in [("points.csv", TextFormat::csv()), ("points.xyz", TextFormat::xyz())] {
+        let path = temp_path(name);
+        text::write_points(File::create(&path).unwrap(), &points, &format).unwrap();
+        let streamed = TextPoints::new(BufReader::new(File::open(&path).unwrap()), &format).unwrap()
+            .collect::<Result<Vec<Point>, _>>()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(streamed, points);
+        let output = KdTree::construct_tree(streamed).nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+        assert_eq!(output, expected);
+    }
+}
+
+#[test]
+fn read_ply_fixtures_in_every_encoding() {
+    let ascii = read_ply(fixture("cube_ascii.ply")).unwrap();
+    let little_endian = read_ply(fixture("cube_binary_little_endian.ply")).unwrap();
+    let big_endian = read_ply(fixture("cube_binary_big_endian.ply")).unwrap();
+    assert_eq!(ascii.points.len(), 8);
+    assert_eq!(ascii.properties.len(), 10);
+    assert_eq!(ascii.points[7].point, Point { x: 0.625, y: 0.75, z: 0.5625 });
+    // nx, ny, nz, red, green, blue and intensity
+    assert_eq!(ascii.points[7].payload, vec![0.0, 0.0, 1.0, 224.0, 31.0, 49.0, 3.75]);
+    assert_eq!(little_endian.points, ascii.points);
+    assert_eq!(big_endian.points, ascii.points);
+    assert_eq!(little_endian.encoding, PlyEncoding::BinaryLittleEndian);
+    assert_eq!(big_endian.encoding, PlyEncoding::BinaryBigEndian);
+}
+
+#[test]
+fn round_trip_ply_fixtures() {
+    for name in ["cube_ascii.ply", "cube_binary_little_endian.ply", "cube_binary_big_endian.ply"] {
+        let cloud = read_ply(fixture(name)).unwrap();
+        let mut bytes = vec![];
+        write_ply(&mut bytes, &cloud).unwrap();
+        let output = read_ply(bytes.as_slice()).unwrap();
+        assert_eq!(output, cloud, "{name}");
+    }
+}
+
+#[test]
+fn write_nearest_neighbours_with_payloads_to_ply() {
+    let cloud = read_ply(fixture("cube_binary_little_endian.ply")).unwrap();
+    let tree = KdTree::construct_tree(cloud.positions());
+    let neighbours = tree.nearest(&Point { x: 0.6, y: 0.7, z: 0.6 }, 2);
+    let mut bytes = vec![];
+    write_ply(&mut bytes, &cloud.select(&neighbours)).unwrap();
+    let output = read_ply(bytes.as_slice()).unwrap();
+    assert_eq!(output.points.len(), 2);
+    assert_eq!(output.points[0].point, cloud.points[7].point);
+    assert_eq!(output.points[0].payload[..7], cloud.points[7].payload[..]);
+    assert_eq!(output.points[0].payload[7], neighbours[0].distance);
+}
+
+#[test]
+fn search_points_read_back_from_ply() {
+    let points = generate_random_points(10_000);
+    let mut bytes = vec![];
+    write_ply(&mut bytes, &PlyCloud::from_points(points.clone(), PlyEncoding::BinaryBigEndian)).unwrap();
+    let output = read_ply(bytes.as_slice()).unwrap().positions();
+    assert_eq!(output, points);
+}
+
+#[test]
+fn read_pcd_fixtures_in_every_encoding() {
+    let ascii = read_pcd(fixture("cube_ascii.pcd")).unwrap();
+    let binary = read_pcd(fixture("cube_binary.pcd")).unwrap();
+    let compressed = read_pcd(fixture("cube_binary_compressed.pcd")).unwrap();
+    assert_eq!(ascii.points.len(), 8);
+    assert_eq!(ascii.fields.len(), 6);
+    assert_eq!(ascii.points[7].point, Point { x: 0.625, y: 0.75, z: 0.5625 });
+    // the three numbers of normal, then intensity and label
+    assert_eq!(ascii.points[7].payload, vec![0.0, 0.0, 1.0, 3.75, 7007.0]);
+    assert_eq!(binary.points, ascii.points);
+    assert_eq!(compressed.points, ascii.points);
+    assert_eq!(binary.encoding, PcdEncoding::Binary);
+    assert_eq!(compressed.encoding, PcdEncoding::BinaryCompressed);
+}
+
+#[test]
+fn round_trip_pcd_fixtures() {
+    for name in ["cube_ascii.pcd", "cube_binary.pcd", "cube_binary_compressed.pcd"] {
+        let cloud = read_pcd(fixture(name)).unwrap();
+        let mut bytes = vec![];
+        write_pcd(&mut bytes, &cloud).unwrap();
+        let output = read_pcd(bytes.as_slice()).unwrap();
+        assert_eq!(output, cloud, "{name}");
+    }
+}
+
+#[test]
+fn write_nearest_neighbours_with_payloads_to_pcd() {
+    let cloud = read_pcd(fixture("cube_binary_compressed.pcd")).unwrap();
+    let tree = KdTree::construct_tree(cloud.positions());
+    let neighbours = tree.nearest(&Point { x: 0.6, y: 0.7, z: 0.6 }, 2);
+    let mut bytes = vec![];
+    write_pcd(&mut bytes, &cloud.select(&neighbours)).unwrap();
+    let output = read_pcd(bytes.as_slice()).unwrap();
+    assert_eq!(output.points.len(), 2);
+    assert_eq!(output.points[0].point, cloud.points[7].point);
+    assert_eq!(output.points[0].payload[..5], cloud.points[7].payload[..]);
+    assert_eq!(output.points[0].payload[5], neighbours[0].distance);
+}
+
+#[test]
+fn search_points_read_back_from_pcd() {
+    let points = generate_random_points(10_000);
+    for encoding in [PcdEncoding::Binary, PcdEncoding::BinaryCompressed] {
+        let mut bytes = vec![];
+        write_pcd(&mut bytes, &PcdCloud::from_points(points.clone(), encoding)).unwrap();
+        let output = read_pcd(bytes.as_slice()).unwrap().positions();
+        assert_eq!(output, points);
+    }
+}
+
+#[test]
+fn read_las_fixtures_of_legacy_and_extended_point_formats() {
+    let legacy = read_las(fixture("cube_1_2_format_3.las")).unwrap();
+    let extended = read_las(fixture("cube_1_4_format_7.las")).unwrap();
+    assert_eq!((legacy.version, legacy.point_format), ((1, 2), 3));
+    assert_eq!((extended.version, extended.point_format), ((1, 4), 7));
+    assert_eq!(legacy.points.len(), 8);
+    assert_eq!(legacy.points[7].point, Point { x: 625.0 * 0.001 + 100.0, y: 750.0 * 0.001 + 200.0, z: 562.0 * 0.001 + 10.0 });
+    assert_eq!(extended.positions(), legacy.positions());
+    assert_eq!(legacy.payload_names(), extended.payload_names());
+    // intensity, return number, number of returns and classification
+    assert_eq!(legacy.points[7].payload[..4], [700.0, 2.0, 2.0, 6.0]);
+    // the scan angle is stored in whole degrees by format 3 but in steps of 0.006 degrees by format 7
+    assert!((legacy.points[7].payload[4] - extended.points[7].payload[4]).abs() < 0.006);
+    assert_eq!(legacy.points[7].payload[5..], extended.points[7].payload[5..]);
+}
+
+#[test]
+fn search_points_read_from_las() {
+    let cloud = read_las(fixture("cube_1_4_format_7.las")).unwrap();
+    let tree = KdTree::construct_tree(cloud.positions());
+    let output = tree.nearest(&Point { x: 100.6, y: 200.7, z: 10.6 }, 1);
+    assert_eq!(output[0].point, cloud.points[7].point);
+}
+
+#[test]
+fn read_npy_fixtures_in_c_and_fortran_order() {
+    let c_order = read_npy_points(fixture("cube_f8.npy")).unwrap();
+    let fortran_order = read_npy_points(fixture("cube_f4_fortran.npy")).unwrap();
+    assert_eq!(c_order.len(), 8);
+    assert_eq!(c_order[6], Point { x: 0.125, y: 0.75, z: 0.5625 });
+    assert_eq!(fortran_order, c_order);
+}
+
+#[test]
+fn write_neighbours_of_npy_points_to_npy() {
+    let points = read_npy_points(fixture("cube_f8.npy")).unwrap();
+    let tree = KdTree::construct_tree(points.clone());
+    let queries = [Point { x: 0.6, y: 0.7, z: 0.6 }, Point { x: 0.1, y: 0.2, z: 0.0 }];
+    let neighbours = queries.iter().map(|query| tree.nearest(query, 2)).collect::<Vec<_>>();
+    let mut indices = vec![];
+    write_npy_neighbour_indices(&mut indices, &points, &neighbours).unwrap();
+    let mut distances = vec![];
+    write_npy_neighbour_distances(&mut distances, &neighbours).unwrap();
+    let data_start = |bytes: &[u8]| 10 + u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    let output = indices[data_start(&indices)..].chunks(8).map(|bytes| i64::from_le_bytes(bytes.try_into().unwrap())).collect::<Vec<i64>>();
+    assert_eq!(output[0], 7);
+    assert_eq!(output[2], 0);
+    for (i, index) in output.iter().enumerate() {
+        assert_eq!(points[*index as usize], neighbours[i / 2][i % 2].point);
+    }
+    let output = distances[data_start(&distances)..].chunks(8).map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap())).collect::<Vec<f64>>();
+    assert_eq!(output, neighbours.iter().flatten().map(|neighbour| neighbour.distance).collect::<Vec<f64>>());
+}
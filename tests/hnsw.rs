@@ -0,0 +1,72 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use find_closest_points::NUM_OF_NEAREST_NEIGHBOURS;
+use find_closest_points::hnsw::{Hnsw, HnswParams};
+use find_closest_points::point::Point;
+use find_closest_points::vp_tree::{Euclidean, Metric};
+
+/// Seed of the generators that the items, the queries and the layers of the indexes are drawn from,
+/// so that every run checks the same graphs.
+const SEED: u64 = 42;
+
+/// Returns n vectors with the given number of dimensions and coordinates between 0 and 1, drawn from `rng`.
+fn seeded_vectors(rng: &mut StdRng, n: usize, dimensions: usize) -> Vec<Vec<f64>> {
+    (0..n).map(|_| (0..dimensions).map(|_| rng.gen_range(0.0..=1.0)).collect()).collect()
+}
+
+/// Returns n points in the unit cube, drawn from `rng`.
+fn seeded_points(rng: &mut StdRng, n: usize) -> Vec<Point> {
+    (0..n).map(|_| Point::new(rng.gen(), rng.gen(), rng.gen())).collect()
+}
+
+/// Returns the share of the true k nearest neighbours, found by brute force, that the index reports for the queries.
+fn recall<T, M: Metric<T>>(hnsw: &Hnsw<T, M>, items: &[T], queries: &[T], k: usize) -> f64 {
+    let mut found = 0;
+    for query in queries {
+        let mut expected = items.iter().enumerate()
+            .map(|(id, item)| (hnsw.metric().distance(query, item), id))
+            .collect::<Vec<(f64, usize)>>();
+        expected.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let expected = expected[0..k].iter().map(|(_, id)| *id).collect::<Vec<usize>>();
+
+        let output = hnsw.nearest(query, k);
+        found += output.iter().filter(|neighbour| expected.contains(&neighbour.point)).count();
+    }
+    found as f64 / (queries.len() * k) as f64
+}
+
+#[test]
+fn recall_on_128_dimensional_vectors() {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let vectors = seeded_vectors(&mut rng, 1000, 128);
+    let queries = seeded_vectors(&mut rng, 50, 128);
+    let params = HnswParams { m: 16, ef_construction: 64, ef_search: 64 };
+    let hnsw = Hnsw::construct_index_with_seed(vectors.clone(), Euclidean, params, SEED);
+    let output = recall(&hnsw, &vectors, &queries, NUM_OF_NEAREST_NEIGHBOURS);
+    assert!(output >= 0.9, "recall was {}", output);
+}
+
+#[test]
+fn recall_grows_with_ef_search() {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let vectors = seeded_vectors(&mut rng, 1000, 128);
+    let queries = seeded_vectors(&mut rng, 50, 128);
+    let params = HnswParams { m: 8, ef_construction: 50, ef_search: NUM_OF_NEAREST_NEIGHBOURS };
+    let mut hnsw = Hnsw::construct_index_with_seed(vectors.clone(), Euclidean, params, SEED);
+    let low = recall(&hnsw, &vectors, &queries, NUM_OF_NEAREST_NEIGHBOURS);
+    hnsw.set_ef_search(200);
+    let high = recall(&hnsw, &vectors, &queries, NUM_OF_NEAREST_NEIGHBOURS);
+    assert!(high > low && high >= 0.9, "recall went from {} to {}", low, high);
+}
+
+#[test]
+fn recall_on_points() {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let points = seeded_points(&mut rng, 2000);
+    let queries = seeded_points(&mut rng, 100);
+    let params = HnswParams { m: 8, ef_construction: 50, ef_search: 50 };
+    let hnsw = Hnsw::construct_index_with_seed(points.clone(), Euclidean, params, SEED);
+    let output = recall::<Point, _>(&hnsw, &points, &queries, NUM_OF_NEAREST_NEIGHBOURS);
+    assert!(output >= 0.95, "recall was {}", output);
+}
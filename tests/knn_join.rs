@@ -0,0 +1,39 @@
+use std::collections::BinaryHeap;
+
+use find_closest_points::{generate_random_points, NUM_OF_NEAREST_NEIGHBOURS};
+use find_closest_points::brute_force::BruteForce;
+use find_closest_points::kd_tree::KdTreeNode;
+use find_closest_points::knn_join::find_k_nearest_neighbours_join;
+use find_closest_points::nearest_neighbour::{find_k_nearest_neighbours, NeighbourSearch};
+use find_closest_points::point::{Dimension, Point};
+
+#[test]
+fn join_1000_queries_against_10000_points() {
+    let points = generate_random_points(10_000);
+    let queries = generate_random_points(1000);
+    let tree = Some(Box::new(KdTreeNode::construct_tree(points.clone())));
+    let output = find_k_nearest_neighbours_join(&queries, &tree, NUM_OF_NEAREST_NEIGHBOURS);
+
+    assert_eq!(output.len(), queries.len());
+    let brute_force = BruteForce::construct_index(points);
+    for (query, neighbours) in queries.iter().zip(output) {
+        let expected = brute_force.nearest(query, NUM_OF_NEAREST_NEIGHBOURS);
+        assert_eq!(neighbours, expected);
+    }
+}
+
+#[test]
+fn join_matches_single_point_search() {
+    let points = generate_random_points(100_000);
+    let queries = generate_random_points(500);
+    let tree = Some(Box::new(KdTreeNode::construct_tree(points)));
+    let output = find_k_nearest_neighbours_join(&queries, &tree, NUM_OF_NEAREST_NEIGHBOURS);
+
+    for (query, neighbours) in queries.iter().zip(output) {
+        let mut heap = BinaryHeap::new();
+        find_k_nearest_neighbours(&tree, query, &Dimension::X, &mut heap, NUM_OF_NEAREST_NEIGHBOURS);
+        let expected = heap.into_sorted_vec();
+        assert_eq!(neighbours.iter().map(|n| &n.point).collect::<Vec<&Point>>(),
+                   expected.iter().map(|n| &n.point).collect::<Vec<&Point>>());
+    }
+}
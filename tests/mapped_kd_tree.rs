@@ -0,0 +1,51 @@
+#![cfg(feature = "mmap")]
+
+use std::fs;
+use std::path::PathBuf;
+
+use find_closest_points::{generate_random_points, NUM_OF_NEAREST_NEIGHBOURS};
+use find_closest_points::kd_tree::KdTree;
+use find_closest_points::mapped_kd_tree::MappedKdTree;
+use find_closest_points::nearest_neighbour::NeighbourSearch;
+use find_closest_points::point::Point;
+use find_closest_points::scalar::Native;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("find_closest_points_{}_{name}.kdtree", std::process::id()))
+}
+
+#[test]
+fn mapped_tree_finds_the_same_neighbours_within_1000000_points() {
+    let path = temp_path("1000000");
+    let tree = KdTree::construct_tree(generate_random_points(1_000_000));
+    tree.save_mapped(&path).unwrap();
+    let mapped = MappedKdTree::open(&path).unwrap();
+    assert_eq!(mapped.len(), 1_000_000);
+    for _ in 0..100 {
+        let given_point = Point::random();
+        let output = mapped.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+        let expected = tree.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+        assert_eq!(output.iter().map(|n| (n.distance, &n.point)).collect::<Vec<_>>(),
+                   expected.iter().map(|n| (n.distance, &n.point)).collect::<Vec<_>>());
+
+        let output = mapped.within_radius(&given_point, 0.02);
+        let expected = tree.within_radius(&given_point, 0.02);
+        assert_eq!(output.len(), expected.len());
+        assert_eq!(output, expected);
+    }
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn mapped_f32_tree_finds_the_same_neighbours() {
+    let path = temp_path("f32");
+    let points = generate_random_points(10_000).iter().map(Point::cast).collect::<Vec<Point<f32>>>();
+    let tree = KdTree::construct_tree(points);
+    tree.save_mapped(&path).unwrap();
+    let mapped = MappedKdTree::<f32>::open(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    let given_point = Point::<f32>::random();
+    let output = mapped.nearest_with::<Native>(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+    let expected = tree.nearest_with::<Native>(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+    assert_eq!(output.iter().map(|n| &n.point).collect::<Vec<_>>(), expected.iter().map(|n| &n.point).collect::<Vec<_>>());
+}
@@ -0,0 +1,112 @@
+use find_closest_points::{generate_random_points, NUM_OF_NEAREST_NEIGHBOURS};
+use find_closest_points::ball_tree::BallTree;
+use find_closest_points::brute_force::BruteForce;
+use find_closest_points::kd_tree::KdTreeNode;
+use find_closest_points::octree::Octree;
+use find_closest_points::nearest_neighbour::NeighbourSearch;
+use find_closest_points::point::Point;
+use find_closest_points::rtree::RTree;
+use find_closest_points::spatial_hash::SpatialHash;
+use find_closest_points::vp_tree::{Euclidean, VpTree};
+
+fn check_nearest(index: &impl NeighbourSearch, points: &[Point]) {
+    let brute_force = BruteForce::construct_index(points.to_vec());
+    for _ in 0..10 {
+        let given_point = Point::random();
+        let output = index.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+
+        let expected = brute_force.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+
+        assert_eq!(output, expected);
+    }
+}
+
+fn check_within_radius(index: &impl NeighbourSearch, points: &[Point]) {
+    let brute_force = BruteForce::construct_index(points.to_vec());
+    for radius in [0.0, 0.01, 0.05, 0.2] {
+        let given_point = Point::random();
+        let output = index.within_radius(&given_point, radius);
+
+        let expected = brute_force.within_radius(&given_point, radius);
+
+        assert_eq!(output, expected);
+    }
+}
+
+#[test]
+fn kd_tree_nearest_within_10000_points() {
+    let points = generate_random_points(10_000);
+    check_nearest(&KdTreeNode::construct_tree(points.clone()), &points);
+}
+
+#[test]
+fn kd_tree_within_radius_within_10000_points() {
+    let points = generate_random_points(10_000);
+    check_within_radius(&KdTreeNode::construct_tree(points.clone()), &points);
+}
+
+#[test]
+fn ball_tree_nearest_within_10000_points() {
+    let points = generate_random_points(10_000);
+    check_nearest(&BallTree::construct_tree(points.clone()), &points);
+}
+
+#[test]
+fn ball_tree_within_radius_within_10000_points() {
+    let points = generate_random_points(10_000);
+    check_within_radius(&BallTree::construct_tree(points.clone()), &points);
+}
+
+#[test]
+fn switch_index_type_behind_trait_object() {
+    let points = generate_random_points(1000);
+    let indexes: Vec<Box<dyn NeighbourSearch>> = vec![
+        Box::new(KdTreeNode::construct_tree(points.clone())),
+        Box::new(BallTree::construct_tree(points.clone())),
+        Box::new(VpTree::construct_tree(points.clone(), Euclidean)),
+        Box::new(Octree::construct_tree(points.clone())),
+        Box::new(RTree::from_points(points.clone())),
+        Box::new(SpatialHash::from_points(points.clone(), 0.05)),
+    ];
+    let given_point = Point::random();
+    let expected = BruteForce::construct_index(points).nearest(&given_point, 3);
+    for index in indexes {
+        assert_eq!(index.nearest(&given_point, 3), expected);
+    }
+}
+
+#[test]
+fn octree_nearest_within_10000_points() {
+    let points = generate_random_points(10_000);
+    check_nearest(&Octree::construct_tree(points.clone()), &points);
+}
+
+#[test]
+fn octree_within_radius_within_10000_points() {
+    let points = generate_random_points(10_000);
+    check_within_radius(&Octree::construct_tree(points.clone()), &points);
+}
+
+#[test]
+fn rtree_nearest_within_10000_points() {
+    let points = generate_random_points(10_000);
+    check_nearest(&RTree::from_points(points.clone()), &points);
+}
+
+#[test]
+fn rtree_within_radius_within_10000_points() {
+    let points = generate_random_points(10_000);
+    check_within_radius(&RTree::from_points(points.clone()), &points);
+}
+
+#[test]
+fn spatial_hash_nearest_within_10000_points() {
+    let points = generate_random_points(10_000);
+    check_nearest(&SpatialHash::from_points(points.clone(), 0.05), &points);
+}
+
+#[test]
+fn spatial_hash_within_radius_within_10000_points() {
+    let points = generate_random_points(10_000);
+    check_within_radius(&SpatialHash::from_points(points.clone(), 0.05), &points);
+}
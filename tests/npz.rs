@@ -0,0 +1,25 @@
+#![cfg(feature = "npz")]
+
+use std::fs::File;
+
+use find_closest_points::formats::npy::{read_npy_points, read_npz_points};
+use find_closest_points::formats::PointFileError;
+
+fn fixture(name: &str) -> File {
+    File::open(format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"))).unwrap()
+}
+
+#[test]
+fn read_named_and_positional_arrays_of_compressed_npz() {
+    let expected = read_npy_points(fixture("cube_f8.npy")).unwrap();
+    let named = read_npz_points(fixture("cube.npz"), "points").unwrap();
+    let positional = read_npz_points(fixture("cube.npz"), "arr_0").unwrap();
+    assert_eq!(named, expected);
+    assert_eq!(positional, expected);
+}
+
+#[test]
+fn report_missing_array_with_the_names_there_are() {
+    let output = read_npz_points(fixture("cube.npz"), "normals");
+    assert!(matches!(output, Err(PointFileError::Format(message)) if message.contains("arr_0") && message.contains("points")));
+}
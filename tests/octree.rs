@@ -0,0 +1,35 @@
+use find_closest_points::bounding_box::BoundingBox;
+use find_closest_points::generate_random_points;
+use find_closest_points::octree::Octree;
+use find_closest_points::point::Point;
+
+fn sort_points(points: &mut [Point]) {
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)).then(a.z.total_cmp(&b.z)));
+}
+
+#[test]
+fn points_in_box_within_10000_points() {
+    let points = generate_random_points(10_000);
+    let octree = Octree::construct_tree(points.clone());
+    for _ in 0..10 {
+        let bounding_box = BoundingBox::from_points(&[Point::random(), Point::random()]).unwrap();
+        let mut output = octree.in_box(&bounding_box);
+        sort_points(&mut output);
+
+        let mut expected = points.iter().filter(|p| bounding_box.contains(p)).cloned().collect::<Vec<Point>>();
+        sort_points(&mut expected);
+
+        assert_eq!(output, expected);
+    }
+}
+
+#[test]
+fn level_of_detail_covers_every_point() {
+    let points = generate_random_points(10_000);
+    let octree = Octree::construct_tree(points.clone());
+    for depth in 0..6 {
+        let cells = octree.level_of_detail(depth).collect::<Vec<_>>();
+        assert_eq!(cells.iter().map(|cell| cell.count).sum::<usize>(), points.len());
+        assert!(cells.iter().all(|cell| cell.depth <= depth && cell.bounds.contains(&cell.centroid)));
+    }
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "mmap")]
+
+use std::fs;
+use std::mem::size_of;
+
+use find_closest_points::{generate_random_points, NUM_OF_NEAREST_NEIGHBOURS};
+use find_closest_points::brute_force::BruteForce;
+use find_closest_points::kd_tree::KdTreeNode;
+use find_closest_points::mapped_kd_tree::MappedKdTree;
+use find_closest_points::nearest_neighbour::NeighbourSearch;
+use find_closest_points::out_of_core::build_mapped_tree;
+use find_closest_points::point::Point;
+
+#[test]
+fn build_200000_points_with_room_for_1000() {
+    // a budget of a thousand nodes makes the build partition the points on disk several levels deep,
+    // as it would for a dataset many times larger than memory
+    let dir = std::env::temp_dir().join(format!("find_closest_points_{}_out_of_core", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("tree");
+    let points = generate_random_points(200_000);
+    let output = build_mapped_tree(points.clone(), &path, &dir, 1000 * size_of::<KdTreeNode>()).unwrap();
+    let tree = MappedKdTree::open(&path).unwrap();
+    let remaining = fs::read_dir(&dir).unwrap().count();
+    assert_eq!(output, 200_000);
+    assert_eq!(remaining, 1, "every chunk file is removed");
+
+    let brute_force = BruteForce::construct_index(points);
+    for _ in 0..20 {
+        let given_point = Point::random();
+        let output = tree.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+        let expected = brute_force.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+        assert_eq!(output, expected);
+
+        let output = tree.within_radius(&given_point, 0.05);
+        let expected = brute_force.within_radius(&given_point, 0.05);
+        assert_eq!(output, expected);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
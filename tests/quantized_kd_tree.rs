@@ -0,0 +1,51 @@
+use find_closest_points::{generate_random_points, NUM_OF_NEAREST_NEIGHBOURS};
+use find_closest_points::brute_force::BruteForce;
+use find_closest_points::nearest_neighbour::NeighbourSearch;
+use find_closest_points::point::Point;
+use find_closest_points::quantized_kd_tree::QuantizedKdTree;
+
+#[test]
+fn nearest_neighbours_have_exact_distances() {
+    let points = generate_random_points(100_000);
+    let tree = QuantizedKdTree::construct_tree(points.clone());
+    let given_point = Point::random();
+    for neighbour in tree.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS) {
+        assert_eq!(neighbour.distance, given_point.distance_to(&neighbour.point));
+        assert!(points.contains(&neighbour.point));
+    }
+}
+
+#[test]
+fn recall_of_10_nearest_neighbours_within_100000_points() {
+    let points = generate_random_points(100_000);
+    let tree = QuantizedKdTree::construct_tree(points.clone());
+    let brute_force = BruteForce::construct_index(points);
+    let mut found = 0;
+    for _ in 0..100 {
+        let given_point = Point::random();
+        let expected = brute_force.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+        let output = tree.nearest_with_candidates(&given_point, NUM_OF_NEAREST_NEIGHBOURS, 2 * NUM_OF_NEAREST_NEIGHBOURS);
+        found += output.iter().filter(|n| expected.iter().any(|e| e.point == n.point)).count();
+    }
+    let recall = found as f64 / (100 * NUM_OF_NEAREST_NEIGHBOURS) as f64;
+    assert!(recall >= 0.99, "recall was {recall}");
+}
+
+#[test]
+fn within_radius_is_exact() {
+    let points = generate_random_points(100_000);
+    let tree = QuantizedKdTree::construct_tree(points.clone());
+    let brute_force = BruteForce::construct_index(points);
+    let given_point = Point::random();
+    let output = tree.within_radius(&given_point, 0.05);
+    let expected = brute_force.within_radius(&given_point, 0.05);
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn quantized_points_take_less_memory_than_points() {
+    let points = generate_random_points(100_000);
+    let tree = QuantizedKdTree::construct_tree(points);
+    assert_eq!(tree.len(), 100_000);
+    assert!(tree.memory_usage() < tree.len() * std::mem::size_of::<Point>() / 2);
+}
@@ -0,0 +1,93 @@
+use rand::{Rng, thread_rng};
+
+use find_closest_points::bounding_box::BoundingBox;
+use find_closest_points::point::Point;
+use find_closest_points::rtree::{RTree, RTreeChildren, RTreeEntry, RTreeNode};
+
+fn generate_random_boxes(n: usize) -> Vec<RTreeEntry<usize>> {
+    let mut rng = thread_rng();
+    (0..n).map(|i| {
+        let min = Point::random();
+        let max = Point { x: min.x + rng.gen_range(0.0..0.05), y: min.y + rng.gen_range(0.0..0.05), z: min.z + rng.gen_range(0.0..0.05) };
+        RTreeEntry::new(BoundingBox::new(min, max), i)
+    }).collect()
+}
+
+fn inserted_tree(entries: &[RTreeEntry<usize>]) -> RTree<usize> {
+    let mut tree = RTree::new();
+    for entry in entries {
+        tree.insert(entry.clone());
+    }
+    tree
+}
+
+fn items(entries: Vec<&RTreeEntry<usize>>) -> Vec<usize> {
+    let mut items = entries.into_iter().map(|entry| entry.item).collect::<Vec<usize>>();
+    items.sort();
+    items
+}
+
+/// Checks that every node covers its children and holds at most `max_entries` of them, and returns the depth of its leaves.
+fn check_node(node: &RTreeNode<usize>, max_entries: usize) -> usize {
+    assert!(node.len() <= max_entries);
+    match &node.children {
+        RTreeChildren::Leaf(entries) => {
+            assert!(entries.iter().all(|entry| node.bounds.contains_box(&entry.bounds)));
+            0
+        }
+        RTreeChildren::Internal(children) => {
+            assert!(children.iter().all(|child| node.bounds.contains_box(&child.bounds)));
+            let depths = children.iter().map(|child| check_node(child, max_entries)).collect::<Vec<usize>>();
+            assert!(depths.iter().all(|depth| *depth == depths[0]));
+            depths[0] + 1
+        }
+    }
+}
+
+#[test]
+fn bulk_loaded_and_inserted_trees_are_balanced() {
+    let entries = generate_random_boxes(5000);
+    for tree in [RTree::bulk_load(entries.clone()), inserted_tree(&entries)] {
+        assert_eq!(tree.len(), entries.len());
+        let root = tree.root.as_ref().unwrap();
+        assert_eq!(check_node(root, 16), root.height());
+    }
+}
+
+#[test]
+fn box_queries_within_5000_boxes() {
+    let entries = generate_random_boxes(5000);
+    for tree in [RTree::bulk_load(entries.clone()), inserted_tree(&entries)] {
+        for _ in 0..10 {
+            let query = BoundingBox::from_points(&[Point::random(), Point::random()]).unwrap();
+            let expected = entries.iter().filter(|entry| entry.bounds.intersects(&query)).map(|entry| entry.item).collect::<Vec<usize>>();
+            assert_eq!(items(tree.intersecting(&query)), expected);
+
+            let expected = entries.iter().filter(|entry| query.contains_box(&entry.bounds)).map(|entry| entry.item).collect::<Vec<usize>>();
+            assert_eq!(items(tree.contained_in(&query)), expected);
+
+            let given_point = Point::random();
+            let expected = entries.iter().filter(|entry| entry.bounds.contains(&given_point)).map(|entry| entry.item).collect::<Vec<usize>>();
+            assert_eq!(items(tree.containing(&given_point)), expected);
+        }
+    }
+}
+
+#[test]
+fn nearest_boxes_within_5000_boxes() {
+    let entries = generate_random_boxes(5000);
+    for tree in [RTree::bulk_load(entries.clone()), inserted_tree(&entries)] {
+        for _ in 0..10 {
+            let given_point = Point::random();
+            let mut expected = entries.iter().map(|entry| entry.bounds.distance_to_point(&given_point)).collect::<Vec<f64>>();
+            expected.sort_by(f64::total_cmp);
+
+            let output = tree.nearest(&given_point, 10).into_iter().map(|n| n.distance).collect::<Vec<f64>>();
+            assert_eq!(output, &expected[0..10]);
+
+            let output = tree.within_radius(&given_point, 0.1).into_iter().map(|n| n.distance).collect::<Vec<f64>>();
+            let expected = expected.into_iter().filter(|distance| *distance <= 0.1).collect::<Vec<f64>>();
+            assert_eq!(output, expected);
+        }
+    }
+}
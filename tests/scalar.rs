@@ -0,0 +1,83 @@
+use rand::{Rng, thread_rng};
+
+use find_closest_points::{generate_random_points, NUM_OF_NEAREST_NEIGHBOURS};
+use find_closest_points::kd_tree::KdTree;
+use find_closest_points::point::Point;
+use find_closest_points::scalar::{Exact, Native, Widened};
+
+#[test]
+fn find_10_closest_f32_points_within_10000_points() {
+    let points = generate_random_points(10_000).iter().map(Point::cast).collect::<Vec<Point<f32>>>();
+    let given_point = Point::<f32>::random();
+    let output = KdTree::construct_tree(points.clone()).nearest_with::<Native>(&given_point, NUM_OF_NEAREST_NEIGHBOURS)
+        .into_iter()
+        .map(|n| n.distance)
+        .collect::<Vec<f32>>();
+
+    let mut expected = points.iter().map(|p| given_point.distance_to(p)).collect::<Vec<f32>>();
+    expected.sort_by(f32::total_cmp);
+    expected.truncate(NUM_OF_NEAREST_NEIGHBOURS);
+
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn find_10_closest_f32_points_with_widened_distances() {
+    let points = generate_random_points(10_000).iter().map(Point::cast).collect::<Vec<Point<f32>>>();
+    let given_point = Point::<f32>::random();
+    let output = KdTree::construct_tree(points.clone()).nearest_with::<Widened>(&given_point, NUM_OF_NEAREST_NEIGHBOURS)
+        .into_iter()
+        .map(|n| n.distance)
+        .collect::<Vec<f64>>();
+
+    // widening an f32 is exact, so the distances are those between the same points stored as f64
+    let given_point = given_point.cast::<f64>();
+    let mut expected = points.iter().map(|p| given_point.distance_to(&p.cast())).collect::<Vec<f64>>();
+    expected.sort_by(f64::total_cmp);
+    expected.truncate(NUM_OF_NEAREST_NEIGHBOURS);
+
+    assert_eq!(output, expected);
+}
+
+fn random_integer_points(n: usize, range: std::ops::RangeInclusive<i64>) -> Vec<Point<i64>> {
+    let mut rng = thread_rng();
+    (0..n).map(|_| Point { x: rng.gen_range(range.clone()), y: rng.gen_range(range.clone()), z: rng.gen_range(range.clone()) }).collect()
+}
+
+#[test]
+fn find_10_closest_i32_points_with_exact_distances() {
+    let points = random_integer_points(10_000, i32::MIN as i64..=i32::MAX as i64).iter()
+        .map(|p| Point { x: p.x as i32, y: p.y as i32, z: p.z as i32 })
+        .collect::<Vec<Point<i32>>>();
+    let given_point = Point { x: 0, y: -5, z: 17 };
+    let output = KdTree::construct_tree(points.clone()).nearest_squared_with::<Exact>(&given_point, NUM_OF_NEAREST_NEIGHBOURS)
+        .into_iter()
+        .map(|n| n.distance)
+        .collect::<Vec<u128>>();
+
+    let mut expected = points.iter().map(|p| given_point.squared_distance_with::<Exact>(p)).collect::<Vec<u128>>();
+    expected.sort();
+    expected.truncate(NUM_OF_NEAREST_NEIGHBOURS);
+
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn find_closest_i64_points_beyond_f64_precision() {
+    // offsets of a few units around 2^60 are lost when converted to f64, but not by exact integer distances
+    let base = 1i64 << 60;
+    let points = random_integer_points(1000, -100..=100).into_iter()
+        .map(|p| Point { x: base + p.x, y: base + p.y, z: base + p.z })
+        .collect::<Vec<Point<i64>>>();
+    let given_point = Point { x: base, y: base, z: base };
+    let output = KdTree::construct_tree(points.clone()).nearest_squared_with::<Exact>(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+
+    let mut expected = points.clone();
+    expected.sort_by_key(|p| given_point.squared_distance_with::<Exact>(p));
+    let expected = expected.into_iter().take(NUM_OF_NEAREST_NEIGHBOURS)
+        .map(|p| given_point.squared_distance_with::<Exact>(&p))
+        .collect::<Vec<u128>>();
+
+    assert_eq!(output.iter().map(|n| n.distance).collect::<Vec<u128>>(), expected);
+    assert!(output.iter().all(|n| points.contains(&n.point)));
+}
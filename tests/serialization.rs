@@ -0,0 +1,44 @@
+#![cfg(feature = "serde")]
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+use find_closest_points::{generate_random_points, NUM_OF_NEAREST_NEIGHBOURS};
+use find_closest_points::kd_tree::KdTree;
+use find_closest_points::nearest_neighbour::{Neighbour, NeighbourSearch};
+use find_closest_points::point::Point;
+
+#[test]
+fn loaded_tree_finds_the_same_neighbours_within_1000000_points() {
+    let tree = KdTree::construct_tree(generate_random_points(1_000_000));
+    let path = std::env::temp_dir().join(format!("find_closest_points_{}.kdtree", std::process::id()));
+    tree.save(BufWriter::new(File::create(&path).unwrap())).unwrap();
+    let loaded = KdTree::load(BufReader::new(File::open(&path).unwrap()));
+    std::fs::remove_file(&path).unwrap();
+    let loaded = loaded.unwrap();
+
+    let given_point = Point::random();
+    let output = loaded.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+    let expected = tree.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+    assert_eq!(output.iter().map(|n| &n.point).collect::<Vec<_>>(), expected.iter().map(|n| &n.point).collect::<Vec<_>>());
+    assert_eq!(loaded, tree);
+}
+
+#[test]
+fn save_and_load_integer_tree() {
+    let points = (0..1000).map(|i| Point { x: i * 7 % 1000, y: i * 13 % 1000, z: i }).collect::<Vec<Point<i64>>>();
+    let tree = KdTree::construct_tree(points);
+    let mut bytes = vec![];
+    tree.save(&mut bytes).unwrap();
+    let output = KdTree::<i64>::load(bytes.as_slice()).unwrap();
+    assert_eq!(output, tree);
+}
+
+#[test]
+fn neighbours_round_trip_through_serde() {
+    let neighbour = Neighbour { distance: 0.5, point: Point::new(0.1, 0.2, 0.3) };
+    let bytes = bincode::serialize(&neighbour).unwrap();
+    let output: Neighbour = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(output.distance, neighbour.distance);
+    assert_eq!(output.point, neighbour.point);
+}
@@ -0,0 +1,55 @@
+use rand::{Rng, thread_rng};
+
+use find_closest_points::{generate_random_points, NUM_OF_NEAREST_NEIGHBOURS};
+use find_closest_points::brute_force::BruteForce;
+use find_closest_points::nearest_neighbour::NeighbourSearch;
+use find_closest_points::point::Point;
+use find_closest_points::spatial_hash::{Handle, SpatialHash};
+
+/// Checks the radius and nearest neighbour queries against brute force over the points that are still in the spatial hash.
+fn check_against_brute_force(spatial_hash: &SpatialHash, live: &[(Handle, Point)]) {
+    let brute_force = BruteForce::construct_index(live.iter().map(|(_, point)| point.clone()).collect());
+    for _ in 0..10 {
+        let given_point = Point::random();
+        let output = spatial_hash.within_radius(&given_point, spatial_hash.cell_size());
+        let expected = brute_force.within_radius(&given_point, spatial_hash.cell_size());
+        assert_eq!(output, expected);
+
+        let output = spatial_hash.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+        let expected = brute_force.nearest(&given_point, NUM_OF_NEAREST_NEIGHBOURS);
+        assert_eq!(output, expected);
+    }
+}
+
+#[test]
+fn queries_after_inserting_10000_points() {
+    let points = generate_random_points(10_000);
+    let mut spatial_hash = SpatialHash::new(0.05);
+    let live = points.into_iter().map(|point| (spatial_hash.insert(point.clone()), point)).collect::<Vec<(Handle, Point)>>();
+    check_against_brute_force(&spatial_hash, &live);
+}
+
+#[test]
+fn queries_after_moving_and_removing_points() {
+    let mut rng = thread_rng();
+    let points = generate_random_points(10_000);
+    let mut spatial_hash = SpatialHash::new(0.05);
+    let mut live = points.into_iter().map(|point| (spatial_hash.insert(point.clone()), point)).collect::<Vec<(Handle, Point)>>();
+
+    for _ in 0..5 {
+        // move every point a small step, like a frame of a particle simulation
+        for (handle, point) in live.iter_mut() {
+            *point = Point::new(point.x + rng.gen_range(-0.01..0.01), point.y + rng.gen_range(-0.01..0.01), point.z + rng.gen_range(-0.01..0.01));
+            assert!(spatial_hash.move_to(*handle, point.clone()));
+        }
+        for _ in 0..500 {
+            let (handle, point) = live.swap_remove(rng.gen_range(0..live.len()));
+            assert_eq!(spatial_hash.remove(handle), Some(point));
+        }
+        for point in generate_random_points(250) {
+            live.push((spatial_hash.insert(point.clone()), point));
+        }
+        assert_eq!(spatial_hash.len(), live.len());
+        check_against_brute_force(&spatial_hash, &live);
+    }
+}
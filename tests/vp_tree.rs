@@ -0,0 +1,85 @@
+use proptest::prelude::*;
+
+use find_closest_points::nearest_neighbour::Neighbour;
+use find_closest_points::point::Point;
+use find_closest_points::vp_tree::{Euclidean, Metric, VpTree};
+
+fn brute_force_neighbours<T: Clone>(items: &[T], given_item: &T, metric: &impl Metric<T>) -> Vec<Neighbour<T>> {
+    let mut neighbours = items.iter()
+        .map(|item| Neighbour { distance: metric.distance(given_item, item), point: item.clone() })
+        .collect::<Vec<Neighbour<T>>>();
+    neighbours.sort();
+    neighbours
+}
+
+fn manhattan(a: &Point, b: &Point) -> f64 {
+    (a.x - b.x).abs() + (a.y - b.y).abs() + (a.z - b.z).abs()
+}
+
+fn hamming(a: &u64, b: &u64) -> f64 {
+    (a ^ b).count_ones() as f64
+}
+
+fn point() -> impl Strategy<Value=Point> {
+    (0.0..=1.0, 0.0..=1.0, 0.0..=1.0).prop_map(|(x, y, z)| Point::new(x, y, z))
+}
+
+proptest! {
+    #[test]
+    fn nearest_euclidean_matches_brute_force(points in prop::collection::vec(point(), 0..300), given_point in point(), k in 1usize..20) {
+        let tree = VpTree::construct_tree(points.clone(), Euclidean);
+        let output = tree.nearest(&given_point, k);
+
+        let mut expected = brute_force_neighbours(&points, &given_point, &Euclidean);
+        expected.truncate(k);
+
+        prop_assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn within_radius_euclidean_matches_brute_force(points in prop::collection::vec(point(), 0..300), given_point in point(), radius in 0.0..0.5) {
+        let tree = VpTree::construct_tree(points.clone(), Euclidean);
+        let output = tree.within_radius(&given_point, radius);
+
+        let expected = brute_force_neighbours(&points, &given_point, &Euclidean).into_iter()
+            .filter(|n| n.distance <= radius)
+            .collect::<Vec<Neighbour>>();
+
+        prop_assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn nearest_manhattan_matches_brute_force(points in prop::collection::vec(point(), 0..300), given_point in point(), k in 1usize..20) {
+        let tree = VpTree::construct_tree(points.clone(), manhattan);
+        let output = tree.nearest(&given_point, k);
+
+        let mut expected = brute_force_neighbours(&points, &given_point, &manhattan);
+        expected.truncate(k);
+
+        prop_assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn nearest_hamming_matches_brute_force(items in prop::collection::vec(any::<u64>(), 0..300), given_item in any::<u64>(), k in 1usize..20) {
+        let tree = VpTree::construct_tree(items.clone(), hamming);
+        let output = tree.nearest(&given_item, k);
+
+        let mut expected = brute_force_neighbours(&items, &given_item, &hamming);
+        expected.truncate(k);
+
+        prop_assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn within_radius_hamming_matches_brute_force(items in prop::collection::vec(any::<u64>(), 0..300), given_item in any::<u64>(), radius in 0u32..40) {
+        let radius = radius as f64;
+        let tree = VpTree::construct_tree(items.clone(), hamming);
+        let output = tree.within_radius(&given_item, radius);
+
+        let expected = brute_force_neighbours(&items, &given_item, &hamming).into_iter()
+            .filter(|n| n.distance <= radius)
+            .collect::<Vec<Neighbour<u64>>>();
+
+        prop_assert_eq!(output, expected);
+    }
+}